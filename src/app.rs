@@ -1,6 +1,6 @@
 use std::error::Error;
 use std::sync::{mpsc::Receiver, Arc, Mutex, MutexGuard};
-use std::{env, path::PathBuf, thread, time::Duration};
+use std::{env, path::PathBuf, thread, time::{Duration, Instant}};
 use std::io::BufRead;
 use std::thread::JoinHandle;
 
@@ -14,16 +14,25 @@ use ratatui::{
 use rodio::OutputStream;
 
 use crate::{
+    auto_dj::AutoDj,
     config::Config,
-    player::Player,
-    state::State,
+    cover_art::{CoverArt, CoverSlot},
+    files::{LibraryStore, PlaylistStore},
+    library_scanner::LibraryScanner,
+    player::{PlayEvent, Player},
+    playlist_files::{free_playlist_path, write_playlist_file, PlaylistFileAction, PlaylistFileResolver},
+    state::{Sorting, State},
+    structs::Playlist,
     term::set_terminal,
     ui,
     ui::{CurrentlyPlaying, KeyboardHandler, KeyboardHandlerMut, TopBar},
     Command,
-    components::{FileBrowser, FileBrowserSelection, Library},
+    components::{FileBrowser, FileBrowserSelection, Library, Search, SearchResultSource},
 };
 
+/// How long notices stay in the top bar.
+const NOTICE_DURATION: Duration = Duration::from_secs(5);
+
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub enum FocusedElement {
     Browser,
@@ -35,6 +44,7 @@ pub enum AppTab {
     Library = 0,
     Playlists,
     FileBrowser,
+    Search,
     Help,
 }
 
@@ -46,22 +56,36 @@ pub struct App<'a> {
     player: Arc<Player>,
     player_command_receiver: Arc<Mutex<Receiver<Command>>>,
     media_rec_t: Option<JoinHandle<()>>,
+    auto_dj: AutoDj,
+    library_scanner: Arc<LibraryScanner>,
+    library_store: LibraryStore,
+    /// Whether the library scanner or the player changed the library since it was last saved.
+    library_changed: bool,
+    playlist_store: PlaylistStore,
+    cover_art: Arc<CoverArt>,
+    /// A message for the top bar, such as the songs of a playlist file that weren't found, and when it was given.
+    notice: Arc<Mutex<Option<(String, Instant)>>>,
+    playlist_file_resolver: Arc<PlaylistFileResolver>,
 
     focused_element: FocusedElement,
+    show_remaining_time: bool,
+    show_lyrics: bool,
     target: Option<KeyboardHandler<'a>>,
     active_tab: AppTab,
 
     library: Arc<Library<'a>>,
     playlist: Arc<ui::Playlists<'a>>,
     browser: Arc<Mutex<FileBrowser<'a>>>,
+    search: Arc<Search<'a>>,
     help_tab: Arc<Mutex<ui::HelpTab<'a>>>,
+    lyrics: ui::LyricsPanel,
 }
 
 impl<'a> App<'a> {
     pub fn new(player_command_receiver: Receiver<Command>) -> Self {
         let config = Config::from_file();
         let state = State::from_file();
-        let library_songs = crate::files::Library::from_file();
+        let library_store = LibraryStore::open_or_default();
 
         let (output_stream, output_stream_handle) = OutputStream::try_default().unwrap(); // Indirectly this spawns the cpal_alsa_out thread, and creates the mixer tied to it
 
@@ -72,19 +96,55 @@ impl<'a> App<'a> {
             None => env::current_dir().unwrap(),
         };
 
-        let library = Arc::new(Library::new(config.theme, library_songs.songs));
+        let cover_art = Arc::new(CoverArt::new(config.album_art));
+
+        let library = Arc::new(Library::new(
+            config.theme,
+            cover_art.clone(),
+            library_store.library(),
+        ));
+        library.set_song_sort(state.sorting.library);
         library.on_select({
             let player = player.clone();
-            move |(song, key)| {
+            move |(songs, key)| {
                 if key.code == KeyCode::Enter {
-                    player.play_song(song);
+                    player.play_songs(songs);
                 } else if key.code == KeyCode::Char('a') {
-                    player.enqueue_song(song);
+                    player.enqueue_songs(songs);
                 }
             }
         });
 
-        let playlist = Arc::new(ui::Playlists::new(config.theme, state.playlists));
+        let mut playlist_store = PlaylistStore::in_config_dir();
+        let mut playlists = playlist_store.load(&state.playlist_order);
+
+        // Playlists in state.toml are either from older versions, or ones that couldn't be saved to the playlists
+        // folder last time. Either way, they're newer than the files of the same name.
+        if !state.playlists.is_empty() {
+            log::info!("moving the playlists from state.toml to the playlists folder");
+
+            for state_playlist in state.playlists {
+                match playlists.iter_mut().find(|pl| pl.name == state_playlist.name) {
+                    Some(playlist) => *playlist = state_playlist,
+                    None => playlists.push(state_playlist),
+                }
+            }
+
+            if let Err(err) = playlist_store.save(&playlists) {
+                log::error!("Could not write the playlists folder: {:#?}", err);
+            }
+        }
+        playlist_store.watch();
+
+        let smart_playlists = crate::files::SmartPlaylists::from_file();
+        let playlist = Arc::new(ui::Playlists::new(
+            config.theme,
+            playlists,
+            smart_playlists.smart_playlists,
+            library.clone(),
+        ));
+        playlist.set_sort(state.sorting.playlists);
+        player.queue().set_sort(state.sorting.queue);
         playlist.on_select({
             let player = player.clone();
             move |(song, key)| {
@@ -96,17 +156,63 @@ impl<'a> App<'a> {
             }
         });
 
-        let mut browser = FileBrowser::new(config.theme, current_directory, player.queue());
+        let search = Arc::new(Search::new(config.theme, library.clone(), playlist.clone()));
+        search.on_select({
+            let player = player.clone();
+            let playlists = playlist.clone();
+            move |(song, key)| {
+                match key.code {
+                    KeyCode::Enter => player.play_song(song),
+                    KeyCode::Char('a') => player.enqueue_song(song),
+                    KeyCode::Char('y') => playlists.add_song(song),
+                    _ => {}
+                }
+            }
+        });
+
+        let mut other_songs: Vec<_> = player.queue().songs().iter().cloned().collect();
+        other_songs.extend(playlist.playlists().into_iter().flat_map(|pl| pl.songs));
+        library.check_missing_files(other_songs);
+
+        let library_scanner = Arc::new(LibraryScanner::new(library.file_stamps()));
+
+        if !config.library.folders.is_empty() {
+            let known_files = library.all_songs().into_iter().map(|s| s.path).chain(library.file_stamps().into_keys()).collect();
+            library_scanner.scan_folders(config.library.folders.clone(), known_files);
+
+            if config.library.watch {
+                library_scanner.watch(&config.library.folders);
+            }
+        }
+
+        let notice = Arc::new(Mutex::new(None));
+        let playlist_file_resolver = Arc::new(PlaylistFileResolver::default());
+
+        let mut browser = FileBrowser::new(config.theme, current_directory, player.clone());
         browser.on_select({
             let player = player.clone();
             let playlists = playlist.clone();
             let media_library = Arc::clone(&library);
+            let library_scanner = library_scanner.clone();
+            let playlist_file_resolver = playlist_file_resolver.clone();
 
             move |(s, key_event)| {
-                Self::on_file_browser_key(player.as_ref(), playlists.as_ref(), media_library.as_ref(), s, key_event);
+                Self::on_file_browser_key(
+                    player.as_ref(),
+                    playlists.as_ref(),
+                    media_library.as_ref(),
+                    library_scanner.as_ref(),
+                    playlist_file_resolver.as_ref(),
+                    s,
+                    key_event,
+                );
             }
         });
 
+        let auto_dj = AutoDj::new(config.auto_dj);
+        let help_tab = ui::HelpTab::new(config.clone());
+        let lyrics = ui::LyricsPanel::new(config.theme);
+
         Self {
             must_quit: false,
             config,
@@ -115,15 +221,27 @@ impl<'a> App<'a> {
             player,
             player_command_receiver: Arc::new(Mutex::new(player_command_receiver)),
             media_rec_t: None,
+            auto_dj,
+            library_scanner,
+            library_store,
+            library_changed: false,
+            playlist_store,
+            cover_art,
+            notice,
+            playlist_file_resolver,
 
             focused_element: FocusedElement::Browser,
+            show_remaining_time: false,
+            show_lyrics: false,
             target: Some(KeyboardHandler::Ref(library.clone())),
             active_tab: AppTab::Library,
 
             library,
             playlist,
             browser: Arc::new(Mutex::new(browser)),
-            help_tab: Arc::new(Mutex::new(ui::HelpTab::new(config))),
+            search,
+            help_tab: Arc::new(Mutex::new(help_tab)),
+            lyrics,
         }
     }
 
@@ -133,12 +251,38 @@ impl<'a> App<'a> {
 
     fn to_state(&self) -> State {
         let queue_items = self.player.queue().songs().clone();
-        let playlists = self.playlist.playlists();
+        let playlist_order = self.playlist.playlists().into_iter().map(|pl| pl.name).collect();
 
         State {
             last_visited_path: self.file_browser().current_directory().to_str().map(String::from),
             queue_items: Vec::from(queue_items),
-            playlists,
+            playlists: vec![],
+            playlist_order,
+            sorting: Sorting {
+                library: self.library.song_sort(),
+                playlists: self.playlist.sort(),
+                queue: self.player.queue().sort(),
+            },
+        }
+    }
+
+    /// Takes the user to the library or playlist of the search result they asked to go to, if any.
+    fn jump_to_search_result(&mut self) {
+        let Some(result) = self.search.take_jump_target() else {
+            return;
+        };
+
+        match result.source {
+            SearchResultSource::Library => {
+                self.active_tab = AppTab::Library;
+                self.target = Some(KeyboardHandler::Ref(self.library.clone()));
+                self.library.select_song(&result.song);
+            }
+            SearchResultSource::Playlist(name) => {
+                self.active_tab = AppTab::Playlists;
+                self.target = Some(KeyboardHandler::Ref(self.playlist.clone()));
+                self.playlist.select_song(&name, &result.song);
+            }
         }
     }
 
@@ -153,10 +297,14 @@ impl<'a> App<'a> {
         self.player.spawn();
 
         while !self.must_quit {
-            terminal.draw(|frame| {
+            let frame = terminal.draw(|frame| {
                 frame.render_widget_ref(&*self, frame.size());
             })?;
 
+            if let Err(err) = self.cover_art.flush(&mut std::io::stdout(), frame.buffer) {
+                log::error!("Could not draw album art: {err}");
+            }
+
             let timeout = tick_rate.saturating_sub(last_tick.elapsed());
 
             if event::poll(timeout)? {
@@ -168,21 +316,149 @@ impl<'a> App<'a> {
             if last_tick.elapsed() >= tick_rate {
                 last_tick = std::time::Instant::now();
             }
+
+            self.auto_dj_tick();
+            self.play_events_tick();
+            self.library_scanner_tick();
+            self.missing_files_tick();
+            self.playlists_tick();
+            self.playlist_files_tick();
         }
 
         log::trace!("App.start() -> exiting");
 
-        self.to_state().to_file()?;
+        let mut state = self.to_state();
 
-        let library_songs = self.library.songs();
+        if let Err(err) = self.playlist_store.save(&self.playlist.playlists()) {
+            log::error!("Could not save the playlists, will keep them in state.toml: {:#?}", err);
+            state.playlists = self.playlist.playlists();
+        }
 
-        crate::files::Library::to_file(&crate::files::Library {
-           songs: library_songs,
+        state.to_file()?;
+
+        crate::files::SmartPlaylists {
+            smart_playlists: self.playlist.smart_playlists(),
+        }.to_file()?;
+
+        self.save_library()?;
+
+        Ok(())
+    }
+
+    fn save_library(&mut self) -> Result<(), Box<dyn Error>> {
+        self.library_changed = false;
+
+        self.library_store.save(&crate::files::Library {
+           songs: self.library.tagged_songs(),
+           virtual_folders: self.library.virtual_folders(),
+           overrides: self.library.overrides(),
+           file_stamps: self.library.file_stamps(),
+           stats: self.library.stats(),
+           hidden: self.library.hidden(),
+           removed: self.library.removed(),
         })?;
 
         Ok(())
     }
 
+    /// Applies the changes the library scanner found in the library folders since the last tick, and saves the
+    /// library once the scanner is done, so a crash doesn't cost a rescan.
+    fn library_scanner_tick(&mut self) {
+        let updates = self.library_scanner.updates();
+
+        if !updates.is_empty() {
+            self.library.apply_updates(updates);
+            self.library_changed = true;
+        }
+
+        if self.library_changed && self.library_scanner.progress().is_none() {
+            if let Err(err) = self.save_library() {
+                log::error!("Could not save the library: {:#?}", err);
+            }
+        }
+    }
+
+    /// Shows the missing files found since the last tick and, once they're relinked in the library, points the
+    /// playlists and queue to their new paths too.
+    fn missing_files_tick(&mut self) {
+        self.library.missing_files_tick();
+
+        let new_paths = self.library.take_relinked();
+
+        if !new_paths.is_empty() {
+            self.playlist.relink(&new_paths);
+            self.player.queue().relink(&new_paths);
+            self.library_changed = true;
+        }
+    }
+
+    /// Saves the playlists that changed in the app, and picks up the ones other programs changed in the playlists
+    /// folder, leaving both alone while a playlist is being named.
+    fn playlists_tick(&mut self) {
+        if self.playlist.is_editing() {
+            return;
+        }
+
+        if self.playlist.take_changed() {
+            if let Err(err) = self.playlist_store.save(&self.playlist.playlists()) {
+                log::error!("Could not save the playlists: {:#?}", err);
+            }
+        }
+
+        if let Some(playlists) = self.playlist_store.external_changes(|| self.playlist.playlist_names()) {
+            self.playlist.set_playlists(playlists);
+        }
+    }
+
+    /// Counts the songs the player played and skipped in the library's stats, and saves their stats alone.
+    fn play_events_tick(&mut self) {
+        let mut songs = vec![];
+
+        for event in self.player.take_play_events() {
+            match event {
+                PlayEvent::Played(song) => {
+                    self.library.record_play(&song);
+                    songs.push(song);
+                }
+                PlayEvent::Skipped(song) => {
+                    self.library.record_skip(&song);
+                    songs.push(song);
+                }
+            }
+        }
+
+        if songs.is_empty() {
+            return;
+        }
+
+        if let Err(err) = self.library_store.save_stats(&self.library.song_stats(&songs)) {
+            log::error!("Could not save the stats of the songs played: {:#?}", err);
+        }
+    }
+
+    /// Records the currently playing song in the auto-DJ's history and, if the auto-DJ is on and the queue ran dry,
+    /// picks a song from the library and queues it up.
+    fn auto_dj_tick(&self) {
+        let currently_playing = self.player.currently_playing().lock().unwrap().clone();
+
+        if let Some(ref song) = currently_playing {
+            self.auto_dj.on_song_played(song);
+        }
+
+        // The queue is refilled while its last song plays, so the auto-DJ never starts the music on its own
+        if !self.auto_dj.is_enabled() || currently_playing.is_none() || self.player.queue().length() > 0 {
+            return;
+        }
+
+        let songs = self.library.songs();
+        let stats = self.library.song_stats(&songs);
+
+        if let Some(song) = self.auto_dj.pick(&songs, &stats, currently_playing.as_ref()) {
+            log::debug!("auto-DJ picked {:?}", song.title);
+            self.player.queue().add_auto_dj(song);
+        }
+    }
+
     fn spawn_media_key_receiver_thread(&mut self) {
         let player_command_receiver = self.player_command_receiver.clone();
         let player = self.player.clone();
@@ -216,6 +492,8 @@ impl<'a> App<'a> {
         player: &Player,
         playlists: &ui::Playlists,
         media_library: &Library,
+        library_scanner: &LibraryScanner,
+        playlist_file_resolver: &PlaylistFileResolver,
         file_browser_selection: FileBrowserSelection,
         key_event: KeyEvent,
     ) {
@@ -228,6 +506,9 @@ impl<'a> App<'a> {
             (FileBrowserSelection::CueSheet(cue_sheet), KeyCode::Enter) => {
                 player.enqueue_cue(cue_sheet);
             }
+            (FileBrowserSelection::Playlist(playlist_file), KeyCode::Enter) => {
+                playlist_file_resolver.resolve(playlist_file, PlaylistFileAction::Enqueue, media_library.songs());
+            }
 
             (FileBrowserSelection::Song(song), KeyCode::Char('j')) => {
                 media_library.add_song(song.clone());
@@ -236,8 +517,11 @@ impl<'a> App<'a> {
                 // log::debug!("on_file_browser_key CUE ({:#?})", cue_sheet);
                 media_library.add_cue(cue_sheet);
             }
+            (FileBrowserSelection::Playlist(playlist_file), KeyCode::Char('j')) => {
+                playlist_file_resolver.resolve(playlist_file, PlaylistFileAction::AddToLibrary, media_library.songs());
+            }
             (FileBrowserSelection::Directory(path), KeyCode::Char('j')) => {
-                media_library.add_directory(&path);
+                library_scanner.add_directory(path);
             }
 
             (FileBrowserSelection::Song(song), KeyCode::Char('a')) => {
@@ -246,6 +530,9 @@ impl<'a> App<'a> {
             (FileBrowserSelection::CueSheet(cue_sheet), KeyCode::Char('a')) => {
                 player.enqueue_cue(cue_sheet);
             }
+            (FileBrowserSelection::Playlist(playlist_file), KeyCode::Char('a')) => {
+                playlist_file_resolver.resolve(playlist_file, PlaylistFileAction::Enqueue, media_library.songs());
+            }
             (FileBrowserSelection::Directory(path), KeyCode::Char('a')) => {
                 log::debug!("TODO: file_browser().on_select(Directory({}), a)", path.display());
                 // directory_to_songs_and_folders
@@ -257,6 +544,10 @@ impl<'a> App<'a> {
             (FileBrowserSelection::CueSheet(cue_sheet), KeyCode::Char('y')) => {
                 playlists.add_cue(cue_sheet);
             }
+            // Playlist files are imported as playlists of their own, rather than added to the selected one
+            (FileBrowserSelection::Playlist(playlist_file), KeyCode::Char('y')) => {
+                playlist_file_resolver.resolve(playlist_file, PlaylistFileAction::Import, media_library.songs());
+            }
             (FileBrowserSelection::Directory(path), KeyCode::Char('y')) => {
                 log::debug!("TODO: file_browser().on_select(Directory({}), y)", path.display());
                 // directory_to_songs_and_folders
@@ -265,6 +556,65 @@ impl<'a> App<'a> {
         }
     }
 
+    /// Acts on the playlist files whose songs were found since the last tick, telling in the top bar how many of them
+    /// weren't.
+    fn playlist_files_tick(&mut self) {
+        for (action, resolved) in self.playlist_file_resolver.take_resolved() {
+            if !resolved.unresolved.is_empty() {
+                let message = format!("{} songs of {} weren't found", resolved.unresolved.len(), resolved.name);
+                log::warn!("{message}");
+                *self.notice.lock().unwrap() = Some((message, Instant::now()));
+            }
+
+            match action {
+                PlaylistFileAction::Enqueue => self.player.enqueue_songs(resolved.songs),
+                PlaylistFileAction::AddToLibrary => self.library.add_songs(resolved.songs),
+                // Songs that couldn't be found show up among the missing files, to be relinked
+                PlaylistFileAction::Import => {
+                    if !resolved.unresolved.is_empty() {
+                        self.library.check_missing_files(resolved.songs.clone());
+                    }
+
+                    self.playlist.import(Playlist { name: resolved.name, songs: resolved.songs });
+                }
+            }
+        }
+    }
+
+    /// Writes the selected playlist, or the queue, to a file in the folder open in the file browser, in the format set
+    /// in the config.
+    fn export_playlist(&self, relative: bool) {
+        let playlist = match self.active_tab {
+            AppTab::Playlists => self.playlist.selected_playlist(),
+            _ => Some(Playlist {
+                name: "Queue".to_string(),
+                songs: self.player.queue().songs().iter().cloned().collect(),
+            }),
+        };
+
+        let Some(playlist) = playlist else {
+            return;
+        };
+
+        // Files already in the folder, such as the one the playlist was imported from, are left alone
+        let extension = self.config.playlists.export_format.extension();
+        let folder = self.file_browser().current_directory().clone();
+        let path = free_playlist_path(&folder, &playlist.name, extension, |path| path.exists());
+
+        let message = match write_playlist_file(&path, &playlist.name, &playlist.songs, relative) {
+            Ok(()) => {
+                self.file_browser().refresh();
+                format!("exported to {}", path.file_name().unwrap_or_default().to_string_lossy())
+            }
+            Err(err) => {
+                log::error!("Could not export {:?} to {:?}: {err}", playlist.name, path);
+                format!("could not export {}: {err}", playlist.name)
+            }
+        };
+
+        *self.notice.lock().unwrap() = Some((message, Instant::now()));
+    }
+
     fn spawn_terminal(&self) {
         let cwd = self.file_browser().current_directory().clone();
 
@@ -302,7 +652,13 @@ impl<'a> KeyboardHandlerMut<'a> for App<'a> {
     fn on_key(&mut self, key: KeyEvent) -> bool {
         let mut handled = true;
 
-        let focus_trapped = self.focused_element == FocusedElement::Browser && self.file_browser().filter().is_some();
+        let focus_trapped = match self.active_tab {
+            AppTab::FileBrowser => self.focused_element == FocusedElement::Browser && self.file_browser().filter().is_some(),
+            AppTab::Search => self.search.is_typing(),
+            AppTab::Library => self.library.is_typing(),
+            AppTab::Playlists => self.playlist.is_editing(),
+            _ => false,
+        };
         if !focus_trapped {
             match key.code {
                 KeyCode::Right => self.player.seek_forward(),
@@ -312,6 +668,15 @@ impl<'a> KeyboardHandlerMut<'a> for App<'a> {
                 KeyCode::Char('p') if key.modifiers == KeyModifiers::CONTROL => self.player.toggle(),
                 KeyCode::Char('g') if key.modifiers == KeyModifiers::CONTROL => self.player.stop(),
                 KeyCode::Char('c') if key.modifiers == KeyModifiers::ALT => self.spawn_terminal(),
+                KeyCode::Char('d') if key.modifiers == KeyModifiers::CONTROL => self.auto_dj.toggle(),
+                KeyCode::Char('d') if key.modifiers == KeyModifiers::ALT => self.auto_dj.next_strategy(),
+                KeyCode::Char('x') if key.modifiers == KeyModifiers::CONTROL => self.library_scanner.cancel(),
+                KeyCode::Char('t') if key.modifiers == KeyModifiers::CONTROL => {
+                    self.show_remaining_time = !self.show_remaining_time;
+                }
+                KeyCode::Char('l') if key.modifiers == KeyModifiers::CONTROL => {
+                    self.show_lyrics = !self.show_lyrics;
+                }
                 KeyCode::Char('q') if key.modifiers == KeyModifiers::CONTROL => {
                     self.must_quit = true;
                 }
@@ -328,9 +693,27 @@ impl<'a> KeyboardHandlerMut<'a> for App<'a> {
                     self.target = Some(KeyboardHandler::Mut(self.browser.clone()));
                 }
                 KeyCode::Char('4') => {
+                    self.active_tab = AppTab::Search;
+                    self.target = Some(KeyboardHandler::Ref(self.search.clone()));
+                    self.search.open();
+                }
+                KeyCode::Char('5') => {
                     self.active_tab = AppTab::Help;
                     self.target = Some(KeyboardHandler::Mut(self.help_tab.clone()));
                 }
+                // Sorting the queue takes the stats of its songs from the library
+                KeyCode::Char('o' | 'O') if self.active_tab == AppTab::FileBrowser && self.focused_element == FocusedElement::Queue => {
+                    let queue = self.player.queue();
+                    let sort = if key.code == KeyCode::Char('o') { queue.sort().next(false) } else { queue.sort().reversed() };
+                    queue.sort_by(sort, |songs| self.library.song_stats(songs));
+                }
+                // Ctrl+E writes the paths of the songs relative to the file, Alt+E writes them as they are
+                KeyCode::Char('e')
+                    if matches!(key.modifiers, KeyModifiers::CONTROL | KeyModifiers::ALT)
+                        && matches!(self.active_tab, AppTab::Playlists | AppTab::FileBrowser) =>
+                {
+                    self.export_playlist(key.modifiers == KeyModifiers::CONTROL);
+                }
                 KeyCode::Tab if self.active_tab == AppTab::FileBrowser && self.file_browser().filter().is_none() => {
                     self.focused_element = match self.focused_element {
                         FocusedElement::Browser => FocusedElement::Queue,
@@ -371,6 +754,8 @@ impl<'a> KeyboardHandlerMut<'a> for App<'a> {
             }
         }
 
+        self.jump_to_search_result();
+
         true
     }
 }
@@ -380,12 +765,52 @@ impl<'a> WidgetRef for &App<'a> {
         let block = Block::default().style(Style::default().bg(self.config.theme.background));
         block.render(area, buf);
 
-        let [area_top, _, area_center, area_bottom] =
-            Layout::vertical([Constraint::Length(1), Constraint::Length(1), Constraint::Min(0), Constraint::Length(3)]).areas(area);
+        let current_song = self.player.currently_playing().lock().unwrap().clone();
+
+        // The now playing area grows to fit the cover of the current song, when there is one
+        let has_cover = current_song.as_ref().is_some_and(|song| self.cover_art.has_cover(song));
+        let area_bottom_height = if has_cover { 6 } else { 3 };
+
+        let [area_top, _, area_center, area_bottom] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(area_bottom_height),
+        ])
+            .areas(area);
+
+        let auto_dj_strategy = if self.auto_dj.is_enabled() {
+            Some(self.auto_dj.strategy())
+        } else {
+            None
+        };
+
+        let notice = self
+            .notice
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|(_, given_at)| given_at.elapsed() < NOTICE_DURATION)
+            .map(|(message, _)| message.clone());
 
-        let top_bar = TopBar::new(self.config.theme, self.active_tab);
+        let top_bar = TopBar::new(self.config.theme, self.active_tab, auto_dj_strategy, self.library_scanner.progress(), notice);
         top_bar.render(area_top, buf);
 
+        // The lyrics take the right side of the active tab
+        let area_center = if self.show_lyrics {
+            let [area_tab, _, area_lyrics] = Layout::horizontal([
+                Constraint::Percentage(65),
+                Constraint::Length(2),
+                Constraint::Min(0),
+            ])
+                .areas(area_center);
+
+            self.lyrics.render(current_song.as_ref(), self.player.get_pos(), area_lyrics, buf);
+            area_tab
+        } else {
+            area_center
+        };
+
         match self.active_tab {
             AppTab::Library => {
                 self.library.render_ref(area_center, buf);
@@ -397,6 +822,9 @@ impl<'a> WidgetRef for &App<'a> {
                 let file_browser = self.browser.lock().unwrap();
                 (*file_browser).render_ref(area_center, buf);
             },
+            AppTab::Search => {
+                self.search.render_ref(area_center, buf);
+            },
             AppTab::Help => {
                 self.help_tab.lock().unwrap().render_ref(area_center, buf);
             },
@@ -406,12 +834,25 @@ impl<'a> WidgetRef for &App<'a> {
 
         let currently_playing = CurrentlyPlaying::new(
             self.config.theme,
-            self.player.currently_playing().lock().unwrap().clone(),
+            current_song.clone(),
             self.player.get_pos(),
             queue.total_time(),
             queue.length(),
+            self.show_remaining_time,
         );
         currently_playing.render(area_bottom, buf);
+
+        // The terminal can be too small for the bottom area to fit
+        if let Some(song) = current_song.as_ref().filter(|_| has_cover && area_bottom.height >= 2 && area_bottom.width > 2) {
+            // Left of the song's title, above the progress bar
+            let area_cover = Rect {
+                x: area_bottom.x + 2,
+                width: ((area_bottom.height - 1) * 2).min(area_bottom.width - 2),
+                height: area_bottom.height - 1,
+                ..area_bottom
+            };
+            self.cover_art.render(CoverSlot::NowPlaying, song, area_cover, buf);
+        }
     }
 }
 
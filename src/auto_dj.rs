@@ -0,0 +1,182 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use rand::seq::SliceRandom;
+
+use crate::{
+    config::{AutoDjConfig, AutoDjStrategy},
+    structs::{Song, SongStats},
+};
+
+/// Keeps the music going when the queue runs dry, picking songs from the library.
+pub struct AutoDj {
+    enabled: AtomicBool,
+    strategy: Mutex<AutoDjStrategy>,
+    history_size: usize,
+
+    /// Recently played songs, most recent last.
+    history: Mutex<VecDeque<Song>>,
+}
+
+impl AutoDj {
+    pub fn new(config: AutoDjConfig) -> Self {
+        Self {
+            enabled: AtomicBool::new(config.enabled),
+            strategy: Mutex::new(config.strategy),
+            history_size: config.history_size,
+            history: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn toggle(&self) {
+        self.enabled.fetch_xor(true, Ordering::Relaxed);
+    }
+
+    pub fn strategy(&self) -> AutoDjStrategy {
+        *self.strategy.lock().unwrap()
+    }
+
+    pub fn next_strategy(&self) {
+        let mut strategy = self.strategy.lock().unwrap();
+        *strategy = strategy.next();
+    }
+
+    /// Records the song as played, so it's not picked again for a while.
+    /// Calling this repeatedly with the same song is a no-op.
+    pub fn on_song_played(&self, song: &Song) {
+        let mut history = self.history.lock().unwrap();
+
+        if history.back() == Some(song) {
+            return;
+        }
+
+        history.retain(|s| s != song);
+        history.push_back(song.clone());
+
+        while history.len() > self.history_size {
+            history.pop_front();
+        }
+    }
+
+    /// Picks the next song from the library, according to the current strategy, skipping recently played songs.
+    /// `stats` are the ones of the library songs, in the same order, and `seed` is the song the pick should relate
+    /// to — usually the one currently playing.
+    pub fn pick(&self, library_songs: &[Song], stats: &[SongStats], seed: Option<&Song>) -> Option<Song> {
+        let history = self.history.lock().unwrap();
+        let mut rng = rand::thread_rng();
+
+        let fresh: Vec<&Song> = library_songs.iter().filter(|s| !history.contains(s)).collect();
+
+        let song = match self.strategy() {
+            AutoDjStrategy::Random => fresh.choose(&mut rng).copied(),
+            AutoDjStrategy::SameArtist => {
+                let same_artist: Vec<&Song> = fresh
+                    .iter()
+                    .copied()
+                    .filter(|s| seed.is_some_and(|seed| seed.artist.is_some() && s.artist == seed.artist))
+                    .collect();
+
+                same_artist.choose(&mut rng).or(fresh.choose(&mut rng)).copied()
+            }
+            AutoDjStrategy::SameGenre => {
+                let seed_genres = seed.map(|seed| seed.genres()).unwrap_or_default();
+                let same_genre: Vec<&Song> = fresh
+                    .iter()
+                    .copied()
+                    .filter(|s| s.genres().iter().any(|g| seed_genres.iter().any(|seed_genre| g.eq_ignore_ascii_case(seed_genre))))
+                    .collect();
+
+                same_genre.choose(&mut rng).or(fresh.choose(&mut rng)).copied()
+            }
+            AutoDjStrategy::SameEra => {
+                let seed_decade = seed.and_then(|seed| seed.year).map(|year| year / 10);
+                let same_era: Vec<&Song> = fresh
+                    .iter()
+                    .copied()
+                    .filter(|s| seed_decade.is_some() && s.year.map(|year| year / 10) == seed_decade)
+                    .collect();
+
+                same_era.choose(&mut rng).or(fresh.choose(&mut rng)).copied()
+            }
+            AutoDjStrategy::LeastRecentlyPlayed => {
+                // Songs that haven't been played at all win, then the ones played the longest ago. If every song was
+                // played recently, pick the one of those played the longest ago.
+                let candidates: Vec<(&Song, Option<u64>)> = library_songs
+                    .iter()
+                    .zip(stats)
+                    .filter(|(s, _)| !history.contains(s))
+                    .map(|(s, stats)| (s, stats.last_played))
+                    .collect();
+                let oldest = candidates.iter().map(|(_, last_played)| *last_played).min();
+                let least_recent: Vec<&Song> =
+                    candidates.iter().filter(|(_, last_played)| Some(*last_played) == oldest).map(|(s, _)| *s).collect();
+
+                least_recent.choose(&mut rng).copied().or_else(|| {
+                    history
+                        .iter()
+                        .find(|played| library_songs.contains(played))
+                })
+            }
+        };
+
+        song.cloned().or_else(|| {
+            // Every song in the library was played recently. Better repeat one than stop the music.
+            library_songs.choose(&mut rng).cloned()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn song(title: &str, genre: &str, year: u32) -> Song {
+        Song {
+            path: PathBuf::from(format!("/music/{title}.flac")),
+            title: title.to_string(),
+            genre: Some(genre.to_string()),
+            year: Some(year),
+            ..Default::default()
+        }
+    }
+
+    fn auto_dj(strategy: AutoDjStrategy) -> AutoDj {
+        AutoDj::new(AutoDjConfig { enabled: true, strategy, history_size: 1 })
+    }
+
+    #[test]
+    fn picks_by_strategy() {
+        let songs = vec![song("A", "Jazz", 1959), song("B", "Rock", 1971), song("C", "jazz", 1964), song("D", "Rock", 1955)];
+        let mut stats: Vec<SongStats> = songs.iter().map(SongStats::new).collect();
+
+        let same_genre = auto_dj(AutoDjStrategy::SameGenre);
+        same_genre.on_song_played(&songs[0]);
+        assert_eq!(same_genre.pick(&songs, &stats, Some(&songs[0])).unwrap().title, "C");
+
+        let same_era = auto_dj(AutoDjStrategy::SameEra);
+        same_era.on_song_played(&songs[0]);
+        assert_eq!(same_era.pick(&songs, &stats, Some(&songs[0])).unwrap().title, "D");
+
+        // Never played first, then the one played the longest ago, skipping the one just played
+        for (song_stats, last_played) in stats.iter_mut().zip([Some(100), None, Some(300), Some(200)]) {
+            song_stats.last_played = last_played;
+        }
+
+        let least_recently_played = auto_dj(AutoDjStrategy::LeastRecentlyPlayed);
+        assert_eq!(least_recently_played.pick(&songs, &stats, None).unwrap().title, "B");
+
+        least_recently_played.on_song_played(&songs[1]);
+        assert_eq!(least_recently_played.pick(&songs, &stats, None).unwrap().title, "A");
+    }
+}
@@ -1,5 +1,13 @@
 mod file_browser;
 mod library;
+mod search;
 
-pub use file_browser::{FileBrowser, FileBrowserSelection, directory_to_songs_and_folders};
+pub use file_browser::{
+    FileBrowser,
+    FileBrowserSelection,
+    path_has_cue_extension,
+    path_has_song_extension,
+    path_is_not_hidden,
+};
 pub use library::{Library};
+pub use search::{Search, SearchResultSource};
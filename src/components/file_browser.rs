@@ -4,4 +4,10 @@ pub mod keyboard_handler;
 mod file_browser_selection;
 
 pub use file_browser::*;
-pub use file_browser_selection::{FileBrowserSelection, directory_to_songs_and_folders};
+pub use file_browser_selection::{
+    FileBrowserEntry,
+    FileBrowserSelection,
+    path_has_cue_extension,
+    path_has_song_extension,
+    path_is_not_hidden,
+};
@@ -8,9 +8,11 @@ use crossterm::event::{KeyCode, KeyEvent};
 use crate::{
     structs::Queue,
     config::{Theme},
+    player::Player,
 };
 
 use super::file_browser_selection::{
+    FileBrowserEntry,
     FileBrowserSelection,
     directory_to_songs_and_folders,
 };
@@ -19,11 +21,12 @@ pub struct FileBrowser<'a> {
     on_select_fn: Box<dyn FnMut((FileBrowserSelection, KeyEvent)) + 'a>,
 
     current_directory: PathBuf,
-    pub(super) items: Vec<FileBrowserSelection>,
+    pub(super) items: Vec<FileBrowserEntry>,
     pub(super) selected_index: usize,
     pub(super) filter: Option<String>,
 
     pub(super) queue_items: Arc<Queue>,
+    pub(super) player: Arc<Player>,
 
     pub(super) theme: Theme,
     padding: usize,
@@ -32,7 +35,7 @@ pub struct FileBrowser<'a> {
 }
 
 impl<'a> FileBrowser<'a> {
-    pub fn new(theme: Theme, current_directory: PathBuf, queue_items: Arc<Queue>) -> Self {
+    pub fn new(theme: Theme, current_directory: PathBuf, player: Arc<Player>) -> Self {
         let items = directory_to_songs_and_folders(&current_directory);
 
         Self {
@@ -43,7 +46,8 @@ impl<'a> FileBrowser<'a> {
             selected_index: 0,
             filter: None,
 
-            queue_items,
+            queue_items: player.queue(),
+            player,
 
             theme,
             padding: 6,
@@ -68,16 +72,11 @@ impl<'a> FileBrowser<'a> {
         &self.current_directory
     }
 
-    pub fn selected_item(&self) -> FileBrowserSelection {
-        if self.items.is_empty() {
-            log::error!("self.selected_index -> self.items.is_empty()");
-            FileBrowserSelection::from_path(&self.current_directory).unwrap()
-        } else if self.selected_index >= self.items.len() {
-            log::error!("self.selected_index >= self.items.len()");
-            FileBrowserSelection::from_path(&self.current_directory).unwrap()
-        } else {
-            self.items[self.selected_index].clone()
-            // self.current_directory.join(&self.items[self.selected_index])
+    /// The selected folder or file, or the current directory if it's empty.
+    pub fn selected_entry(&self) -> FileBrowserEntry {
+        match self.items.get(self.selected_index) {
+            Some(entry) => entry.clone(),
+            None => FileBrowserEntry::Directory(self.current_directory.clone()),
         }
     }
 
@@ -86,14 +85,14 @@ impl<'a> FileBrowser<'a> {
     }
 
     pub(super) fn enter_selection(&mut self, key_event: KeyEvent) {
-        let fbs = self.selected_item();
-
-        match fbs {
-            FileBrowserSelection::Directory(path) if key_event.code == KeyCode::Enter => {
+        match self.selected_entry() {
+            FileBrowserEntry::Directory(path) if key_event.code == KeyCode::Enter => {
                 self.navigate_into(path);
             }
-            _ => {
-                (self.on_select_fn)((fbs, key_event));
+            entry => {
+                if let Some(fbs) = entry.read() {
+                    (self.on_select_fn)((fbs, key_event));
+                }
             }
         }
     }
@@ -104,6 +103,12 @@ impl<'a> FileBrowser<'a> {
         self.selected_index = 0;
     }
 
+    /// Lists the files of the current directory again, such as after a file was written to it.
+    pub fn refresh(&mut self) {
+        self.items = directory_to_songs_and_folders(&self.current_directory);
+        self.selected_index = self.selected_index.min(self.items.len().saturating_sub(1));
+    }
+
     pub fn navigate_up(&mut self) {
         let Some(parent) = self.current_directory.as_path().parent().map(|p| p.to_path_buf()) else { return };
         self.items = directory_to_songs_and_folders(&parent);
@@ -224,7 +229,7 @@ impl<'a> FileBrowser<'a> {
         }
 
         if !self
-            .selected_item()
+            .selected_entry()
             .to_path()
             .to_string_lossy()
             .to_lowercase()
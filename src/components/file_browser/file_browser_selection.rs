@@ -1,13 +1,13 @@
 use std::{
     fs,
     fs::DirEntry,
-    path::PathBuf,
-    cmp::Ordering,
+    path::{Path, PathBuf},
 };
 
 use crate::{
     structs::Song,
     cue::CueSheet,
+    playlist_files::{path_has_playlist_extension, PlaylistFile},
 };
 
 const VALID_EXTENSIONS: [&str; 7] = ["mp3", "mp4", "m4a", "wav", "flac", "ogg", "aac"];
@@ -16,115 +16,86 @@ const VALID_EXTENSIONS: [&str; 7] = ["mp3", "mp4", "m4a", "wav", "flac", "ogg",
 pub enum FileBrowserSelection {
     Song(Song),
     CueSheet(CueSheet),
+    Playlist(PlaylistFile),
     Directory(PathBuf),
 }
 
 impl FileBrowserSelection {
-    pub fn from_path(path: &PathBuf) -> Option<Self> {
-        if path.is_dir() {
-            Some(FileBrowserSelection::Directory(path.clone()))
-        } else if path.extension().is_some_and(|e| e == "cue") {
-            CueSheet::from_file(&path).ok().map(FileBrowserSelection::CueSheet)
-        } else {
-            Song::from_file(&path).ok().map(FileBrowserSelection::Song)
-        }
-    }
-
     pub fn to_path(&self) -> PathBuf {
         match self {
             FileBrowserSelection::Song(s) => { s.path.clone() }
             FileBrowserSelection::CueSheet(cs) => { cs.cue_sheet_file_path() }
+            FileBrowserSelection::Playlist(pl) => { pl.path() }
             FileBrowserSelection::Directory(p) => { p.clone() }
         }
     }
 }
 
-impl PartialEq for FileBrowserSelection {
-    fn eq(&self, other: &Self) -> bool {
-        match self {
-            FileBrowserSelection::Directory(path) => {
-                match other {
-                    FileBrowserSelection::Directory(other_path) => path == other_path,
-                    _ => false,
-                }
-            }
-            FileBrowserSelection::CueSheet(cue_sheet) => {
-                match other {
-                    FileBrowserSelection::CueSheet(other_cue_sheet) =>
-                        cue_sheet.cue_sheet_file_path() == other_cue_sheet.cue_sheet_file_path(),
-                    _ => false,
-                }
-            }
-            FileBrowserSelection::Song(song) => {
-                match other {
-                    FileBrowserSelection::Song(other_song) => song.path == other_song.path,
-                    _ => false,
-                }
-            }
-        }
-    }
+/// A folder or file listed in the file browser. Listing a folder only looks at the names of its files, since reading
+/// the tags of every one of them would freeze the app on large folders. The file is read once it's acted on.
+///
+/// Folders come first, then cue sheets, playlists and songs, each by path.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FileBrowserEntry {
+    Directory(PathBuf),
+    CueSheet(PathBuf),
+    Playlist(PathBuf),
+    Song(PathBuf),
 }
 
-impl Eq for FileBrowserSelection {}
-
-impl PartialOrd for FileBrowserSelection {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(match self {
-            FileBrowserSelection::Directory(path) => {
-                // Directories come first
-                match other {
-                    FileBrowserSelection::Directory(other_path) => path.cmp(other_path),
-                    _ => Ordering::Less,
-                }
-            }
-            FileBrowserSelection::CueSheet(cue_sheet) => {
-                // then queue sheets
-                match other {
-                    FileBrowserSelection::Directory(_) => Ordering::Greater,
-                    FileBrowserSelection::CueSheet(other_cue_sheet) =>
-                        cue_sheet.cue_sheet_file_path().cmp(&other_cue_sheet.cue_sheet_file_path()),
-                    FileBrowserSelection::Song(_) => Ordering::Less,
-                }
-            }
-            FileBrowserSelection::Song(song) => {
-                // last, but not least, song_list
-                match other {
-                    FileBrowserSelection::Directory(_) => Ordering::Greater,
-                    FileBrowserSelection::CueSheet(_) => Ordering::Greater,
-                    FileBrowserSelection::Song(other_song) => song.path.cmp(&other_song.path),
-                }
-            }
-        })
+impl FileBrowserEntry {
+    pub fn to_path(&self) -> PathBuf {
+        match self {
+            FileBrowserEntry::Directory(path)
+            | FileBrowserEntry::CueSheet(path)
+            | FileBrowserEntry::Playlist(path)
+            | FileBrowserEntry::Song(path) => path.clone(),
+        }
     }
-}
 
-impl Ord for FileBrowserSelection {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+    /// Reads the file of the entry. Files that can't be read, such as corrupt ones, are logged and skipped.
+    pub fn read(&self) -> Option<FileBrowserSelection> {
+        match self {
+            FileBrowserEntry::Directory(path) => Some(FileBrowserSelection::Directory(path.clone())),
+            FileBrowserEntry::CueSheet(path) => CueSheet::from_file(path)
+                .inspect_err(|err| log::error!("Could not read cue sheet {:?}: {:?}", path, err))
+                .ok()
+                .map(FileBrowserSelection::CueSheet),
+            FileBrowserEntry::Playlist(path) => PlaylistFile::from_file(path)
+                .inspect_err(|err| log::error!("Could not read playlist file {:?}: {:?}", path, err))
+                .ok()
+                .map(FileBrowserSelection::Playlist),
+            FileBrowserEntry::Song(path) => Song::from_file(path)
+                .inspect_err(|err| log::error!("Could not read song {:?}: {:?}", path, err))
+                .ok()
+                .map(FileBrowserSelection::Song),
+        }
     }
 }
 
-fn dir_entry_to_file_browser_selection(entry: &DirEntry) -> Option<FileBrowserSelection>{
+fn dir_entry_to_file_browser_entry(entry: &DirEntry) -> Option<FileBrowserEntry> {
     if dir_entry_is_dir(&entry) {
-        Some(FileBrowserSelection::Directory(entry.path()))
+        Some(FileBrowserEntry::Directory(entry.path()))
     } else if dir_entry_is_song(&entry) {
-        Some(FileBrowserSelection::Song(Song::from_file(&entry.path()).unwrap()))
+        Some(FileBrowserEntry::Song(entry.path()))
     } else if dir_entry_is_cue(&entry) {
-        Some(FileBrowserSelection::CueSheet(CueSheet::from_file(&entry.path()).unwrap()))
+        Some(FileBrowserEntry::CueSheet(entry.path()))
+    } else if dir_entry_is_file(entry) && path_has_playlist_extension(&entry.path()) {
+        Some(FileBrowserEntry::Playlist(entry.path()))
     } else {
         None
     }
 }
 
-pub fn directory_to_songs_and_folders(path: &PathBuf) -> Vec<FileBrowserSelection> {
+pub fn directory_to_songs_and_folders(path: &PathBuf) -> Vec<FileBrowserEntry> {
     let Ok(entries) = path.read_dir() else {
         return vec![];
     };
 
-    let mut items: Vec<FileBrowserSelection> = entries
+    let mut items: Vec<FileBrowserEntry> = entries
         .filter_map(|e| e.ok())
         .filter(|e| path_is_not_hidden(&e.path()))
-        .filter_map(|e| dir_entry_to_file_browser_selection(&e))
+        .filter_map(|e| dir_entry_to_file_browser_entry(&e))
         .collect();
 
     items.sort_unstable();
@@ -157,6 +128,14 @@ pub fn path_is_not_hidden(path: &PathBuf) -> bool {
         .is_some_and(|d| !d.starts_with('.'))
 }
 
+pub fn path_has_song_extension(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()).is_some_and(|e| VALID_EXTENSIONS.contains(&e))
+}
+
+pub fn path_has_cue_extension(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "cue")
+}
+
 pub fn dir_entry_has_song_extension(dir_entry: &DirEntry) -> bool {
     dir_entry
         .path()
@@ -179,3 +158,33 @@ pub fn dir_entry_has_cue_extension(dir_entry: &DirEntry) -> bool {
 pub fn dir_entry_is_cue(dir_entry: &DirEntry) -> bool {
     dir_entry_is_file(dir_entry) && dir_entry_has_cue_extension(dir_entry)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    use super::*;
+
+    #[test]
+    fn lists_folders_without_reading_files() {
+        let folder = std::env::temp_dir().join(format!("jolteon-file-browser-test-{}", std::process::id()));
+        let _ = remove_dir_all(&folder);
+        create_dir_all(folder.join("Album")).unwrap();
+
+        // Not what their names say they are
+        write(folder.join("corrupt.flac"), "not audio").unwrap();
+        write(folder.join("broken.cue"), [0xFF, 0xFE, 0x00]).unwrap();
+        write(folder.join("Mix.m3u"), "").unwrap();
+        write(folder.join("notes.txt"), "").unwrap();
+        write(folder.join(".hidden.flac"), "").unwrap();
+
+        assert_eq!(directory_to_songs_and_folders(&folder), vec![
+            FileBrowserEntry::Directory(folder.join("Album")),
+            FileBrowserEntry::CueSheet(folder.join("broken.cue")),
+            FileBrowserEntry::Playlist(folder.join("Mix.m3u")),
+            FileBrowserEntry::Song(folder.join("corrupt.flac")),
+        ]);
+
+        remove_dir_all(&folder).unwrap();
+    }
+}
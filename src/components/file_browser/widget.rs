@@ -1,5 +1,6 @@
 use std::fmt::{Display, Formatter};
 use std::path::Path;
+use std::time::Duration;
 
 use ratatui::{
     buffer::Buffer,
@@ -25,9 +26,9 @@ use crate::{
     config::{Theme},
 };
 
-use super::{FileBrowser, FileBrowserSelection};
+use super::{FileBrowser, FileBrowserEntry};
 
-impl Display for FileBrowserSelection {
+impl Display for FileBrowserEntry {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let path = self.to_path();
         let file_name = path.file_name().map(|p| p.to_string_lossy());
@@ -36,8 +37,8 @@ impl Display for FileBrowserSelection {
     }
 }
 
-impl From<&FileBrowserSelection> for Text<'_> {
-    fn from(value: &FileBrowserSelection) -> Self {
+impl From<&FileBrowserEntry> for Text<'_> {
+    fn from(value: &FileBrowserEntry) -> Self {
         Text::raw(value.to_string())
     }
 }
@@ -65,7 +66,7 @@ impl<'a> WidgetRef for FileBrowser<'a> {
         let vertical_separator = Block::default().borders(Borders::RIGHT).border_type(BorderType::Double);
         vertical_separator.render_ref(separator_middle, buf);
 
-        let ql = queue_list(&self.theme, &self.queue_items);
+        let ql = queue_list(&self.theme, &self.queue_items, self.player.time_remaining());
         StatefulWidget::render(
             ql,
             area_main_right,
@@ -118,7 +119,7 @@ fn top_bar(theme: &Theme, current_directory: &Path, filter: &Option<String>) ->
     top_bar
 }
 
-fn file_list(theme: &Theme, items: &Vec<FileBrowserSelection>, filter: &Option<String>) -> List<'static> {
+fn file_list(theme: &Theme, items: &Vec<FileBrowserEntry>, filter: &Option<String>) -> List<'static> {
     let browser_items: Vec<ListItem> = items
         .iter()
         // .map(|i| i.to_path().to_string_lossy().to_string())
@@ -145,8 +146,25 @@ fn file_list(theme: &Theme, items: &Vec<FileBrowserSelection>, filter: &Option<S
     browser_list
 }
 
-fn queue_list<'a>(theme: &Theme, queue_items: &Queue) -> List<'a> {
-    let queue_items: Vec<String> = queue_items.songs().iter().map(ui::song_to_string).collect();
+/// `first_song_start` is how long until the first song in the queue starts playing —
+/// that is, how much is left of the song currently playing.
+fn queue_list<'a>(theme: &Theme, queue_items: &Queue, first_song_start: Duration) -> List<'a> {
+    let mut song_start = first_song_start;
+
+    let queue_items: Vec<String> = queue_items
+        .songs()
+        .iter()
+        .map(|song| {
+            let start_time = ui::wall_clock_after(song_start);
+            song_start += song.length;
+
+            if queue_items.is_auto_dj(song) {
+                format!("{start_time}  {} [auto-DJ]", ui::song_to_string(song))
+            } else {
+                format!("{start_time}  {}", ui::song_to_string(song))
+            }
+        })
+        .collect();
 
     let queue_list = List::new(queue_items)
         .style(Style::default().fg(theme.foreground))
@@ -3,5 +3,7 @@ pub mod widget;
 pub mod keyboard_handler;
 
 mod song_list;
+mod metadata_edit;
+mod duplicate_review;
 
 pub use library::*;
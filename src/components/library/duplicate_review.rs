@@ -0,0 +1,123 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use crate::{
+    fingerprint::Fingerprint,
+    structs::{Song, SongId},
+};
+
+/// A row of the duplicates review: the header of a group of copies of the same song, or one of the copies.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum DuplicateRow {
+    Group(usize),
+    Song(usize, usize),
+}
+
+/// The songs that look like copies of each other, shown instead of the song list when the duplicates section of the
+/// library is selected, so they can be compared and the unwanted copies hidden or removed from the library.
+#[derive(Default)]
+pub(super) struct DuplicateReview {
+    pub groups: Vec<Vec<Song>>,
+    pub rows: Vec<DuplicateRow>,
+    pub selected_index: usize,
+    pub offset: usize,
+    /// How much the audio of each copy sounds like the first one of its group, for the groups compared so far, or
+    /// `None` while it's being compared.
+    pub similarities: Arc<Mutex<HashMap<SongId, Option<f32>>>>,
+    /// The revision of the library the groups were found in, so they're only looked for again once it changed.
+    pub revision: Option<usize>,
+}
+
+impl DuplicateReview {
+    pub fn set_groups(&mut self, groups: Vec<Vec<Song>>) {
+        self.rows = groups
+            .iter()
+            .enumerate()
+            .flat_map(|(g, group)| [DuplicateRow::Group(g)].into_iter().chain((0..group.len()).map(move |s| DuplicateRow::Song(g, s))))
+            .collect();
+        self.groups = groups;
+        self.selected_index = self.selected_index.min(self.rows.len().saturating_sub(1));
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected_index = (self.selected_index + 1).min(self.rows.len().saturating_sub(1));
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected_index = self.selected_index.saturating_sub(1);
+    }
+
+    pub fn select_first(&mut self) {
+        self.selected_index = 0;
+        self.offset = 0;
+    }
+
+    pub fn select_last(&mut self) {
+        self.selected_index = self.rows.len().saturating_sub(1);
+    }
+
+    fn selected_row(&self) -> Option<DuplicateRow> {
+        self.rows.get(self.selected_index).copied()
+    }
+
+    /// The selected copy, if a copy rather than the header of a group is selected.
+    pub fn selected_song(&self) -> Option<Song> {
+        match self.selected_row()? {
+            DuplicateRow::Song(g, s) => self.groups[g].get(s).cloned(),
+            DuplicateRow::Group(_) => None,
+        }
+    }
+
+    /// All the copies in the group of the selected row.
+    pub fn selected_group(&self) -> Vec<Song> {
+        match self.selected_row() {
+            Some(DuplicateRow::Group(g) | DuplicateRow::Song(g, _)) => self.groups[g].clone(),
+            None => vec![],
+        }
+    }
+
+    /// Fingerprints the audio of each copy in the selected group and compares it with the first one, in a thread of
+    /// its own, since it decodes every copy.
+    pub fn compare_selected_group(&self) {
+        let group = self.selected_group();
+        let ids: Vec<SongId> = group.iter().map(SongId::of).collect();
+        let mut similarities = self.similarities.lock().unwrap();
+
+        // Already being compared
+        if group.is_empty() || ids.iter().any(|id| similarities.get(id).is_some_and(|s| s.is_none())) {
+            return;
+        }
+
+        for id in &ids {
+            similarities.insert(id.clone(), None);
+        }
+
+        drop(similarities);
+
+        let similarities = self.similarities.clone();
+        let compared_ids = ids.clone();
+
+        let spawned = thread::Builder::new().name("duplicates".to_string()).spawn(move || {
+            let Some(first) = Fingerprint::from_song(&group[0]) else {
+                log::warn!("Could not fingerprint {:?}", group[0].path);
+                similarities.lock().unwrap().retain(|id, _| !compared_ids.contains(id));
+                return;
+            };
+
+            similarities.lock().unwrap().insert(SongId::of(&group[0]), Some(1.));
+
+            for song in &group[1..] {
+                let similarity = Fingerprint::from_song(song).map(|f| first.similarity(&f)).unwrap_or(0.);
+                similarities.lock().unwrap().insert(SongId::of(song), Some(similarity));
+            }
+        });
+
+        if let Err(err) = spawned {
+            log::error!("Could not spawn the duplicates thread: {err}");
+            self.similarities.lock().unwrap().retain(|id, _| !ids.contains(id));
+        }
+    }
+}
@@ -1,15 +1,35 @@
 use std::sync::atomic::Ordering;
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
 use crate::{
+    structs::{Song, VirtualFolderAlbum},
     ui::KeyboardHandlerRef,
 };
 
-use super::library::{Library, LibraryScreenElement};
+use super::{
+    library::{ArtistListItem, Library, LibraryScreenElement, LibraryView},
+    metadata_edit::MetadataEditTarget,
+    song_list::SongListRow,
+};
 
 impl<'a> KeyboardHandlerRef<'a> for Library<'a> {
 
     fn on_key(&self, key: KeyEvent) -> bool {
+        if self.folder_rename.lock().unwrap().is_some() {
+            self.on_key_event_renaming(key);
+            return true;
+        }
+
+        if self.metadata_edit.lock().unwrap().is_some() {
+            self.on_key_event_metadata_edit(key);
+            return true;
+        }
+
+        if self.relink_root.lock().unwrap().is_some() {
+            self.on_key_event_relink_root(key);
+            return true;
+        }
+
         let mut focused_element_guard = self.focused_element.lock().unwrap();
 
         match key.code {
@@ -20,8 +40,88 @@ impl<'a> KeyboardHandlerRef<'a> for Library<'a> {
                 };
             }
             _ if *focused_element_guard == LibraryScreenElement::ArtistList  => {
+                drop(focused_element_guard);
                 self.on_key_event_artist_list(key);
             },
+            _ if *focused_element_guard == LibraryScreenElement::SongList && self.selected_item() == Some(ArtistListItem::Duplicates) => {
+                drop(focused_element_guard);
+                self.on_key_event_duplicates(key);
+            },
+            KeyCode::Char('r' | 'R') if key.modifiers != KeyModifiers::CONTROL && self.selected_item() == Some(ArtistListItem::MissingFiles) => {
+                drop(focused_element_guard);
+
+                if key.code == KeyCode::Char('r') {
+                    self.start_relink();
+                } else {
+                    self.relink_missing_files();
+                }
+            },
+            KeyCode::Char('m') if *focused_element_guard == LibraryScreenElement::SongList => {
+                drop(focused_element_guard);
+                self.move_selected_album_to_next_folder();
+            },
+            KeyCode::Char('e') if *focused_element_guard == LibraryScreenElement::SongList => {
+                drop(focused_element_guard);
+                let song_list = self.song_list.lock().unwrap();
+                let is_album = matches!(song_list.selected_row(), Some(SongListRow::Album { .. }));
+                let song = song_list.selected_song();
+                drop(song_list);
+
+                match song {
+                    Some(song) if is_album => {
+                        if let Some(target) = album_edit_target(song) {
+                            self.start_metadata_edit(target);
+                        }
+                    }
+                    Some(song) => {
+                        self.start_metadata_edit(MetadataEditTarget::Song(Box::new(song)));
+                    }
+                    None => {}
+                }
+            },
+            KeyCode::Char('g') if *focused_element_guard == LibraryScreenElement::SongList => {
+                drop(focused_element_guard);
+                let songs = self.song_list.lock().unwrap().selected_songs();
+                self.accept_path_guesses(songs);
+            },
+            KeyCode::Char('f') if *focused_element_guard == LibraryScreenElement::SongList => {
+                drop(focused_element_guard);
+                let songs = self.song_list.lock().unwrap().selected_songs();
+                self.toggle_favorite(&songs);
+            },
+            KeyCode::Char('E') if *focused_element_guard == LibraryScreenElement::SongList => {
+                drop(focused_element_guard);
+                let song = self.song_list.lock().unwrap().selected_song();
+                if let Some(target) = song.and_then(album_edit_target) {
+                    self.start_metadata_edit(target);
+                }
+            },
+            KeyCode::Char('u') | KeyCode::Delete if *focused_element_guard == LibraryScreenElement::SongList && self.selected_item() == Some(ArtistListItem::RecentlyRemoved) => {
+                drop(focused_element_guard);
+                let songs = self.song_list.lock().unwrap().selected_songs();
+
+                if key.code == KeyCode::Char('u') {
+                    self.restore_songs(&songs);
+                } else {
+                    self.forget_removed(&songs);
+                }
+            },
+            KeyCode::Char('h') | KeyCode::Delete if *focused_element_guard == LibraryScreenElement::SongList => {
+                drop(focused_element_guard);
+
+                // Hidden and removed songs are restored from the recently removed songs instead
+                if self.selected_item() == Some(ArtistListItem::RecentlyRemoved) {
+                    return true;
+                }
+
+                let songs = self.song_list.lock().unwrap().selected_songs();
+
+                if key.code == KeyCode::Char('h') {
+                    self.hide_songs(&songs);
+                } else {
+                    self.remove_from_library(&songs);
+                }
+            },
             _ if *focused_element_guard == LibraryScreenElement::SongList  => {
                 self.song_list.lock().unwrap().on_key(key);
             },
@@ -38,46 +138,270 @@ impl<'a> KeyboardHandlerRef<'a> for Library<'a> {
 impl<'a> Library<'a> {
 
     fn on_key_event_artist_list(&self, key: KeyEvent) {
-        let mut artists = self.artists.lock().unwrap();
-        let len = artists.len();
+        let len = self.tree.lock().unwrap().len();
 
         match key.code {
             KeyCode::Up => {
                 let _ = self.selected_artist_index.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |a| { Some(a.saturating_sub(1)) });
-                self.selected_song_index.store(0, Ordering::SeqCst);
-                self.offset.store(0, Ordering::SeqCst);
+                self.song_list.lock().unwrap().select_first();
             },
             KeyCode::Down => {
                 let _ = self.selected_artist_index.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |a| { Some(a.saturating_add(1).min(len.saturating_sub(1))) });
-                self.selected_song_index.store(0, Ordering::SeqCst);
-                self.offset.store(0, Ordering::SeqCst);
+                self.song_list.lock().unwrap().select_first();
             },
             KeyCode::Home => {
                 self.selected_artist_index.store(0, Ordering::SeqCst);
-                self.selected_song_index.store(0, Ordering::SeqCst);
-                self.offset.store(0, Ordering::SeqCst);
+                self.song_list.lock().unwrap().select_first();
             },
             KeyCode::End => {
                 self.selected_artist_index.store(len.saturating_sub(1), Ordering::SeqCst);
-                self.selected_song_index.store(0, Ordering::SeqCst);
-                self.offset.store(0, Ordering::SeqCst);
+                self.song_list.lock().unwrap().select_first();
             },
-            KeyCode::Delete => {
-                let removed_artist = artists.remove(self.selected_artist_index.load(Ordering::SeqCst));
-                let mut songs = self.songs.lock().unwrap();
-                songs.remove(removed_artist.as_str());
-                self.offset.store(0, Ordering::SeqCst);
-                let _ = self.selected_artist_index.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |a| { Some(a.saturating_sub(1).min(len.saturating_sub(1))) });
+            KeyCode::Enter | KeyCode::Char('a') => {
+                match self.selected_item() {
+                    Some(ArtistListItem::Folder(name)) if key.code == KeyCode::Enter => {
+                        self.toggle_folder(&name);
+                    }
+                    Some(item) => {
+                        let songs = self.item_songs(&item);
+
+                        if !songs.is_empty() {
+                            self.on_select_fn.lock().unwrap()((songs, key));
+                        }
+                    }
+                    None => {}
+                }
+            },
+            KeyCode::Char('v') => {
+                self.next_view();
+            },
+            // Folders are only shown in the artists view
+            KeyCode::Char('n') if key.modifiers == KeyModifiers::CONTROL && *self.view.lock().unwrap() == LibraryView::Artists => {
+                self.create_virtual_folder();
+                self.start_folder_rename();
+            },
+            KeyCode::Char('r') if key.modifiers == KeyModifiers::CONTROL => {
+                self.start_folder_rename();
+            },
+            KeyCode::Char('m') => {
+                if let Some(item) = self.selected_item() {
+                    self.move_to_next_folder(item);
+                }
+            },
+            KeyCode::Char('g') => {
+                if let Some(item @ (ArtistListItem::UnknownArtist | ArtistListItem::LibraryHealth)) = self.selected_item() {
+                    self.accept_path_guesses(self.item_songs(&item));
+                }
+            },
+            KeyCode::Char('e') => {
+                match self.selected_item() {
+                    Some(ArtistListItem::Artist(artist)) => {
+                        self.start_metadata_edit(MetadataEditTarget::Artist(artist));
+                    }
+                    Some(ArtistListItem::Album(album)) => {
+                        let year = self.item_songs(&ArtistListItem::Album(album.clone())).iter().find_map(|s| s.year);
+                        self.start_metadata_edit(MetadataEditTarget::Album {
+                            artist: album.artist,
+                            album: album.album,
+                            year,
+                        });
+                    }
+                    _ => {}
+                }
+            },
+            KeyCode::Char('h') | KeyCode::Delete => {
+                let songs = match self.selected_item() {
+                    Some(ArtistListItem::Folder(name)) if key.code == KeyCode::Delete => {
+                        self.delete_folder(&name);
+                        vec![]
+                    }
+                    Some(ArtistListItem::Artist(artist)) => self.artist_songs(&artist),
+                    Some(item @ ArtistListItem::Album(_)) => self.item_songs(&item),
+                    _ => vec![],
+                };
+
+                if key.code == KeyCode::Char('h') {
+                    self.hide_songs(&songs);
+                } else if !songs.is_empty() {
+                    self.remove_from_library(&songs);
+                }
             },
+            KeyCode::Char('u') => {
+                if let Some(item @ ArtistListItem::RecentlyRemoved) = self.selected_item() {
+                    self.restore_songs(&self.item_songs(&item));
+                }
+            },
+            _ => {},
+        }
+
+        self.refresh_song_list();
+    }
+
+    fn on_key_event_duplicates(&self, key: KeyEvent) {
+        let mut review = self.duplicate_review.lock().unwrap();
+
+        match key.code {
+            KeyCode::Up => review.select_previous(),
+            KeyCode::Down => review.select_next(),
+            KeyCode::Home => review.select_first(),
+            KeyCode::End => review.select_last(),
+            KeyCode::Char('c') => review.compare_selected_group(),
+            KeyCode::Enter | KeyCode::Char('a') => {
+                let songs = review.selected_song().map(|s| vec![s]).unwrap_or_else(|| review.selected_group());
+                drop(review);
+
+                if !songs.is_empty() {
+                    self.on_select_fn.lock().unwrap()((songs, key));
+                }
+            }
+            KeyCode::Char('h') => {
+                let Some(song) = review.selected_song() else {
+                    return;
+                };
+                drop(review);
+
+                if self.is_hidden(&song) {
+                    self.unhide_songs(&[song]);
+                } else {
+                    self.hide_songs(&[song]);
+                }
+            }
+            // Keeps the selected copy, hiding the rest of its group
+            KeyCode::Char('k') => {
+                let Some(song) = review.selected_song() else {
+                    return;
+                };
+                let others: Vec<Song> = review.selected_group().into_iter().filter(|s| *s != song).collect();
+                drop(review);
+
+                self.unhide_songs(&[song]);
+                self.hide_songs(&others);
+            }
+            KeyCode::Delete => {
+                let Some(song) = review.selected_song() else {
+                    return;
+                };
+                drop(review);
+
+                self.remove_from_library(&[song]);
+            }
+            _ => {}
+        }
+    }
+
+    fn on_key_event_renaming(&self, key: KeyEvent) {
+        let mut folder_rename = self.folder_rename.lock().unwrap();
+        let Some(name) = folder_rename.as_mut() else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Char(char) => {
+                if name.len() < 60 {
+                    name.push(char);
+                }
+            }
+            KeyCode::Backspace => {
+                if key.modifiers == KeyModifiers::ALT {
+                    name.clear();
+                } else {
+                    name.pop();
+                }
+            }
+            KeyCode::Enter => {
+                drop(folder_rename);
+                self.finish_folder_rename();
+            }
+            KeyCode::Esc => {
+                *folder_rename = None;
+            }
+            _ => {},
+        }
+    }
+
+    fn on_key_event_relink_root(&self, key: KeyEvent) {
+        let mut relink_root = self.relink_root.lock().unwrap();
+        let Some(root) = relink_root.as_mut() else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Char(char) => {
+                root.push(char);
+            }
+            KeyCode::Backspace => {
+                if key.modifiers == KeyModifiers::ALT {
+                    root.clear();
+                } else {
+                    root.pop();
+                }
+            }
+            KeyCode::Esc => {
+                *relink_root = None;
+            }
+            KeyCode::Enter => {
+                drop(relink_root);
+                self.search_missing_files();
+            }
+            _ => {},
+        }
+    }
+
+    fn on_key_event_metadata_edit(&self, key: KeyEvent) {
+        let mut metadata_edit = self.metadata_edit.lock().unwrap();
+        let Some(edit) = metadata_edit.as_mut() else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Char(char) => {
+                let value = edit.value_mut();
+                if value.len() < 200 {
+                    value.push(char);
+                }
+            }
+            KeyCode::Backspace => {
+                if key.modifiers == KeyModifiers::ALT {
+                    edit.value_mut().clear();
+                } else {
+                    edit.value_mut().pop();
+                }
+            }
+            KeyCode::Tab | KeyCode::Down => {
+                edit.next_field();
+            }
+            KeyCode::BackTab | KeyCode::Up => {
+                edit.previous_field();
+            }
+            KeyCode::Esc => {
+                *metadata_edit = None;
+            }
+            KeyCode::Enter => {
+                drop(metadata_edit);
+                self.save_metadata_edit();
+            }
             _ => {},
         }
+    }
+
+    fn move_selected_album_to_next_folder(&self) {
+        let Some(song) = self.song_list.lock().unwrap().selected_song() else {
+            return;
+        };
 
+        let (Some(artist), Some(album)) = (song.library_artist(), song.album) else {
+            return;
+        };
 
-        let artist = artists[self.selected_artist_index.load(Ordering::SeqCst)].as_str();
-        let songs = self.songs.lock().unwrap();
-        let artist_songs = songs.get(artist).unwrap();
-        let artist_songs = artist_songs.iter().map(|s| s.clone()).collect();
-        self.song_list.lock().unwrap().set_songs(artist_songs);
+        self.move_to_next_folder(ArtistListItem::Album(VirtualFolderAlbum { artist, album }));
     }
 
 }
+
+fn album_edit_target(song: Song) -> Option<MetadataEditTarget> {
+    Some(MetadataEditTarget::Album {
+        artist: song.library_artist()?,
+        album: song.album?,
+        year: song.year,
+    })
+}
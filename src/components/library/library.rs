@@ -1,6 +1,6 @@
 use std::{
-    cmp::Ordering,
-    collections::{HashMap, HashSet},
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, HashMap, HashSet},
     rc::Rc,
     sync::{
         atomic::{AtomicUsize, Ordering as AtomicOrdering},
@@ -8,17 +8,47 @@ use std::{
         Arc,
     },
     path::PathBuf,
+    time::Duration,
 };
 
 use crossterm::event::KeyEvent;
 
 use crate::{
-    structs::{Song},
+    structs::{
+        compare_artists,
+        find_duplicates,
+        guess_from_path,
+        ArtistNames,
+        tag_issues,
+        FileStamp,
+        JoltFiles,
+        MetadataOverride,
+        MetadataOverrides,
+        Song,
+        SmartCandidate,
+        SmartPlaylist,
+        SongId,
+        SongSort,
+        SongStats,
+        RemovedSong,
+        VirtualFolder,
+        VirtualFolderAlbum,
+        time_ago,
+        unix_now,
+    },
     config::Theme,
+    cover_art::CoverArt,
     cue::CueSheet,
+    files,
+    library_scanner::LibraryUpdate,
+    missing_files::MissingFiles,
     ui::KeyboardHandlerRef,
 };
-use super::song_list::SongList;
+use super::{
+    duplicate_review::DuplicateReview,
+    metadata_edit::{MetadataEdit, MetadataEditTarget},
+    song_list::SongList,
+};
 
 #[derive(Eq, PartialEq)]
 pub(super) enum LibraryScreenElement {
@@ -26,62 +56,212 @@ pub(super) enum LibraryScreenElement {
     SongList,
 }
 
+/// What the left side of the library lists: artists (along with virtual folders and the special sections),
+/// genres, or years.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum LibraryView {
+    Artists,
+    Genres,
+    Years,
+}
+
+impl LibraryView {
+    pub fn next(&self) -> Self {
+        match self {
+            LibraryView::Artists => LibraryView::Genres,
+            LibraryView::Genres => LibraryView::Years,
+            LibraryView::Years => LibraryView::Artists,
+        }
+    }
+}
+
+/// An item of the tree shown in the left side of the library: virtual folders, and the artists and albums in them,
+/// followed by compilations, the artists that aren't in any folder, songs without an artist, the library health view,
+/// the duplicates review, the songs whose files are missing and the songs hidden or removed recently.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(super) enum ArtistListItem {
+    Folder(String),
+    Artist(String),
+    Album(VirtualFolderAlbum),
+    Compilations,
+    UnknownArtist,
+    /// Songs with missing or suspicious tags.
+    LibraryHealth,
+    /// Songs that look like copies of each other.
+    Duplicates,
+    /// Songs of the library, playlists and queue whose files no longer exist.
+    MissingFiles,
+    /// Songs hidden or removed from the library, to be restored.
+    RecentlyRemoved,
+    Genre(String),
+    Year(u32),
+}
+
+/// Songs without an artist are filed under this name.
+const UNKNOWN_ARTIST: &str = "Unknown Artist";
+
+/// Sorts songs of the same artist by album, and then by disc and track number.
+fn compare_songs(a: &Song, b: &Song) -> Ordering {
+    match (&a.album, &b.album) {
+        (Some(album_a), Some(album_b)) if album_a == album_b => {
+            let disc_a = a.disc.unwrap_or(1);
+            let disc_b = b.disc.unwrap_or(1);
+
+            if disc_a != disc_b {
+                return disc_a.cmp(&disc_b);
+            }
+
+            match (&a.track, &b.track) {
+                (Some(a), Some(b)) => a.cmp(b),
+                (Some(_), None) => Ordering::Greater,
+                (None, Some(_)) => Ordering::Less,
+                _ => a.title.cmp(&b.title),
+            }
+        },
+        (Some(album_a), Some(album_b)) if album_a != album_b => {
+            album_a.cmp(album_b)
+        },
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        _ => a.title.cmp(&b.title)
+    }
+}
+
+/// Sorts songs by the artist they're filed under, and then as [`compare_songs`] does.
+fn sorted_by_artist<'s>(songs: impl Iterator<Item = (&'s String, &'s Song)>) -> Vec<Song> {
+    let mut songs: Vec<(&String, &Song)> = songs.collect();
+    songs.sort_by(|(artist_a, a), (artist_b, b)| compare_artists(artist_a, artist_b).then_with(|| compare_songs(a, b)));
+    songs.into_iter().map(|(_, s)| s.clone()).collect()
+}
+
 pub struct Library<'a> {
     pub(super) theme: Theme,
+    pub(super) cover_art: Arc<CoverArt>,
 
     pub(super) artists: Arc<Mutex<Vec<String>>>,
     pub(super) songs: Mutex<HashMap<String, Vec<Song>>>,
+    /// The name each artist is shown with. Spellings that only differ in case, accents or a leading "The" are merged
+    /// into one artist, shown with the spelling most of its songs are tagged with.
+    pub(super) artist_names: Mutex<ArtistNames>,
     pub(super) song_list: Mutex<SongList<'a>>,
 
+    pub(super) virtual_folders: Mutex<Vec<VirtualFolder>>,
+    pub(super) collapsed_folders: Mutex<HashSet<String>>,
+    /// The flattened tree of folders, artists and albums, as rendered. Items inside a folder have a depth of 1.
+    pub(super) tree: Mutex<Vec<(usize, ArtistListItem)>>,
+    /// The new name of the selected folder, while it's being typed.
+    pub(super) folder_rename: Mutex<Option<String>>,
+
+    /// The songs as read from their files, hidden ones included. The `.jolt` files and overrides are only applied to
+    /// the songs shown, so editing or deleting them undoes them, and these are the ones saved.
+    pub(super) tagged_songs: Mutex<HashMap<SongId, Song>>,
+    pub(super) jolt_files: Mutex<JoltFiles>,
+    pub(super) overrides: Mutex<MetadataOverrides>,
+    pub(super) metadata_edit: Mutex<Option<MetadataEdit>>,
+
+    pub(super) file_stamps: Mutex<BTreeMap<PathBuf, FileStamp>>,
+    /// Play counts, skips and favorites, by path and start time of the song.
+    pub(super) stats: Mutex<HashMap<(PathBuf, Duration), SongStats>>,
+    /// Songs kept in the library file, but left out of the library, such as unwanted duplicates.
+    pub(super) hidden: Mutex<HashSet<SongId>>,
+    pub(super) hidden_songs: Mutex<Vec<Song>>,
+    /// Songs removed from the library, along with when, so they can be restored for a while.
+    pub(super) removed: Mutex<Vec<RemovedSong>>,
+    pub(super) duplicate_review: Mutex<DuplicateReview>,
+    pub(super) missing_files: MissingFiles,
+    /// The folder to search for missing files in, while it's being typed.
+    pub(super) relink_root: Mutex<Option<String>>,
+    /// The old and new paths of the files relinked in the library, for the playlists and queue to follow.
+    pub(super) relinked: Mutex<BTreeMap<PathBuf, PathBuf>>,
+    /// Bumped whenever songs, their stats or which of them are missing change, so views derived from them know when to
+    /// refresh.
+    pub(super) revision: AtomicUsize,
+
     pub(super) focused_element: Mutex<LibraryScreenElement>,
+    pub(super) view: Mutex<LibraryView>,
 
     pub(super) selected_artist_index: AtomicUsize,
-    pub(super) selected_song_index: AtomicUsize,
 
-    pub(super) on_select_fn: Rc<Mutex<Box<dyn FnMut((Song, KeyEvent)) + 'a>>>,
+    pub(super) on_select_fn: Rc<Mutex<Box<dyn FnMut((Vec<Song>, KeyEvent)) + 'a>>>,
 
     pub(super) offset: AtomicUsize,
     pub(super) height: AtomicUsize,
 }
 
 impl<'a> Library<'a> {
-    pub fn new(theme: Theme, songs: Vec<Song>) -> Self {
-        let on_select_fn: Rc<Mutex<Box<dyn FnMut((Song, KeyEvent)) + 'a>>> = Rc::new(Mutex::new(Box::new(|_| {}) as _));
+    /// Creates the library component out of the library as stored.
+    pub fn new(theme: Theme, cover_art: Arc<CoverArt>, library: files::Library) -> Self {
+        let on_select_fn: Rc<Mutex<Box<dyn FnMut((Vec<Song>, KeyEvent)) + 'a>>> = Rc::new(Mutex::new(Box::new(|_| {}) as _));
 
         let songs_el = SongList::new(theme);
         songs_el.on_select({
             let on_select_fn = on_select_fn.clone();
-            move |(song, key)| {
-                log::debug!("song selected {:?}", song);
+            move |(songs, key)| {
+                log::debug!("songs selected {:?}", songs);
                 let mut on_select_fn = on_select_fn.lock().unwrap();
 
-                on_select_fn((song, key));
+                on_select_fn((songs, key));
             }
         });
 
+        // Removed songs are forgotten for good once they've been removed for long enough
+        let now = unix_now();
+        let (expired, removed): (Vec<RemovedSong>, Vec<RemovedSong>) =
+            library.removed.into_iter().partition(|r| r.is_expired(now));
+        let expired: HashSet<SongId> = expired.iter().map(|r| SongId::of(&r.song)).collect();
+        let stats = library
+            .stats
+            .into_iter()
+            .filter(|s| !expired.contains(&SongId { path: s.path.clone(), start_time: s.start_time }))
+            .map(|s| ((s.path.clone(), s.start_time), s))
+            .collect();
+
         let lib = Self {
             theme,
+            cover_art,
             focused_element: Mutex::new(LibraryScreenElement::ArtistList),
+            view: Mutex::new(LibraryView::Artists),
 
             on_select_fn,
 
             artists: Arc::new(Mutex::new(vec![])),
             songs: Mutex::new(HashMap::new()),
+            artist_names: Mutex::new(ArtistNames::default()),
             song_list: Mutex::new(songs_el),
 
+            virtual_folders: Mutex::new(library.virtual_folders),
+            collapsed_folders: Mutex::new(HashSet::new()),
+            tree: Mutex::new(vec![]),
+            folder_rename: Mutex::new(None),
+
+            tagged_songs: Mutex::new(HashMap::new()),
+            jolt_files: Mutex::new(JoltFiles::default()),
+            overrides: Mutex::new(library.overrides),
+            metadata_edit: Mutex::new(None),
+
+            file_stamps: Mutex::new(library.file_stamps),
+            stats: Mutex::new(stats),
+            hidden: Mutex::new(library.hidden.into_iter().collect()),
+            hidden_songs: Mutex::new(vec![]),
+            removed: Mutex::new(removed),
+            duplicate_review: Mutex::new(DuplicateReview::default()),
+            missing_files: MissingFiles::default(),
+            relink_root: Mutex::new(None),
+            relinked: Mutex::new(BTreeMap::new()),
+            revision: AtomicUsize::new(0),
+
             selected_artist_index: AtomicUsize::new(0),
-            selected_song_index: AtomicUsize::new(0),
 
             offset: AtomicUsize::new(0),
             height: AtomicUsize::new(0),
         };
 
-        lib.add_songs(songs);
+        lib.insert_songs(library.songs);
 
         lib
     }
 
-    pub fn on_select(&self, cb: impl FnMut((Song, KeyEvent)) + 'a) {
+    pub fn on_select(&self, cb: impl FnMut((Vec<Song>, KeyEvent)) + 'a) {
         *self.on_select_fn.lock().unwrap() = Box::new(cb);
     }
 
@@ -97,84 +277,1174 @@ impl<'a> Library<'a> {
         songs
     }
 
-    pub fn add_songs(&self, songs: Vec<Song>) {
+    /// The songs of the library, along with the hidden ones.
+    pub fn all_songs(&self) -> Vec<Song> {
+        let mut songs = self.songs();
+        songs.extend(self.hidden_songs.lock().unwrap().iter().cloned());
+        songs
+    }
+
+    /// The songs of the library, hidden ones included, as read from their files, to be saved.
+    pub fn tagged_songs(&self) -> Vec<Song> {
+        self.tagged_songs.lock().unwrap().values().cloned().collect()
+    }
+
+    pub fn hidden(&self) -> Vec<SongId> {
+        let mut hidden: Vec<SongId> = self.hidden.lock().unwrap().iter().cloned().collect();
+        hidden.sort_unstable();
+        hidden
+    }
+
+    pub fn removed(&self) -> Vec<RemovedSong> {
+        self.removed.lock().unwrap().clone()
+    }
+
+    pub fn virtual_folders(&self) -> Vec<VirtualFolder> {
+        self.virtual_folders.lock().unwrap().clone()
+    }
+
+    pub fn overrides(&self) -> MetadataOverrides {
+        self.overrides.lock().unwrap().clone()
+    }
+
+    pub fn file_stamps(&self) -> BTreeMap<PathBuf, FileStamp> {
+        self.file_stamps.lock().unwrap().clone()
+    }
+
+    pub fn stats(&self) -> Vec<SongStats> {
+        self.stats.lock().unwrap().values().cloned().collect()
+    }
+
+    /// The stats of each song, or empty ones for songs never played.
+    pub fn song_stats(&self, songs: &[Song]) -> Vec<SongStats> {
+        let stats = self.stats.lock().unwrap();
+        songs.iter().map(|s| stats.get(&(s.path.clone(), s.start_time)).cloned().unwrap_or_default()).collect()
+    }
+
+    pub fn song_sort(&self) -> SongSort {
+        self.song_list.lock().unwrap().sort()
+    }
+
+    pub fn set_song_sort(&self, sort: SongSort) {
+        self.song_list.lock().unwrap().set_sort(sort);
+    }
+
+    /// Whether a folder name, metadata or a folder to search for missing files is being typed, so keys are meant as text.
+    pub fn is_typing(&self) -> bool {
+        self.folder_rename.lock().unwrap().is_some()
+            || self.metadata_edit.lock().unwrap().is_some()
+            || self.relink_root.lock().unwrap().is_some()
+    }
+
+    pub fn revision(&self) -> usize {
+        self.revision.load(AtomicOrdering::SeqCst)
+    }
+
+    fn update_stats(&self, song: &Song, f: impl FnOnce(&mut SongStats)) {
+        let mut stats = self.stats.lock().unwrap();
+        f(stats.entry((song.path.clone(), song.start_time)).or_insert_with(|| SongStats::new(song)));
+        drop(stats);
+
+        self.revision.fetch_add(1, AtomicOrdering::SeqCst);
+
+        self.refresh_song_list();
+    }
+
+    pub fn record_play(&self, song: &Song) {
+        self.update_stats(song, |stats| {
+            stats.play_count += 1;
+            stats.last_played = Some(unix_now());
+        });
+    }
+
+    pub fn record_skip(&self, song: &Song) {
+        self.update_stats(song, |stats| stats.skip_count += 1);
+    }
+
+    /// Marks the songs as favorites or, if they all are already, unmarks them.
+    pub(super) fn toggle_favorite(&self, songs: &[Song]) {
+        let mut stats = self.stats.lock().unwrap();
+        let is_favorite = |song: &Song| stats.get(&(song.path.clone(), song.start_time)).is_some_and(|s| s.favorite);
+        let favorite = !songs.iter().all(is_favorite);
+
         for song in songs {
-            self.add_song(song);
+            stats.entry((song.path.clone(), song.start_time)).or_insert_with(|| SongStats::new(song)).favorite = favorite;
+        }
+
+        drop(stats);
+
+        self.revision.fetch_add(1, AtomicOrdering::SeqCst);
+        self.refresh_song_list();
+    }
+
+    /// Applies the changes found in the library folders: replaces the songs of new and changed files, and lists the
+    /// songs of files that are gone among the missing files, so they can be relinked, rather than dropping them.
+    pub fn apply_updates(&self, updates: Vec<LibraryUpdate>) {
+        let mut file_stamps = self.file_stamps.lock().unwrap();
+        let mut replaced_paths: HashSet<PathBuf> = HashSet::new();
+        let mut removed_paths: Vec<PathBuf> = vec![];
+        let mut jolt_dirs: HashSet<PathBuf> = HashSet::new();
+        let mut new_songs: Vec<Song> = vec![];
+
+        for update in updates {
+            match update {
+                LibraryUpdate::Songs { path, stamp, songs } => {
+                    let paths: HashSet<PathBuf> = songs.iter().map(|s| s.path.clone()).chain([path.clone()]).collect();
+
+                    new_songs.retain(|s| !paths.contains(&s.path));
+                    new_songs.extend(songs);
+                    replaced_paths.extend(paths);
+                    file_stamps.insert(path, stamp);
+                }
+                LibraryUpdate::Removed(path) => {
+                    log::info!("{:?} doesn't exist anymore", path);
+
+                    new_songs.retain(|s| !s.path.starts_with(&path));
+                    file_stamps.retain(|p, _| !p.starts_with(&path));
+                    removed_paths.push(path);
+                }
+                LibraryUpdate::JoltFileChanged(dir) => {
+                    jolt_dirs.insert(dir);
+                }
+            }
+        }
+
+        drop(file_stamps);
+
+        self.record_added(&new_songs);
+
+        self.remove_songs(|s| replaced_paths.contains(&s.path));
+        self.untag_songs(|id| replaced_paths.contains(&id.path));
+
+        // Files that were gone and came back aren't missing anymore
+        self.missing_files.forget(&replaced_paths);
+
+        if !removed_paths.is_empty() {
+            let gone = self.all_songs().into_iter().filter(|s| removed_paths.iter().any(|p| s.path.starts_with(p))).collect();
+            self.missing_files.add(gone);
+        }
+
+        // The songs next to a changed `.jolt` file are shown again, with its overrides as they are now
+        let mut jolt_songs = vec![];
+        if !jolt_dirs.is_empty() {
+            let mut jolt_files = self.jolt_files.lock().unwrap();
+            jolt_dirs.iter().for_each(|dir| jolt_files.forget(dir));
+            drop(jolt_files);
+
+            let in_jolt_dir = |path: &PathBuf| path.parent().is_some_and(|dir| jolt_dirs.contains(dir));
+            self.remove_songs(|s| in_jolt_dir(&s.path));
+            jolt_songs.extend(self.tagged_songs.lock().unwrap().values().filter(|s| in_jolt_dir(&s.path)).cloned());
+        }
+
+        // Removed songs whose files changed are back in the library, and the ones whose files are gone can't come back
+        self.removed.lock().unwrap().retain(|r| {
+            !replaced_paths.contains(&r.song.path) && !removed_paths.iter().any(|p| r.song.path.starts_with(p))
+        });
+        self.insert_songs(new_songs);
+
+        if !jolt_songs.is_empty() {
+            self.show_songs(jolt_songs);
+        }
+    }
+
+    /// Keeps when the songs were added to the library, for the ones it isn't known for yet.
+    /// Songs of files already in the library, such as the ones read again after they changed, aren't new.
+    fn record_added(&self, songs: &[Song]) {
+        let tagged_songs = self.tagged_songs.lock().unwrap();
+        let now = unix_now();
+        let mut stats = self.stats.lock().unwrap();
+
+        for song in songs.iter().filter(|s| !tagged_songs.contains_key(&SongId::of(s))) {
+            let song_stats = stats.entry((song.path.clone(), song.start_time)).or_insert_with(|| SongStats::new(song));
+            song_stats.added.get_or_insert(now);
+        }
+    }
+
+    /// The songs of the smart playlist, out of the whole library.
+    pub fn smart_playlist_songs(&self, playlist: &SmartPlaylist) -> Vec<Song> {
+        let songs = self.songs.lock().unwrap();
+        let stats = self.stats.lock().unwrap();
+        let file_stamps = self.file_stamps.lock().unwrap();
+
+        let mut artists: Vec<&String> = songs.keys().collect();
+        artists.sort_unstable();
+
+        // Songs added before their added date was kept fall back to the modification time of their file
+        let candidates = artists
+            .into_iter()
+            .flat_map(|artist| &songs[artist])
+            .map(|song| {
+                let song_stats = stats.get(&(song.path.clone(), song.start_time));
+                let added = song_stats.and_then(|s| s.added).or_else(|| file_stamps.get(&song.path).map(|s| s.modified));
+
+                SmartCandidate { song, stats: song_stats, added }
+            })
+            .collect();
+
+        playlist.songs(candidates, unix_now())
+    }
+
+    /// Removes the songs matching the predicate, hidden ones included, along with the artists left without songs.
+    fn remove_songs(&self, mut f: impl FnMut(&Song) -> bool) {
+        let mut songs = self.songs.lock().unwrap();
+
+        for artist_songs in songs.values_mut() {
+            artist_songs.retain(|s| !f(s));
         }
+
+        songs.retain(|_, artist_songs| !artist_songs.is_empty());
+        self.hidden_songs.lock().unwrap().retain(|s| !f(s));
+
+        self.artists.lock().unwrap().retain(|artist| songs.contains_key(artist));
+        self.revision.fetch_add(1, AtomicOrdering::SeqCst);
+    }
+
+    pub fn add_songs(&self, songs: Vec<Song>) {
+        self.record_added(&songs);
+        self.insert_songs(songs);
     }
 
     pub fn add_song(&self, song: Song) {
+        self.add_songs(vec![song]);
+    }
+
+    /// Puts songs read from their files into the library, replacing the ones with the same id, and shows them.
+    fn insert_songs(&self, songs: Vec<Song>) {
+        let mut tagged_songs = self.tagged_songs.lock().unwrap();
+        let replaced: Vec<Song> = songs.iter().filter_map(|s| tagged_songs.insert(SongId::of(s), s.clone())).collect();
+        drop(tagged_songs);
+
+        let renamed = self.artist_names.lock().unwrap().update(&replaced, &songs);
+        for (from, to) in renamed {
+            self.rename_artist(&from, &to);
+        }
+
+        self.show_songs(songs);
+    }
+
+    /// Takes the songs matching the predicate out of the songs read from their files, returning them. The songs shown
+    /// are left to the caller.
+    fn untag_songs(&self, f: impl Fn(&SongId) -> bool) -> Vec<Song> {
+        let mut tagged_songs = self.tagged_songs.lock().unwrap();
+        let ids: Vec<SongId> = tagged_songs.keys().filter(|id| f(id)).cloned().collect();
+        let untagged: Vec<Song> = ids.iter().filter_map(|id| tagged_songs.remove(id)).collect();
+        drop(tagged_songs);
+
+        let renamed = self.artist_names.lock().unwrap().update(&untagged, &[]);
+        for (from, to) in renamed {
+            self.rename_artist(&from, &to);
+        }
+
+        untagged
+    }
+
+    /// Shows songs already in the library, with the `.jolt` files, overrides and artist names applied.
+    fn show_songs(&self, songs: Vec<Song>) {
+        for song in songs {
+            self.insert_song(song);
+        }
+
+        self.revision.fetch_add(1, AtomicOrdering::SeqCst);
+        self.refresh_tree();
+        self.refresh_song_list();
+    }
+
+    fn insert_song(&self, mut song: Song) {
         // log::debug!(target: "::library.add_song", "{:?}", song);
 
-        let mut songs = self.songs.lock().unwrap();
+        // Overrides are made for the names shown, and the names they set are shown as they are
+        self.use_artist_names(&mut song);
+        let names = (song.artist.clone(), song.album_artist.clone());
+        self.jolt_files.lock().unwrap().apply(&mut song);
+        self.overrides.lock().unwrap().apply(&mut song);
 
-        let Some(artist) = song.artist.clone() else {
-            log::error!("Library.add_song() -> no artist! {:?}", song);
+        if (song.artist.clone(), song.album_artist.clone()) != names {
+            self.set_artist_names(&song);
+        }
+
+        if self.hidden.lock().unwrap().contains(&SongId::of(&song)) {
+            let mut hidden_songs = self.hidden_songs.lock().unwrap();
+            hidden_songs.retain(|s| s.path != song.path || s.start_time != song.start_time);
+            hidden_songs.push(song);
             return;
-        };
+        }
+
+        let mut songs = self.songs.lock().unwrap();
 
-        if let Some(mut x) = songs.get_mut(&artist) {
+        // Songs are filed under their album artist, so compilations and soundtracks aren't scattered across
+        // dozens of one-song artists.
+        let artist = song.library_artist().unwrap_or(UNKNOWN_ARTIST.to_string());
+        let is_compilation = song.is_compilation();
+
+        if let Some(x) = songs.get_mut(&artist) {
             if !x.iter().any(|s| s.path == song.path && s.title == song.title) {
-                x.push(song);
-                x.sort_by(|a, b| {
-                    match (&a.album, &b.album) {
-                        (Some(album_a), Some(album_b)) if album_a == album_b => {
-                            match (&a.track, &b.track) {
-                                (Some(a), Some(b)) => a.cmp(b),
-                                (Some(_), None) => Ordering::Greater,
-                                (None, Some(_)) => Ordering::Less,
-                                _ => a.title.cmp(&b.title),
-                            }
-                        },
-                        (Some(album_a), Some(album_b)) if album_a != album_b => {
-                            album_a.cmp(album_b)
-                        },
-                        (Some(_), None) => Ordering::Greater,
-                        (None, Some(_)) => Ordering::Less,
-                        _ => a.title.cmp(&b.title)
-                    }
-                })
+                let i = x.partition_point(|s| compare_songs(s, &song) != Ordering::Greater);
+                x.insert(i, song);
             }
         } else {
             songs.insert(artist.clone(), vec![song]);
         }
 
+        drop(songs);
+
+        // Compilations are listed in their own section, rather than under their album artist (usually, "Various Artists").
+        // So are songs without an artist.
+        if is_compilation || artist == UNKNOWN_ARTIST {
+            return;
+        }
+
         let mut artists = self.artists.lock().unwrap();
 
-        if !artists.contains(&artist) {
-            (*artists).push(artist.clone());
+        if let Err(i) = artists.binary_search_by(|a| compare_artists(a, &artist)) {
+            artists.insert(i, artist);
         }
+    }
 
-        artists.sort_unstable();
+    /// Renames the song's artist and album artist to the names their artists are shown with.
+    fn use_artist_names(&self, song: &mut Song) {
+        let artist_names = self.artist_names.lock().unwrap();
+
+        for name in [&mut song.artist, &mut song.album_artist].into_iter().flatten() {
+            *name = artist_names.name(name);
+        }
+    }
+
+    /// Shows the artist and album artist of the song with the names an override gave it, renaming the artists already
+    /// in the library if they were shown with other spellings.
+    fn set_artist_names(&self, song: &Song) {
+        let mut artist_names = self.artist_names.lock().unwrap();
+        let renamed: Vec<(String, String)> = [&song.artist, &song.album_artist]
+            .into_iter()
+            .flatten()
+            .filter_map(|name| artist_names.pin(name).map(|current| (current, name.clone())))
+            .collect();
+        drop(artist_names);
+
+        for (from, to) in renamed {
+            self.rename_artist(&from, &to);
+        }
+    }
+
+    /// Renames the artist of the songs already in the library, along with the artist list and virtual folders.
+    fn rename_artist(&self, from: &str, to: &str) {
+        log::debug!("showing {from:?} as {to:?}");
+
+        let rename = |song: &mut Song| {
+            for name in [&mut song.artist, &mut song.album_artist].into_iter().flatten() {
+                if name == from {
+                    *name = to.to_string();
+                }
+            }
+        };
+
+        let mut songs = self.songs.lock().unwrap();
+        songs.values_mut().flatten().for_each(rename);
+        self.hidden_songs.lock().unwrap().iter_mut().for_each(rename);
+
+        if let Some(artist_songs) = songs.remove(from) {
+            songs.insert(to.to_string(), artist_songs);
+        }
+
+        drop(songs);
+
+        let mut artists = self.artists.lock().unwrap();
+        if let Some(i) = artists.iter().position(|a| a == from) {
+            artists.remove(i);
+            let i = artists.binary_search_by(|a| compare_artists(a, to)).unwrap_or_else(|i| i);
+            artists.insert(i, to.to_string());
+        }
+        drop(artists);
+
+        for folder in self.virtual_folders.lock().unwrap().iter_mut() {
+            folder.artists.iter_mut().filter(|a| *a == from).for_each(|a| *a = to.to_string());
+            folder.albums.iter_mut().filter(|a| a.artist == from).for_each(|a| a.artist = to.to_string());
+        }
+
+        self.revision.fetch_add(1, AtomicOrdering::SeqCst);
+    }
+
+    pub fn add_cue(&self, cue_sheet: CueSheet) {
+        let songs = Song::from_cue_sheet(cue_sheet);
+        self.add_songs(songs);
+    }
+
+    /// Re-applies the metadata overrides to every song in the library, after they changed.
+    fn reapply_overrides(&self) {
+        self.songs.lock().unwrap().clear();
+        self.hidden_songs.lock().unwrap().clear();
+        self.artists.lock().unwrap().clear();
+
+        // Artists named by overrides that are gone go back to the names their songs have
+        let renamed = self.artist_names.lock().unwrap().unpin_all();
+        for (from, to) in renamed {
+            self.rename_artist(&from, &to);
+        }
+
+        self.show_songs(self.tagged_songs());
+    }
+
+    /// Leaves the songs out of the library, without forgetting them, so they can be brought back.
+    pub(super) fn hide_songs(&self, songs: &[Song]) {
+        let ids: HashSet<SongId> = songs.iter().map(SongId::of).collect();
+        let mut hidden_songs = vec![];
+
+        self.remove_songs(|s| {
+            let is_hidden = ids.contains(&SongId::of(s));
+            if is_hidden {
+                hidden_songs.push(s.clone());
+            }
+            is_hidden
+        });
+
+        self.hidden.lock().unwrap().extend(ids);
+        self.hidden_songs.lock().unwrap().extend(hidden_songs);
+
+        self.refresh_tree();
+        self.refresh_song_list();
+    }
+
+    /// Brings hidden songs back into the library.
+    pub(super) fn unhide_songs(&self, songs: &[Song]) {
+        let ids: HashSet<SongId> = songs.iter().map(SongId::of).collect();
+
+        self.hidden.lock().unwrap().retain(|id| !ids.contains(id));
+
+        let mut hidden_songs = self.hidden_songs.lock().unwrap();
+        let (unhidden, still_hidden): (Vec<Song>, Vec<Song>) = hidden_songs.drain(..).partition(|s| ids.contains(&SongId::of(s)));
+        *hidden_songs = still_hidden;
+        drop(hidden_songs);
+
+        let tagged_songs = self.tagged_songs.lock().unwrap();
+        let unhidden = unhidden.iter().filter_map(|s| tagged_songs.get(&SongId::of(s)).cloned()).collect();
+        drop(tagged_songs);
+
+        self.show_songs(unhidden);
+    }
+
+    pub(super) fn is_hidden(&self, song: &Song) -> bool {
+        self.hidden.lock().unwrap().contains(&SongId::of(song))
+    }
+
+    /// Takes the songs out of the library, without touching their files. The stamps of their files are kept, so the
+    /// library scanner doesn't bring them back, unless the files change. They're kept in the recently removed songs,
+    /// along with their stats, so they can be restored for a while.
+    pub(super) fn remove_from_library(&self, songs: &[Song]) {
+        let ids: HashSet<SongId> = songs.iter().map(SongId::of).collect();
+        let now = unix_now();
+
+        self.remove_songs(|s| ids.contains(&SongId::of(s)));
+
+        let removed_songs: Vec<RemovedSong> = self
+            .untag_songs(|id| ids.contains(id))
+            .into_iter()
+            .map(|song| RemovedSong { song, removed_at: now })
+            .collect();
+
+        self.hidden.lock().unwrap().retain(|id| !ids.contains(id));
+
+        let mut removed = self.removed.lock().unwrap();
+        removed.retain(|r| !ids.contains(&SongId::of(&r.song)));
+        removed.extend(removed_songs);
+        drop(removed);
+
+        self.refresh_tree();
+        self.refresh_song_list();
+    }
+
+    /// Brings hidden and recently removed songs back into the library.
+    pub(super) fn restore_songs(&self, songs: &[Song]) {
+        let ids: HashSet<SongId> = songs.iter().map(SongId::of).collect();
+
+        let mut removed = self.removed.lock().unwrap();
+        let (restored, still_removed): (Vec<RemovedSong>, Vec<RemovedSong>) =
+            removed.drain(..).partition(|r| ids.contains(&SongId::of(&r.song)));
+        *removed = still_removed;
+        drop(removed);
+
+        self.unhide_songs(songs);
+        self.insert_songs(restored.into_iter().map(|r| r.song).collect());
+    }
+
+    /// Forgets hidden and recently removed songs for good, along with their stats. Their files are left untouched.
+    pub(super) fn forget_removed(&self, songs: &[Song]) {
+        let ids: HashSet<SongId> = songs.iter().map(SongId::of).collect();
+
+        self.hidden_songs.lock().unwrap().retain(|s| !ids.contains(&SongId::of(s)));
+        self.untag_songs(|id| ids.contains(id));
+        self.hidden.lock().unwrap().retain(|id| !ids.contains(id));
+        self.removed.lock().unwrap().retain(|r| !ids.contains(&SongId::of(&r.song)));
+        self.stats.lock().unwrap().retain(|(path, start_time), _| {
+            !ids.contains(&SongId { path: path.clone(), start_time: *start_time })
+        });
+
+        self.revision.fetch_add(1, AtomicOrdering::SeqCst);
+        self.refresh_tree();
+        self.refresh_song_list();
+    }
+
+    /// All the songs of an artist, including the ones of albums moved into virtual folders, and compilations.
+    pub(super) fn artist_songs(&self, artist: &str) -> Vec<Song> {
+        self.songs.lock().unwrap().get(artist).cloned().unwrap_or_default()
+    }
+
+    /// Checks, in the background, whether the files of the library's songs and the given ones still exist.
+    pub fn check_missing_files(&self, other_songs: Vec<Song>) {
+        let mut songs = self.all_songs();
+        songs.extend(other_songs);
+        self.missing_files.check(songs);
+    }
+
+    pub fn is_missing(&self, song: &Song) -> bool {
+        self.missing_files.is_missing(&song.path)
+    }
 
-        let Some(selected_artist) = artists.get(self.selected_song_index.load(AtomicOrdering::SeqCst)) else {
+    /// Shows the missing files found by the check or search in the background, once they're done.
+    pub fn missing_files_tick(&self) {
+        if self.missing_files.take_changed() {
+            self.revision.fetch_add(1, AtomicOrdering::SeqCst);
+            self.refresh_tree();
+            self.refresh_song_list();
+        }
+    }
+
+    /// Asks for the folder to search for missing files in, starting with the closest one to them that still exists.
+    pub(super) fn start_relink(&self) {
+        let root = self
+            .missing_files
+            .songs()
+            .first()
+            .and_then(|song| song.path.ancestors().skip(1).find(|p| p.is_dir()).map(|p| p.to_string_lossy().to_string()))
+            .unwrap_or_default();
+
+        *self.relink_root.lock().unwrap() = Some(root);
+    }
+
+    pub(super) fn search_missing_files(&self) {
+        let Some(root) = self.relink_root.lock().unwrap().take() else {
             return;
         };
 
-        if artist != *selected_artist {
+        if !root.trim().is_empty() {
+            self.missing_files.search(PathBuf::from(root.trim()));
+        }
+
+        self.refresh_song_list();
+    }
+
+    /// Points the songs of the missing files found so far to their new paths, keeping their stats, metadata
+    /// overrides and whether they're hidden. The playlists and queue follow, through [`Library::take_relinked`].
+    pub(super) fn relink_missing_files(&self) {
+        let new_paths = self.missing_files.new_paths();
+
+        if new_paths.is_empty() {
             return;
         }
 
-        let Some(artist_songs) = songs.get(artist.as_str()) else {
-            log::error!("No song list for {artist}! This is an error: we should always have a Vec<Song>, even if empty.");
+        log::info!("relinking {} files", new_paths.len());
+
+        let relink = |path: &mut PathBuf| {
+            if let Some(new_path) = new_paths.get(path) {
+                *path = new_path.clone();
+            }
+        };
+
+        self.remove_songs(|s| new_paths.contains_key(&s.path));
+
+        let relinked_songs: Vec<Song> = self
+            .untag_songs(|id| new_paths.contains_key(&id.path))
+            .into_iter()
+            .map(|song| Song { path: new_paths[&song.path].clone(), ..song })
+            .collect();
+
+        let mut hidden = self.hidden.lock().unwrap();
+        *hidden = hidden
+            .drain()
+            .map(|mut id| {
+                relink(&mut id.path);
+                id
+            })
+            .collect();
+        drop(hidden);
+
+        let mut stats = self.stats.lock().unwrap();
+        *stats = stats
+            .drain()
+            .map(|((mut path, start_time), mut song_stats)| {
+                relink(&mut path);
+                relink(&mut song_stats.path);
+                ((path, start_time), song_stats)
+            })
+            .collect();
+        drop(stats);
+
+        self.overrides.lock().unwrap().songs.iter_mut().for_each(|o| relink(&mut o.path));
+        self.file_stamps.lock().unwrap().retain(|path, _| !new_paths.contains_key(path));
+
+        self.insert_songs(relinked_songs);
+        self.missing_files.forget(&new_paths.keys().cloned().collect());
+        self.relinked.lock().unwrap().extend(new_paths);
+    }
+
+    /// The old and new paths of the files relinked since the last time it was asked.
+    pub fn take_relinked(&self) -> BTreeMap<PathBuf, PathBuf> {
+        std::mem::take(&mut *self.relinked.lock().unwrap())
+    }
+
+    pub(super) fn start_metadata_edit(&self, target: MetadataEditTarget) {
+        *self.metadata_edit.lock().unwrap() = Some(MetadataEdit::new(target));
+    }
+
+    /// Stores the edit as a metadata override, and applies it to the library.
+    pub(super) fn save_metadata_edit(&self) {
+        let Some(edit) = self.metadata_edit.lock().unwrap().take() else {
+            return;
+        };
+
+        let metadata = edit.metadata_override();
+
+        if metadata == Default::default() {
+            return;
+        }
+
+        let mut overrides = self.overrides.lock().unwrap();
+        let mut virtual_folders = self.virtual_folders.lock().unwrap();
+
+        let selected_item = match edit.target {
+            MetadataEditTarget::Artist(artist) => {
+                let Some(new_artist) = metadata.artist else {
+                    return;
+                };
+
+                for folder in virtual_folders.iter_mut() {
+                    folder.artists.iter_mut().filter(|a| **a == artist).for_each(|a| *a = new_artist.clone());
+                    folder.albums.iter_mut().filter(|a| a.artist == artist).for_each(|a| a.artist = new_artist.clone());
+                }
+
+                overrides.add_artist_alias(artist, new_artist.clone());
+                Some(ArtistListItem::Artist(new_artist))
+            }
+            MetadataEditTarget::Album { artist, album, .. } => {
+                let new_album = VirtualFolderAlbum {
+                    artist: metadata.album_artist.clone().unwrap_or(artist.clone()),
+                    album: metadata.album.clone().unwrap_or(album.clone()),
+                };
+
+                for folder in virtual_folders.iter_mut() {
+                    folder.albums.iter_mut().filter(|a| a.artist == artist && a.album == album).for_each(|a| *a = new_album.clone());
+                }
+
+                overrides.add_album_override(artist, album, metadata);
+                None
+            }
+            MetadataEditTarget::Song(song) => {
+                overrides.add_song_override(&song, metadata);
+                None
+            }
+        };
+
+        drop(virtual_folders);
+        drop(overrides);
+
+        self.reapply_overrides();
+
+        if let Some(item) = selected_item {
+            self.select_item(&item);
+            self.refresh_song_list();
+        }
+    }
+
+    /// Overrides the artist and album of songs without an artist with the ones guessed from their paths.
+    pub(super) fn accept_path_guesses(&self, songs: Vec<Song>) {
+        let mut overrides = self.overrides.lock().unwrap();
+
+        for song in songs.iter().filter(|s| s.library_artist().is_none()) {
+            let guess = guess_from_path(&song.path);
+            let metadata = MetadataOverride {
+                artist: guess.artist,
+                album: if song.album.is_none() { guess.album } else { None },
+                ..Default::default()
+            };
+
+            if metadata != MetadataOverride::default() {
+                overrides.add_song_override(song, metadata);
+            }
+        }
+
+        drop(overrides);
+        self.reapply_overrides();
+    }
+
+    /// Switches to the next view of the library, selecting its first item.
+    pub(super) fn next_view(&self) {
+        let mut view = self.view.lock().unwrap();
+        *view = view.next();
+        drop(view);
+
+        self.selected_artist_index.store(0, AtomicOrdering::SeqCst);
+        self.offset.store(0, AtomicOrdering::SeqCst);
+        self.refresh_tree();
+        self.song_list.lock().unwrap().select_first();
+    }
+
+    /// Rebuilds the items shown in the left side of the library: the tree of virtual folders, artists and albums,
+    /// or the genres or years of the library, depending on the view.
+    pub(super) fn refresh_tree(&self) {
+        let view = *self.view.lock().unwrap();
+
+        if view != LibraryView::Artists {
+            let songs = self.songs.lock().unwrap();
+
+            let tree = if view == LibraryView::Genres {
+                let mut genres: Vec<String> = songs.values().flatten().flat_map(|s| s.genres()).collect();
+                genres.sort_unstable_by_key(|g| g.to_lowercase());
+                genres.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+                genres.into_iter().map(|g| (0, ArtistListItem::Genre(g))).collect()
+            } else {
+                let mut years: Vec<u32> = songs.values().flatten().filter_map(|s| s.year).collect();
+                years.sort_unstable();
+                years.dedup();
+                years.into_iter().map(|y| (0, ArtistListItem::Year(y))).collect()
+            };
+
+            drop(songs);
+            self.set_tree(tree);
+            return;
+        }
+
+        let artists = self.artists.lock().unwrap();
+        let virtual_folders = self.virtual_folders.lock().unwrap();
+        let collapsed_folders = self.collapsed_folders.lock().unwrap();
+
+        let mut tree = vec![];
+
+        for folder in virtual_folders.iter() {
+            tree.push((0, ArtistListItem::Folder(folder.name.clone())));
+
+            if collapsed_folders.contains(&folder.name) {
+                continue;
+            }
+
+            let mut folder_artists = folder.artists.clone();
+            folder_artists.sort_by(|a, b| compare_artists(a, b));
+
+            for artist in folder_artists {
+                tree.push((1, ArtistListItem::Artist(artist)));
+            }
+
+            let mut folder_albums = folder.albums.clone();
+            folder_albums.sort_by(|a, b| compare_artists(&a.artist, &b.artist).then_with(|| a.album.cmp(&b.album)));
+
+            for album in folder_albums {
+                tree.push((1, ArtistListItem::Album(album)));
+            }
+        }
+
+        let has_compilations = self.songs.lock().unwrap().values().flatten().any(|s| {
+            s.is_compilation() && !virtual_folders.iter().any(|f| {
+                s.library_artist().zip(s.album.as_ref()).is_some_and(|(artist, album)| f.contains_album(&artist, album))
+            })
+        });
+
+        if has_compilations {
+            tree.push((0, ArtistListItem::Compilations));
+        }
+
+        for artist in artists.iter() {
+            if !virtual_folders.iter().any(|f| f.contains_artist(artist)) {
+                tree.push((0, ArtistListItem::Artist(artist.clone())));
+            }
+        }
+
+        let songs = self.songs.lock().unwrap();
+
+        if songs.contains_key(UNKNOWN_ARTIST) {
+            tree.push((0, ArtistListItem::UnknownArtist));
+        }
+
+        // Finding tag issues and duplicates takes the whole library, so it's only done when their view is opened
+        if !songs.is_empty() {
+            tree.push((0, ArtistListItem::LibraryHealth));
+            tree.push((0, ArtistListItem::Duplicates));
+        }
+
+        drop(songs);
+
+        if !self.missing_files.is_empty() {
+            tree.push((0, ArtistListItem::MissingFiles));
+        }
+
+        if !self.hidden_songs.lock().unwrap().is_empty() || !self.removed.lock().unwrap().is_empty() {
+            tree.push((0, ArtistListItem::RecentlyRemoved));
+        }
+
+        drop(artists);
+        drop(virtual_folders);
+        drop(collapsed_folders);
+
+        self.set_tree(tree);
+    }
+
+    fn set_tree(&self, tree: Vec<(usize, ArtistListItem)>) {
+        let len = tree.len();
+        *self.tree.lock().unwrap() = tree;
+
+        let _ = self.selected_artist_index.fetch_update(AtomicOrdering::SeqCst, AtomicOrdering::SeqCst, |a| {
+            Some(a.min(len.saturating_sub(1)))
+        });
+    }
+
+    pub(super) fn selected_item(&self) -> Option<ArtistListItem> {
+        let tree = self.tree.lock().unwrap();
+        tree.get(self.selected_artist_index.load(AtomicOrdering::SeqCst)).map(|(_, item)| item.clone())
+    }
+
+    pub(super) fn select_item(&self, item: &ArtistListItem) {
+        let tree = self.tree.lock().unwrap();
+
+        if let Some(i) = tree.iter().position(|(_, it)| it == item) {
+            self.selected_artist_index.store(i, AtomicOrdering::SeqCst);
+        }
+    }
+
+    /// Selects the first artist, album or section with the given song in it, and the song itself in the song list.
+    pub fn select_song(&self, song: &Song) {
+        let items: Vec<ArtistListItem> = self.tree.lock().unwrap().iter().map(|(_, item)| item.clone()).collect();
+
+        let item = items.into_iter().find(|item| {
+            !matches!(
+                item,
+                ArtistListItem::Folder(_)
+                    | ArtistListItem::LibraryHealth
+                    | ArtistListItem::Duplicates
+                    | ArtistListItem::MissingFiles
+                    | ArtistListItem::RecentlyRemoved
+            )
+                && self.item_songs(item).iter().any(|s| s.path == song.path && s.start_time == song.start_time)
+        });
+
+        let Some(item) = item else {
+            log::warn!("Library.select_song: {:?} not found", song.path);
             return;
         };
 
+        self.select_item(&item);
+        self.refresh_song_list();
+        self.song_list.lock().unwrap().select_song(song);
+        *self.focused_element.lock().unwrap() = LibraryScreenElement::SongList;
+    }
+
+    /// The songs of the given item of the artist list.
+    /// Albums moved into a virtual folder are excluded from their artist's songs, since they're shown in the folder.
+    /// Compilations are excluded too, since they're shown in their own section.
+    pub(super) fn item_songs(&self, item: &ArtistListItem) -> Vec<Song> {
+        let songs = self.songs.lock().unwrap();
+        let virtual_folders = self.virtual_folders.lock().unwrap();
+
+        let in_folder = |artist: &str, song: &Song| -> bool {
+            song.album.as_ref().is_some_and(|album| {
+                virtual_folders.iter().any(|f| f.contains_album(artist, album))
+            })
+        };
+
+        let artist_songs = |artist: &str| -> Vec<Song> {
+            songs
+                .get(artist)
+                .map(|artist_songs| {
+                    artist_songs
+                        .iter()
+                        .filter(|s| !s.is_compilation() && !in_folder(artist, s))
+                        .cloned()
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let album_songs = |album: &VirtualFolderAlbum| -> Vec<Song> {
+            songs
+                .get(&album.artist)
+                .map(|artist_songs| {
+                    artist_songs
+                        .iter()
+                        .filter(|s| s.album.as_ref() == Some(&album.album))
+                        .cloned()
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        match item {
+            ArtistListItem::Artist(artist) => artist_songs(artist),
+            ArtistListItem::Album(album) => album_songs(album),
+            ArtistListItem::Compilations => {
+                let mut compilations: Vec<(&String, &Song)> = songs
+                    .iter()
+                    .flat_map(|(artist, artist_songs)| artist_songs.iter().map(move |s| (artist, s)))
+                    .filter(|(artist, s)| s.is_compilation() && !in_folder(artist, s))
+                    .collect();
+
+                compilations.sort_by(|(artist_a, a), (artist_b, b)| {
+                    a.album.cmp(&b.album).then(compare_artists(artist_a, artist_b)).then_with(|| compare_songs(a, b))
+                });
+
+                compilations.into_iter().map(|(_, s)| s.clone()).collect()
+            }
+            ArtistListItem::UnknownArtist => songs.get(UNKNOWN_ARTIST).cloned().unwrap_or_default(),
+            ArtistListItem::LibraryHealth => {
+                sorted_by_artist(songs.iter().flat_map(|(artist, artist_songs)| {
+                    artist_songs.iter().filter(|s| !tag_issues(s).is_empty()).map(move |s| (artist, s))
+                }))
+            }
+            // Shown in the duplicates review, rather than the song list
+            ArtistListItem::Duplicates => vec![],
+            ArtistListItem::MissingFiles => self.missing_files.songs(),
+            // The hidden songs, by artist, followed by the removed ones, the latest first
+            ArtistListItem::RecentlyRemoved => {
+                let hidden_songs = self.hidden_songs.lock().unwrap();
+                let artists: Vec<String> =
+                    hidden_songs.iter().map(|s| s.library_artist().unwrap_or(UNKNOWN_ARTIST.to_string())).collect();
+                let mut recently_removed = sorted_by_artist(artists.iter().zip(hidden_songs.iter()));
+
+                let mut removed = self.removed.lock().unwrap().clone();
+                removed.sort_by_key(|r| Reverse(r.removed_at));
+                recently_removed.extend(removed.into_iter().map(|r| r.song));
+                recently_removed
+            }
+            ArtistListItem::Genre(genre) => {
+                sorted_by_artist(songs.iter().flat_map(|(artist, artist_songs)| {
+                    artist_songs
+                        .iter()
+                        .filter(|s| s.genres().iter().any(|g| g.eq_ignore_ascii_case(genre)))
+                        .map(move |s| (artist, s))
+                }))
+            }
+            ArtistListItem::Year(year) => {
+                sorted_by_artist(songs.iter().flat_map(|(artist, artist_songs)| {
+                    artist_songs.iter().filter(|s| s.year == Some(*year)).map(move |s| (artist, s))
+                }))
+            }
+            ArtistListItem::Folder(name) => {
+                let Some(folder) = virtual_folders.iter().find(|f| &f.name == name) else {
+                    return vec![];
+                };
+
+                let mut folder_songs: Vec<Song> = folder.artists.iter().flat_map(|a| artist_songs(a)).collect();
+                folder_songs.extend(folder.albums.iter().flat_map(|a| album_songs(a)));
+                folder_songs
+            }
+        }
+    }
+
+    /// Updates the song list to show the songs of the selected item of the artist list.
+    pub(super) fn refresh_song_list(&self) {
+        let item = self.selected_item();
+
+        if item == Some(ArtistListItem::Duplicates) {
+            let revision = self.revision();
+            let mut duplicate_review = self.duplicate_review.lock().unwrap();
+
+            if duplicate_review.revision != Some(revision) {
+                duplicate_review.set_groups(find_duplicates(&self.all_songs()));
+                duplicate_review.revision = Some(revision);
+            }
+        }
+
+        let songs = match &item {
+            Some(item) => self.item_songs(item),
+            None => vec![],
+        };
+
+        let song_stats = self.song_stats(&songs);
+
+        let missing = songs.iter().map(|s| self.missing_files.is_missing(&s.path)).collect();
+
         // Cloning the song list once per key press is probably more performant than dealing with Rc's, WeakRef's and whatnot.
-        let artist_songs = artist_songs.iter().map(|s| s.clone()).collect();
-        self.song_list.lock().unwrap().set_songs(artist_songs);
+        if item == Some(ArtistListItem::LibraryHealth) {
+            let notes = songs
+                .iter()
+                .map(|s| tag_issues(s).iter().map(|issue| issue.description()).collect::<Vec<_>>().join(", "))
+                .collect();
+            self.song_list.lock().unwrap().set_songs_with_notes(songs, song_stats, notes);
+        } else if item == Some(ArtistListItem::MissingFiles) {
+            let is_searching = self.missing_files.is_searching();
+            let notes = songs
+                .iter()
+                .map(|s| match self.missing_files.new_path(&s.path) {
+                    Some(new_path) => format!("found at {}", new_path.display()),
+                    None if is_searching => "missing, searching…".to_string(),
+                    None => "missing".to_string(),
+                })
+                .collect();
+            self.song_list.lock().unwrap().set_songs_with_notes(songs, song_stats, notes);
+        } else if item == Some(ArtistListItem::RecentlyRemoved) {
+            let now = unix_now();
+            let removed = self.removed.lock().unwrap();
+            let notes = songs
+                .iter()
+                .map(|s| match removed.iter().find(|r| SongId::of(&r.song) == SongId::of(s)) {
+                    Some(r) => format!("removed {}", time_ago(now.saturating_sub(r.removed_at))),
+                    None => "hidden".to_string(),
+                })
+                .collect();
+            drop(removed);
+            self.song_list.lock().unwrap().set_songs_with_notes(songs, song_stats, notes);
+        } else {
+            self.song_list.lock().unwrap().set_songs(songs, song_stats);
+        }
+
+        self.song_list.lock().unwrap().set_missing(missing);
     }
 
-    pub fn add_cue(&self, cue_sheet: CueSheet) {
-        let songs = Song::from_cue_sheet(cue_sheet);
-        self.add_songs(songs);
+    pub fn create_virtual_folder(&self) {
+        let mut virtual_folders = self.virtual_folders.lock().unwrap();
+
+        let mut name = "New folder".to_string();
+        let mut i = 1;
+        while virtual_folders.iter().any(|f| f.name == name) {
+            i += 1;
+            name = format!("New folder {i}");
+        }
+
+        virtual_folders.push(VirtualFolder::new(name.clone()));
+        drop(virtual_folders);
+
+        self.refresh_tree();
+        self.select_item(&ArtistListItem::Folder(name));
+        self.refresh_song_list();
     }
 
-    pub fn add_directory(&self, path: &PathBuf) {
-        let songs = Song::from_dir(path);
-        self.add_songs(songs);
+    /// Starts typing a new name for the selected folder.
+    pub(super) fn start_folder_rename(&self) {
+        if let Some(ArtistListItem::Folder(name)) = self.selected_item() {
+            *self.folder_rename.lock().unwrap() = Some(name);
+        }
     }
 
+    /// Why the selected folder can't be given the name being typed, if it can't. Folders are told apart by their names,
+    /// so they must have one, and not the one of another folder.
+    pub(super) fn folder_rename_error(&self) -> Option<&'static str> {
+        let folder_rename = self.folder_rename.lock().unwrap();
+        let new_name = folder_rename.as_deref()?.trim();
+
+        let Some(ArtistListItem::Folder(name)) = self.selected_item() else {
+            return None;
+        };
+
+        if new_name.is_empty() {
+            Some("a name is needed")
+        } else if new_name != name && self.virtual_folders.lock().unwrap().iter().any(|f| f.name == new_name) {
+            Some("another folder has this name")
+        } else {
+            None
+        }
+    }
+
+    /// Gives the selected folder the name typed, unless it can't have it.
+    pub(super) fn finish_folder_rename(&self) {
+        if self.folder_rename_error().is_some() {
+            return;
+        }
+
+        let Some(new_name) = self.folder_rename.lock().unwrap().take().map(|n| n.trim().to_string()) else {
+            return;
+        };
+
+        let Some(ArtistListItem::Folder(name)) = self.selected_item() else {
+            return;
+        };
+
+        let mut virtual_folders = self.virtual_folders.lock().unwrap();
+        let Some(folder) = virtual_folders.iter_mut().find(|f| f.name == name) else {
+            return;
+        };
+
+        folder.name = new_name.clone();
+        drop(virtual_folders);
+
+        let mut collapsed_folders = self.collapsed_folders.lock().unwrap();
+        if collapsed_folders.remove(&name) {
+            collapsed_folders.insert(new_name.clone());
+        }
+        drop(collapsed_folders);
+
+        self.refresh_tree();
+        self.select_item(&ArtistListItem::Folder(new_name));
+    }
+
+    /// Deletes the folder. The artists and albums in it go back to the top level of the library.
+    pub(super) fn delete_folder(&self, name: &str) {
+        self.virtual_folders.lock().unwrap().retain(|f| f.name != name);
+        self.collapsed_folders.lock().unwrap().remove(name);
+        self.refresh_tree();
+        self.refresh_song_list();
+    }
+
+    pub(super) fn toggle_folder(&self, name: &str) {
+        let mut collapsed_folders = self.collapsed_folders.lock().unwrap();
+
+        if !collapsed_folders.remove(name) {
+            collapsed_folders.insert(name.to_string());
+        }
+
+        drop(collapsed_folders);
+        self.refresh_tree();
+    }
+
+    /// Moves the artist or album into the next virtual folder, or out of them, after the last one.
+    pub(super) fn move_to_next_folder(&self, item: ArtistListItem) {
+        let mut virtual_folders = self.virtual_folders.lock().unwrap();
+
+        if virtual_folders.is_empty() {
+            return;
+        }
+
+        let current_folder = match &item {
+            ArtistListItem::Artist(artist) => virtual_folders.iter().position(|f| f.contains_artist(artist)),
+            ArtistListItem::Album(album) => {
+                virtual_folders.iter().position(|f| f.contains_album(&album.artist, &album.album))
+            }
+            _ => return,
+        };
+
+        if let Some(i) = current_folder {
+            match &item {
+                ArtistListItem::Artist(artist) => virtual_folders[i].artists.retain(|a| a != artist),
+                ArtistListItem::Album(album) => virtual_folders[i].albums.retain(|a| a != album),
+                _ => {}
+            }
+        }
+
+        let next_folder = match current_folder {
+            Some(i) if i + 1 < virtual_folders.len() => Some(i + 1),
+            Some(_) => None,
+            None => Some(0),
+        };
+
+        if let Some(i) = next_folder {
+            let folder = &mut virtual_folders[i];
+
+            match &item {
+                ArtistListItem::Artist(artist) => folder.artists.push(artist.clone()),
+                ArtistListItem::Album(album) => folder.albums.push(album.clone()),
+                _ => {}
+            }
+
+            self.collapsed_folders.lock().unwrap().remove(&folder.name);
+        }
+
+        drop(virtual_folders);
+
+        self.refresh_tree();
+
+        // Moving an album out of a folder leaves nothing to select in the tree, so we select its artist instead.
+        match item {
+            ArtistListItem::Album(album) if next_folder.is_none() => {
+                self.select_item(&ArtistListItem::Artist(album.artist));
+            }
+            item => self.select_item(&item),
+        }
+
+        self.refresh_song_list();
+    }
 }
 
 impl Drop for Library<'_> {
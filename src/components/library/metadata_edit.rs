@@ -0,0 +1,121 @@
+use crate::structs::{MetadataOverride, Song};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum MetadataField {
+    Artist,
+    AlbumArtist,
+    Album,
+    Title,
+    Track,
+    Year,
+}
+
+impl MetadataField {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MetadataField::Artist => "Artist",
+            MetadataField::AlbumArtist => "Album Artist",
+            MetadataField::Album => "Album",
+            MetadataField::Title => "Title",
+            MetadataField::Track => "Track",
+            MetadataField::Year => "Year",
+        }
+    }
+}
+
+/// What's being edited. Editing an artist creates an artist alias, editing an album overrides all of its songs,
+/// and editing a song overrides just that song.
+#[derive(Clone, Debug)]
+pub(super) enum MetadataEditTarget {
+    Artist(String),
+    Album {
+        artist: String,
+        album: String,
+        year: Option<u32>,
+    },
+    Song(Box<Song>),
+}
+
+/// An in-progress, in-app edit of the metadata of an artist, album or song.
+#[derive(Clone, Debug)]
+pub(super) struct MetadataEdit {
+    pub target: MetadataEditTarget,
+    /// The editable fields, with their original value and the edited one.
+    pub fields: Vec<(MetadataField, String, String)>,
+    pub selected_field: usize,
+}
+
+fn option_to_string<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(T::to_string).unwrap_or_default()
+}
+
+impl MetadataEdit {
+    pub fn new(target: MetadataEditTarget) -> Self {
+        let values = match &target {
+            MetadataEditTarget::Artist(artist) => vec![(MetadataField::Artist, artist.clone())],
+            MetadataEditTarget::Album { artist, album, year } => vec![
+                (MetadataField::Album, album.clone()),
+                (MetadataField::AlbumArtist, artist.clone()),
+                (MetadataField::Year, option_to_string(year)),
+            ],
+            MetadataEditTarget::Song(song) => vec![
+                (MetadataField::Title, song.title.clone()),
+                (MetadataField::Artist, option_to_string(&song.artist)),
+                (MetadataField::Album, option_to_string(&song.album)),
+                (MetadataField::Track, option_to_string(&song.track)),
+                (MetadataField::Year, option_to_string(&song.year)),
+            ],
+        };
+
+        Self {
+            target,
+            fields: values.into_iter().map(|(field, value)| (field, value.clone(), value)).collect(),
+            selected_field: 0,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        match &self.target {
+            MetadataEditTarget::Artist(artist) => format!("Artist: {artist}"),
+            MetadataEditTarget::Album { artist, album, .. } => format!("Album: {artist} - {album}"),
+            MetadataEditTarget::Song(song) => format!("Song: {}", song.title),
+        }
+    }
+
+    pub fn value_mut(&mut self) -> &mut String {
+        &mut self.fields[self.selected_field].2
+    }
+
+    pub fn next_field(&mut self) {
+        self.selected_field = (self.selected_field + 1) % self.fields.len();
+    }
+
+    pub fn previous_field(&mut self) {
+        self.selected_field = (self.selected_field + self.fields.len() - 1) % self.fields.len();
+    }
+
+    /// The override resulting from the edit, including only the fields that changed.
+    /// Empty or unparsable numbers are ignored, since overrides can't remove values.
+    pub fn metadata_override(&self) -> MetadataOverride {
+        let mut metadata = MetadataOverride::default();
+
+        for (field, original, value) in &self.fields {
+            let value = value.trim();
+
+            if value == original.trim() || value.is_empty() {
+                continue;
+            }
+
+            match field {
+                MetadataField::Artist => metadata.artist = Some(value.to_string()),
+                MetadataField::AlbumArtist => metadata.album_artist = Some(value.to_string()),
+                MetadataField::Album => metadata.album = Some(value.to_string()),
+                MetadataField::Title => metadata.title = Some(value.to_string()),
+                MetadataField::Track => metadata.track = value.parse().ok(),
+                MetadataField::Year => metadata.year = value.parse().ok(),
+            }
+        }
+
+        metadata
+    }
+}
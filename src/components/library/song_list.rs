@@ -2,4 +2,4 @@ mod component;
 mod widget;
 mod keyboard_handler;
 
-pub use component::SongList;
+pub use component::{SongList, SongListRow};
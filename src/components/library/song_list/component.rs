@@ -1,29 +1,64 @@
 use std::{
-    cmp::Ordering,
-    collections::{HashMap, HashSet},
+    collections::HashSet,
     sync::{
         atomic::{AtomicUsize, Ordering as AtomicOrdering},
         Mutex,
-        Arc,
     },
-    path::PathBuf,
+    time::Duration,
 };
 
 use crossterm::event::KeyEvent;
 
 use crate::{
-    structs::{Song},
+    structs::{guess_from_path, Song, SongSort, SongSortKey, SongStats},
     config::Theme,
-    cue::CueSheet,
 };
 
+/// Albums are told apart by (album) artist and name, since different artists may have albums with the same name.
+pub type AlbumKey = (Option<String>, Option<String>);
+
+/// Songs without an artist are grouped by the album they're tagged with, or the one guessed from their folder.
+pub fn album_key(song: &Song) -> AlbumKey {
+    match song.library_artist() {
+        Some(artist) => (Some(artist), song.album.clone()),
+        None => (None, song.album.clone().or_else(|| guess_from_path(&song.path).album)),
+    }
+}
+
+/// A row of the song list: the header of an album, or one of its songs.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SongListRow {
+    Album {
+        key: AlbumKey,
+        /// For albums without an artist, the one guessed from the path of their songs.
+        guessed_artist: Option<String>,
+        year: Option<u32>,
+        genre: Option<String>,
+        label: Option<String>,
+        song_count: usize,
+        length: Duration,
+        collapsed: bool,
+    },
+    /// Index of the song in the list of songs.
+    Song(usize),
+}
+
 pub struct SongList<'a> {
     pub(super) theme: Theme,
 
     pub(super) songs: Mutex<Vec<Song>>,
-    pub(super) selected_song_index: AtomicUsize,
+    /// Text shown next to each song, if any.
+    pub(super) notes: Mutex<Vec<String>>,
+    /// Play counts and favorites of each song.
+    pub(super) stats: Mutex<Vec<SongStats>>,
+    /// Whether the file of each song is missing, for them to be greyed out.
+    pub(super) missing: Mutex<Vec<bool>>,
+    pub(super) sort: Mutex<SongSort>,
+    pub(super) rows: Mutex<Vec<SongListRow>>,
+    pub(super) collapsed_albums: Mutex<HashSet<AlbumKey>>,
+    pub(super) selected_index: AtomicUsize,
 
-    pub(super) on_select_fn: Mutex<Box<dyn FnMut((Song, KeyEvent)) + 'a>>,
+    pub(super) on_select_fn: Mutex<Box<dyn FnMut((Vec<Song>, KeyEvent)) + 'a>>,
 
     pub(super) offset: AtomicUsize,
     pub(super) height: AtomicUsize,
@@ -37,19 +72,206 @@ impl<'a> SongList<'a> {
             on_select_fn: Mutex::new(Box::new(|_| {}) as _),
 
             songs: Mutex::new(Vec::new()),
-            selected_song_index: AtomicUsize::new(0),
+            notes: Mutex::new(Vec::new()),
+            stats: Mutex::new(Vec::new()),
+            missing: Mutex::new(Vec::new()),
+            sort: Mutex::new(SongSort::by(SongSortKey::Album)),
+            rows: Mutex::new(Vec::new()),
+            collapsed_albums: Mutex::new(HashSet::new()),
+            selected_index: AtomicUsize::new(0),
 
             offset: AtomicUsize::new(0),
             height: AtomicUsize::new(0),
         }
     }
 
-    pub fn on_select(&self, cb: impl FnMut((Song, KeyEvent)) + 'a) {
+    pub fn on_select(&self, cb: impl FnMut((Vec<Song>, KeyEvent)) + 'a) {
         *self.on_select_fn.lock().unwrap() = Box::new(cb);
     }
 
-    pub fn set_songs(&self, songs: Vec<Song>) {
+    /// Sets the songs, along with their stats, one for each song.
+    pub fn set_songs(&self, songs: Vec<Song>, stats: Vec<SongStats>) {
+        self.set_songs_with_notes(songs, stats, vec![]);
+    }
+
+    /// Sets the songs, along with their stats and a note shown next to each one of them.
+    pub fn set_songs_with_notes(&self, songs: Vec<Song>, stats: Vec<SongStats>, notes: Vec<String>) {
         *self.songs.lock().unwrap() = songs;
+        *self.stats.lock().unwrap() = stats;
+        *self.notes.lock().unwrap() = notes;
+        self.missing.lock().unwrap().clear();
+        self.refresh_rows();
+    }
+
+    /// Marks the songs whose files are missing, one flag for each song.
+    pub fn set_missing(&self, missing: Vec<bool>) {
+        *self.missing.lock().unwrap() = missing;
+    }
+
+    pub fn sort(&self) -> SongSort {
+        *self.sort.lock().unwrap()
+    }
+
+    pub fn set_sort(&self, sort: SongSort) {
+        *self.sort.lock().unwrap() = sort;
+
+        self.refresh_rows();
+        self.select_first();
+    }
+
+    pub fn next_sort(&self) {
+        self.set_sort(self.sort().next(false));
+    }
+
+    pub fn reverse_sort(&self) {
+        self.set_sort(self.sort().reversed());
+    }
+
+    pub fn select_first(&self) {
+        self.selected_index.store(0, AtomicOrdering::SeqCst);
+        self.offset.store(0, AtomicOrdering::SeqCst);
+    }
+
+    /// Selects the given song, expanding its album if it's collapsed.
+    pub fn select_song(&self, song: &Song) {
+        let songs = self.songs.lock().unwrap();
+        let Some(song_index) = songs.iter().position(|s| s.path == song.path && s.start_time == song.start_time) else {
+            return;
+        };
+
+        self.collapsed_albums.lock().unwrap().remove(&album_key(&songs[song_index]));
+        drop(songs);
+
+        self.refresh_rows();
+
+        let rows = self.rows.lock().unwrap();
+        if let Some(i) = rows.iter().position(|row| *row == SongListRow::Song(song_index)) {
+            self.selected_index.store(i, AtomicOrdering::SeqCst);
+        }
+    }
+
+    /// Rebuilds the rows of the list, with a header before the songs of each album. Songs are expected to be sorted
+    /// by album already, so sorting by album the other way round only reverses the order of the albums. Sorted by
+    /// anything else, there are no headers.
+    pub(super) fn refresh_rows(&self) {
+        let songs = self.songs.lock().unwrap();
+        let collapsed_albums = self.collapsed_albums.lock().unwrap();
+        let sort = *self.sort.lock().unwrap();
+
+        let mut rows = vec![];
+        let mut i = 0;
+
+        if sort.key != SongSortKey::Album {
+            // Stable, so songs that compare equal stay in album order
+            let indexes = sort.sorted_indexes(&songs, &self.stats.lock().unwrap());
+            rows.extend(indexes.into_iter().map(SongListRow::Song));
+        }
+
+        let mut albums = vec![];
+
+        while sort.key == SongSortKey::Album && i < songs.len() {
+            let key = album_key(&songs[i]);
+            let album_len = songs[i..].iter().take_while(|s| album_key(s) == key).count();
+            let album_songs = &songs[i..i + album_len];
+            let collapsed = collapsed_albums.contains(&key);
+
+            let guessed_artist = match key.0 {
+                Some(_) => None,
+                None => guess_from_path(&songs[i].path).artist,
+            };
+
+            let mut album_rows = vec![SongListRow::Album {
+                key,
+                guessed_artist,
+                year: album_songs.iter().find_map(|s| s.year),
+                genre: album_songs.iter().find_map(|s| s.genre.clone()),
+                label: album_songs.iter().find_map(|s| s.label.clone()),
+                song_count: album_len,
+                length: album_songs.iter().map(|s| s.length).sum(),
+                collapsed,
+            }];
+
+            if !collapsed {
+                album_rows.extend((i..i + album_len).map(SongListRow::Song));
+            }
+
+            albums.push(album_rows);
+            i += album_len;
+        }
+
+        if sort.descending {
+            albums.reverse();
+        }
+
+        rows.extend(albums.into_iter().flatten());
+
+        let len = rows.len();
+        *self.rows.lock().unwrap() = rows;
+
+        let _ = self.selected_index.fetch_update(AtomicOrdering::SeqCst, AtomicOrdering::SeqCst, |i| {
+            Some(i.min(len.saturating_sub(1)))
+        });
+
+        let height = self.height.load(AtomicOrdering::SeqCst);
+        let _ = self.offset.fetch_update(AtomicOrdering::SeqCst, AtomicOrdering::SeqCst, |offset| {
+            Some(offset.min(len.saturating_sub(height)))
+        });
+    }
+
+    pub fn selected_row(&self) -> Option<SongListRow> {
+        let rows = self.rows.lock().unwrap();
+        rows.get(self.selected_index.load(AtomicOrdering::SeqCst)).cloned()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.songs.lock().unwrap().is_empty()
+    }
+
+    /// The selected song or, if an album header is selected, the first song of the album.
+    pub fn selected_song(&self) -> Option<Song> {
+        self.selected_songs().into_iter().next()
+    }
+
+    /// The selected song or, if an album header is selected, all the songs of the album.
+    pub fn selected_songs(&self) -> Vec<Song> {
+        let songs = self.songs.lock().unwrap();
+
+        match self.selected_row() {
+            Some(SongListRow::Song(i)) => songs.get(i).cloned().into_iter().collect(),
+            Some(SongListRow::Album { key, .. }) => songs.iter().filter(|s| album_key(s) == key).cloned().collect(),
+            None => vec![],
+        }
+    }
+
+    /// Collapses the album of the selected row, or expands it if it's collapsed, and selects the album's header.
+    pub fn toggle_selected_album(&self) {
+        if self.sort().key != SongSortKey::Album {
+            return;
+        }
+
+        let key = match self.selected_row() {
+            Some(SongListRow::Album { key, .. }) => key,
+            Some(SongListRow::Song(i)) => match self.songs.lock().unwrap().get(i) {
+                Some(song) => album_key(song),
+                None => return,
+            },
+            None => return,
+        };
+
+        let mut collapsed_albums = self.collapsed_albums.lock().unwrap();
+        if !collapsed_albums.remove(&key) {
+            collapsed_albums.insert(key.clone());
+        }
+        drop(collapsed_albums);
+
+        self.refresh_rows();
+
+        let rows = self.rows.lock().unwrap();
+        let header = rows.iter().position(|row| matches!(row, SongListRow::Album { key: k, .. } if *k == key));
+
+        if let Some(i) = header {
+            self.selected_index.store(i, AtomicOrdering::SeqCst);
+        }
     }
 }
 
@@ -1,12 +1,9 @@
 use std::sync::atomic::Ordering;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
-use crate::{
-    structs::Song,
-    ui::KeyboardHandlerRef,
-};
+use crate::ui::KeyboardHandlerRef;
 
-use super::component::SongList;
+use super::component::{SongList, SongListRow};
 
 impl<'a> KeyboardHandlerRef<'a> for SongList<'a> {
 
@@ -15,18 +12,27 @@ impl<'a> KeyboardHandlerRef<'a> for SongList<'a> {
             KeyCode::Up | KeyCode::Down | KeyCode::Home | KeyCode::End => {
                 self.on_song_list_directional_key(key);
             },
+            KeyCode::Char(' ') => {
+                self.toggle_selected_album();
+            },
+            KeyCode::Char('o') => {
+                self.next_sort();
+            },
+            KeyCode::Char('O') => {
+                self.reverse_sort();
+            },
             KeyCode::Enter | KeyCode::Char(_) => {
-                let songs = &self.songs.lock().unwrap();
-                let len = songs.len();
+                let songs = self.selected_songs();
 
-                let i = self.selected_song_index.load(Ordering::SeqCst);
-                if i >= songs.len() {
-                    log::error!("library on_key_event_song_list enter: selected_song_index > song_list.len");
+                if songs.is_empty() {
+                    log::error!("library on_key_event_song_list enter: no song selected");
                     return true;
                 }
-                let song = songs[self.selected_song_index.load(Ordering::SeqCst)].clone();
-                self.on_select_fn.lock().unwrap()((song, key));
-                let _ = self.selected_song_index.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |a| { Some(a.saturating_add(1).min(len.saturating_sub(1))) });
+
+                self.on_select_fn.lock().unwrap()((songs, key));
+
+                let len = self.rows.lock().unwrap().len();
+                let _ = self.selected_index.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |a| { Some(a.saturating_add(1).min(len.saturating_sub(1))) });
             },
             _ => {},
         }
@@ -39,14 +45,14 @@ impl<'a> KeyboardHandlerRef<'a> for SongList<'a> {
 impl<'a> SongList<'a> {
 
     fn on_song_list_directional_key(&self, key: KeyEvent) {
-        let songs = self.songs.lock().unwrap();
-        let length = songs.len() as i32;
+        let rows = self.rows.lock().unwrap();
+        let length = rows.len() as i32;
 
         let height = self.height.load(Ordering::Relaxed) as i32;
         let padding = 5;
 
         let mut offset = self.offset.load(Ordering::SeqCst) as i32;
-        let mut i = self.selected_song_index.load(Ordering::SeqCst) as i32;
+        let mut i = self.selected_index.load(Ordering::SeqCst) as i32;
 
         match key.code {
             KeyCode::Up | KeyCode::Down => {
@@ -57,7 +63,7 @@ impl<'a> SongList<'a> {
                         i += 1;
                     }
                 } else if key.modifiers == KeyModifiers::ALT {
-                    if let Some(next) = next_index_by_album(&*songs, i, key.code) {
+                    if let Some(next) = next_album_index(&rows, i as usize, key.code) {
                         i = next as i32;
                     }
                 } else {
@@ -94,43 +100,25 @@ impl<'a> SongList<'a> {
         i = i.min(length - 1).max(0);
 
         self.offset.store(offset as usize, Ordering::SeqCst);
-        self.selected_song_index.store(i as usize, Ordering::SeqCst);
+        self.selected_index.store(i as usize, Ordering::SeqCst);
     }
 
 }
 
-fn next_index_by_album(songs: &Vec<Song>, i: i32, key: KeyCode) -> Option<usize> {
-    let Some(song) = (*songs).get(i as usize) else {
-        log::error!("no selected song");
-        return None;
-    };
-
-    let Some(ref selected_album) = song.album else {
-        log::warn!("no selected song album");
-        return None;
-    };
+/// The index of the header of the next or previous album, depending on the key.
+fn next_album_index(rows: &[SongListRow], i: usize, key: KeyCode) -> Option<usize> {
+    let is_album = |row: &SongListRow| matches!(row, SongListRow::Album { .. });
 
-    let next_song_index = if key == KeyCode::Down {
-        songs
+    if key == KeyCode::Down {
+        rows
             .iter()
-            .skip(i as usize)
-            .position(|s| s.album.as_ref().is_some_and(|a| a != selected_album))
-            .map(|ns| ns.saturating_add(i as usize))
+            .skip(i + 1)
+            .position(is_album)
+            .map(|next| next + i + 1)
     } else {
-        songs
+        rows
             .iter()
-            .take(i as usize)
-            .rposition(|s| s.album.as_ref().is_some_and(|a| a != selected_album))
-            .and_then(|ns| songs.get(ns))
-            .and_then(|ref s| s.album.as_ref())
-            .and_then(|next_song_album| {
-                songs
-                    .iter()
-                    .position(|song| {
-                        song.album.as_ref().is_some_and(|a| a.as_str() == next_song_album)
-                    })
-            })
-    };
-
-    next_song_index
+            .take(i)
+            .rposition(is_album)
+    }
 }
@@ -8,7 +8,12 @@ use ratatui::{
     widgets::{WidgetRef},
 };
 
-use super::component::SongList;
+use crate::{
+    structs::{unix_now, SongSort, SongSortKey},
+    ui::duration_to_string,
+};
+
+use super::component::{SongList, SongListRow};
 
 fn line_style(theme: &crate::config::Theme, index: usize, selected_index: usize, list_has_focus: bool) -> Style {
     if index == selected_index {
@@ -30,42 +35,120 @@ impl<'a> Widget for SongList<'a> {
 
 impl<'a> WidgetRef for SongList<'a> {
     fn render_ref(&self, area: Rect, buf: &mut Buffer) {
+        let sort = *self.sort.lock().unwrap();
+
+        // Songs sorted by anything but album aren't grouped by album, so the list says what they're sorted by instead
+        let area = if sort != SongSort::by(SongSortKey::Album) && area.height > 1 {
+            ratatui::text::Line::from(format!("Sorted by {}", sort.name()))
+                .style(Style::default().fg(self.theme.foreground).bg(self.theme.background))
+                .render_ref(Rect { height: 1, ..area }, buf);
+
+            Rect { y: area.y + 1, height: area.height - 1, ..area }
+        } else {
+            area
+        };
+
         self.height.store(area.height as usize, Ordering::Relaxed);
 
-        let songs = &self.songs.lock().unwrap();
+        let songs = self.songs.lock().unwrap();
+        let notes = self.notes.lock().unwrap();
+        let stats = self.stats.lock().unwrap();
+        let missing = self.missing.lock().unwrap();
+        let rows = self.rows.lock().unwrap();
 
-        if songs.len() < 1 {
+        if rows.len() < 1 {
             return;
         }
 
-        let selected_song_index = self.selected_song_index.load(Ordering::Relaxed);
-        let offset = self.offset.load(Ordering::Relaxed);
+        let now = unix_now();
+
+        let selected_index = self.selected_index.load(Ordering::Relaxed);
 
-        for i in 0..songs.len().min(area.height as usize) {
-            let song_index = i + offset;
+        // Keep the selected row visible
+        let height = area.height as usize;
+        let mut offset = self.offset.load(Ordering::Relaxed);
+        if selected_index < offset {
+            offset = selected_index;
+        } else if height > 0 && selected_index >= offset + height {
+            offset = selected_index + 1 - height;
+        }
+        self.offset.store(offset, Ordering::Relaxed);
 
-            if song_index >= songs.len() {
-                log::error!("song index {song_index} > song_list.len() {} offset={offset}", songs.len());
-                break;
-            }
+        // Albums only need their artist shown when the list has songs of more than one artist
+        let show_artist = songs.iter().any(|s| s.library_artist() != songs[0].library_artist());
 
-            let song = &songs[song_index];
+        for (i, row) in rows.iter().enumerate().skip(offset).take(height) {
             let area = Rect {
-                y: area.y + i as u16,
+                y: area.y + (i - offset) as u16,
                 height: 1,
                 ..area
             };
 
-            let style = line_style(&self.theme, song_index, selected_song_index, true);
-            let line = ratatui::text::Line::from(
-                format!("{} - {} - {}",
-                        song.album.clone().unwrap_or("(no album)".to_string()),
-                        song.track.unwrap_or(0),
-                        song.title.clone()
-                ),
-            ).style(style);
+            let style = line_style(&self.theme, i, selected_index, true);
+
+            let text = match row {
+                SongListRow::Album { key: (artist, album), guessed_artist, year, genre, label, song_count, length, collapsed } => {
+                    let arrow = if *collapsed { '▸' } else { '▾' };
+                    let album = album.clone().unwrap_or("(no album)".to_string());
+                    let album = match (show_artist, artist, guessed_artist) {
+                        (true, Some(artist), _) => format!("{artist} - {album}"),
+                        (_, None, Some(guessed_artist)) => format!("{guessed_artist}? - {album}"),
+                        _ => album,
+                    };
+                    let release = match (year, label) {
+                        (Some(year), Some(label)) => format!(" ({year}, {label})"),
+                        (Some(year), None) => format!(" ({year})"),
+                        (None, Some(label)) => format!(" ({label})"),
+                        (None, None) => String::new(),
+                    };
+                    let genre = genre.as_ref().map(|g| format!(" · {g}")).unwrap_or_default();
+                    let tracks = if *song_count == 1 { "track" } else { "tracks" };
+
+                    format!("{arrow} {album}{release}{genre} · {song_count} {tracks} · {}", duration_to_string(*length))
+                }
+                SongListRow::Song(song_index) => {
+                    let Some(song) = songs.get(*song_index) else {
+                        log::error!("song index {song_index} > song_list.len() {}", songs.len());
+                        break;
+                    };
+
+                    // Songs of compilations and guest appearances show their own artist, and so do all songs when
+                    // they aren't grouped by album
+                    let text = match &song.artist {
+                        Some(artist) if sort.key != SongSortKey::Album => format!("    {} - {}", artist, song.title),
+                        Some(artist) if song.library_artist().as_ref() != Some(artist) => {
+                            format!("    {} - {} - {}", song.track_label(), artist, song.title)
+                        }
+                        _ => format!("    {} - {}", song.track_label(), song.title),
+                    };
+
+                    // Mostly for classical music, where the composer matters as much as the performer
+                    let text = match &song.composer {
+                        Some(composer) if song.artist.as_ref() != Some(composer) => format!("{text} · {composer}"),
+                        _ => text,
+                    };
+
+                    let text = match stats.get(*song_index).map(|s| s.summary(now)) {
+                        Some(summary) if !summary.is_empty() => format!("{text}  {summary}"),
+                        _ => text,
+                    };
+
+                    match notes.get(*song_index) {
+                        Some(note) if !note.is_empty() => format!("{text}  ⚠ {note}"),
+                        _ => text,
+                    }
+                }
+            };
+
+            let style = match row {
+                SongListRow::Album { .. } if i != selected_index => style.fg(self.theme.foreground),
+                SongListRow::Song(song_index) if i != selected_index && missing.get(*song_index) == Some(&true) => {
+                    style.fg(Color::DarkGray)
+                }
+                _ => style,
+            };
 
-            line.render_ref(area, buf);
+            ratatui::text::Line::from(text).style(style).render_ref(area, buf);
         }
     }
 }
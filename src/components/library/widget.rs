@@ -8,7 +8,12 @@ use ratatui::{
     widgets::{WidgetRef},
 };
 
-use super::{Library, LibraryScreenElement};
+use crate::{cover_art::CoverSlot, fingerprint::SAME_RECORDING, structs::SongId, ui::duration_to_string};
+
+use super::{duplicate_review::DuplicateRow, ArtistListItem, Library, LibraryScreenElement};
+
+/// In rows. Covers are about twice as wide, in columns, since cells are about twice as tall as they're wide.
+const COVER_HEIGHT: u16 = 8;
 
 impl<'a> Widget for Library<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
@@ -18,6 +23,32 @@ impl<'a> Widget for Library<'a> {
 
 impl<'a> WidgetRef for Library<'a> {
     fn render_ref(&self, area: Rect, buf: &mut Buffer) {
+        let area = if self.metadata_edit.lock().unwrap().is_some() {
+            let [area, _, area_edit] = Layout::vertical([
+                Constraint::Min(1),
+                Constraint::Length(1),
+                Constraint::Length(2),
+            ])
+                .areas(area);
+
+            self.render_ref_metadata_edit(area_edit, buf);
+            area
+        } else if let Some(root) = self.relink_root.lock().unwrap().as_ref() {
+            let [area, _, area_prompt] = Layout::vertical([
+                Constraint::Min(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+            ])
+                .areas(area);
+
+            ratatui::text::Line::from(format!("Search for missing files in: {root}⎸"))
+                .style(Style::default().fg(self.theme.foreground_selected).bg(self.theme.search))
+                .render_ref(area_prompt.inner(ratatui::layout::Margin { horizontal: 2, vertical: 0 }), buf);
+            area
+        } else {
+            area
+        };
+
         let [area_left, _, area_right] = Layout::horizontal([
             Constraint::Percentage(50),
             Constraint::Length(5),
@@ -29,7 +60,36 @@ impl<'a> WidgetRef for Library<'a> {
         self.height.store(area.height as usize, Ordering::Relaxed);
 
         self.render_ref_artists(area_left, buf);
-        self.song_list.lock().unwrap().render_ref(area_right, buf);
+
+        if self.selected_item() == Some(ArtistListItem::Duplicates) {
+            self.render_ref_duplicates(area_right, buf);
+            return;
+        }
+
+        let song_list = self.song_list.lock().unwrap();
+
+        if self.selected_item() == Some(ArtistListItem::LibraryHealth) && song_list.is_empty() {
+            ratatui::text::Line::from("No tag issues found").style(Style::default().fg(self.theme.foreground_secondary)).render_ref(area_right, buf);
+            return;
+        }
+
+        // The cover of the selected album goes above its songs
+        match song_list.selected_song().filter(|song| self.cover_art.has_cover(song)) {
+            Some(song) if area_right.height > COVER_HEIGHT * 3 => {
+                let [area_cover, _, area_songs] = Layout::vertical([
+                    Constraint::Length(COVER_HEIGHT),
+                    Constraint::Length(1),
+                    Constraint::Min(0),
+                ])
+                    .areas(area_right);
+
+                let area_cover = Rect { width: area_cover.width.min(COVER_HEIGHT * 2), ..area_cover };
+
+                self.cover_art.render(CoverSlot::Library, &song, area_cover, buf);
+                song_list.render_ref(area_songs, buf);
+            }
+            _ => song_list.render_ref(area_right, buf),
+        }
     }
 }
 
@@ -46,23 +106,160 @@ fn line_style(theme: &crate::config::Theme, index: usize, selected_index: usize,
 }
 
 impl<'a> Library<'a> {
+    fn render_ref_metadata_edit(&self, area: Rect, buf: &mut Buffer) {
+        let metadata_edit = self.metadata_edit.lock().unwrap();
+        let Some(edit) = metadata_edit.as_ref() else {
+            return;
+        };
+
+        let area = area.inner(ratatui::layout::Margin { horizontal: 2, vertical: 0 });
+
+        let mut spans = vec![ratatui::text::Span::raw(format!("Editing {}  ", edit.title()))
+            .style(Style::default().fg(self.theme.foreground_secondary))];
+
+        for (i, (field, _, _)) in edit.fields.iter().enumerate() {
+            let style = if i == edit.selected_field {
+                Style::default().fg(self.theme.foreground_selected).bg(self.theme.background_selected)
+            } else {
+                Style::default().fg(self.theme.foreground_secondary)
+            };
+            spans.push(ratatui::text::Span::raw(format!(" {} ", field.name())).style(style));
+        }
+
+        ratatui::text::Line::from(spans).render_ref(Rect { height: 1, ..area }, buf);
+
+        let (field, _, value) = &edit.fields[edit.selected_field];
+        ratatui::text::Line::from(format!("{}: {value}⎸", field.name()))
+            .style(Style::default().fg(self.theme.foreground_selected).bg(self.theme.search))
+            .render_ref(Rect { y: area.y + 1, height: 1, ..area }, buf);
+    }
+
+    fn render_ref_duplicates(&self, area: Rect, buf: &mut Buffer) {
+        let mut review = self.duplicate_review.lock().unwrap();
+        let has_focus = *self.focused_element.lock().unwrap() == LibraryScreenElement::SongList;
+
+        if review.rows.is_empty() {
+            ratatui::text::Line::from("No duplicates found").style(Style::default().fg(self.theme.foreground_secondary)).render_ref(area, buf);
+            return;
+        }
+
+        // Keep the selected row visible
+        let height = area.height as usize;
+        if review.selected_index < review.offset {
+            review.offset = review.selected_index;
+        } else if height > 0 && review.selected_index >= review.offset + height {
+            review.offset = review.selected_index + 1 - height;
+        }
+
+        for (i, row) in review.rows.iter().enumerate().skip(review.offset).take(height) {
+            let area = Rect {
+                y: area.y + (i - review.offset) as u16,
+                height: 1,
+                ..area
+            };
+
+            let (text, style) = match *row {
+                DuplicateRow::Group(g) => {
+                    let song = &review.groups[g][0];
+                    let artist = song.artist.as_deref().or(song.album_artist.as_deref()).unwrap_or("(no artist)");
+                    let hidden = review.groups[g].iter().filter(|s| self.is_hidden(s)).count();
+                    let hidden = if hidden > 0 { format!(", {hidden} hidden") } else { String::new() };
+                    let text = format!("{artist} - {} · {} copies{hidden}", song.title, review.groups[g].len());
+                    let style = line_style(&self.theme, i, review.selected_index, has_focus);
+                    let style = if i == review.selected_index { style } else { style.fg(self.theme.foreground) };
+
+                    (text, style)
+                }
+                DuplicateRow::Song(g, s) => {
+                    let song = &review.groups[g][s];
+                    let album = song.album.as_ref().map(|a| format!(" · {a}")).unwrap_or_default();
+                    let similarity = match review.similarities.lock().unwrap().get(&SongId::of(song)) {
+                        Some(Some(s)) if *s >= SAME_RECORDING => format!(" · same recording ({:.0}%)", s * 100.),
+                        Some(Some(s)) => format!(" · different recording ({:.0}%)", s * 100.),
+                        Some(None) => " · comparing…".to_string(),
+                        None => String::new(),
+                    };
+                    // Tracks of a cue sheet share their file, so they're told apart by where they start in it
+                    let start = if song.start_time.is_zero() {
+                        String::new()
+                    } else {
+                        format!(" @ {}", duration_to_string(song.start_time))
+                    };
+                    let hidden = if self.is_hidden(song) { " · hidden" } else { "" };
+                    let text = format!(
+                        "    {}{start}{album} · {}{similarity}{hidden}",
+                        song.path.display(),
+                        duration_to_string(song.length),
+                    );
+
+                    (text, line_style(&self.theme, i, review.selected_index, has_focus))
+                }
+            };
+
+            ratatui::text::Line::from(text).style(style).render_ref(area, buf);
+        }
+    }
+
     fn render_ref_artists(&self, area: Rect, buf: &mut Buffer) {
         self.height.store(area.height as usize, Ordering::Relaxed);
 
         let focused_element = self.focused_element.lock().unwrap();
         let selected_artist_index = self.selected_artist_index.load(Ordering::Relaxed);
-        let artists = self.artists.lock().unwrap();
+        let folder_rename = self.folder_rename.lock().unwrap().clone();
+        let folder_rename_error = self.folder_rename_error();
+        let is_renaming = folder_rename.is_some();
+        let tree = self.tree.lock().unwrap();
+        let collapsed_folders = self.collapsed_folders.lock().unwrap();
 
-        for i in 0..artists.len() {
-            let artist = artists[i].as_str();
+        // Keep the selected item visible
+        let height = area.height as usize;
+        let mut offset = self.offset.load(Ordering::Relaxed);
+        if selected_artist_index < offset {
+            offset = selected_artist_index;
+        } else if height > 0 && selected_artist_index >= offset + height {
+            offset = selected_artist_index + 1 - height;
+        }
+        offset = offset.min(tree.len().saturating_sub(height));
+        self.offset.store(offset, Ordering::Relaxed);
+
+        for (i, (depth, item)) in tree.iter().enumerate().skip(offset).take(height) {
             let area = Rect {
-                y: area.y + i as u16,
+                y: area.y + (i - offset) as u16,
                 height: 1,
                 ..area
             };
 
-            let style = line_style(&self.theme, i, selected_artist_index, *focused_element == LibraryScreenElement::ArtistList);
-            let line = ratatui::text::Line::from(artist).style(style);
+            let indentation = "    ".repeat(*depth);
+            let text = match item {
+                ArtistListItem::Folder(name) => {
+                    let arrow = if collapsed_folders.contains(name) { '▸' } else { '▾' };
+
+                    match folder_rename.as_ref().filter(|_| i == selected_artist_index) {
+                        Some(new_name) => {
+                            let error = folder_rename_error.map(|e| format!("  ({e})")).unwrap_or_default();
+                            format!("{indentation}{arrow} {new_name}⎸{error}")
+                        }
+                        None => format!("{indentation}{arrow} {name}"),
+                    }
+                }
+                ArtistListItem::Artist(artist) => format!("{indentation}{artist}"),
+                ArtistListItem::Album(album) => format!("{indentation}{} - {}", album.artist, album.album),
+                ArtistListItem::Compilations => format!("{indentation}Compilations"),
+                ArtistListItem::UnknownArtist => format!("{indentation}Unknown Artist"),
+                ArtistListItem::LibraryHealth => format!("{indentation}Library Health"),
+                ArtistListItem::Duplicates => format!("{indentation}Duplicates"),
+                ArtistListItem::MissingFiles => format!("{indentation}Missing Files"),
+                ArtistListItem::RecentlyRemoved => format!("{indentation}Recently Removed"),
+                ArtistListItem::Genre(genre) => format!("{indentation}{genre}"),
+                ArtistListItem::Year(year) => format!("{indentation}{year}"),
+            };
+
+            let style = if is_renaming && i == selected_artist_index {
+                Style::default().fg(self.theme.foreground_selected).bg(self.theme.search)
+            } else {
+                line_style(&self.theme, i, selected_artist_index, *focused_element == LibraryScreenElement::ArtistList)
+            };
+            let line = ratatui::text::Line::from(text).style(style);
 
             line.render_ref(area, buf);
         }
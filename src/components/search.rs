@@ -0,0 +1,7 @@
+pub mod search;
+pub mod widget;
+pub mod keyboard_handler;
+
+mod fuzzy;
+
+pub use search::*;
@@ -0,0 +1,90 @@
+/// Scores how well `query` matches `text`, or returns `None` if it doesn't match at all.
+///
+/// Every character of the query must appear in the text, in order, ignoring case. Matches score higher the more
+/// their characters are consecutive and at the start of words, and the earlier in the text they start.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let mut score = 0;
+    let mut query_index = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for (i, c) in text.iter().enumerate() {
+        if query_index == query.len() {
+            break;
+        }
+
+        if *c != query[query_index] {
+            continue;
+        }
+
+        let is_word_start = i == 0 || !text[i - 1].is_alphanumeric();
+
+        score += 1;
+
+        if is_word_start {
+            score += 8;
+        }
+
+        match previous_match {
+            Some(previous) if previous + 1 == i => score += 5,
+            Some(previous) => score -= (i - previous - 1).min(5) as i64,
+            None => score -= i.min(10) as i64,
+        }
+
+        previous_match = Some(i);
+        query_index += 1;
+    }
+
+    if query_index == query.len() {
+        Some(score)
+    } else {
+        None
+    }
+}
+
+/// Scores a query with multiple, whitespace-separated terms. Every term must match at least one of the fields,
+/// and the score is the sum of the best score of each term.
+pub fn fuzzy_score_fields(query: &str, fields: &[&str]) -> Option<i64> {
+    query
+        .split_whitespace()
+        .map(|term| fields.iter().filter_map(|field| fuzzy_score(term, field)).max())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_subsequences_ignoring_case() {
+        assert!(fuzzy_score("pf", "Pink Floyd").is_some());
+        assert!(fuzzy_score("FLOYD", "Pink Floyd").is_some());
+        assert!(fuzzy_score("dlf", "Pink Floyd").is_none());
+        assert!(fuzzy_score("", "Pink Floyd").is_some());
+    }
+
+    #[test]
+    fn ranks_consecutive_and_word_start_matches_higher() {
+        let consecutive = fuzzy_score("wish", "Wish You Were Here").unwrap();
+        let scattered = fuzzy_score("wish", "Wasn't It Somehow Here").unwrap();
+        assert!(consecutive > scattered);
+
+        let word_start = fuzzy_score("dogs", "Dogs").unwrap();
+        let middle = fuzzy_score("dogs", "Underdogs").unwrap();
+        assert!(word_start > middle);
+    }
+
+    #[test]
+    fn every_term_must_match_some_field() {
+        let fields = ["Pink Floyd", "Animals", "Dogs"];
+
+        assert!(fuzzy_score_fields("floyd dogs", &fields).is_some());
+        assert!(fuzzy_score_fields("floyd sheep", &fields).is_none());
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::atomic::Ordering;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::ui::KeyboardHandlerRef;
+
+use super::Search;
+
+impl<'a> KeyboardHandlerRef<'a> for Search<'a> {
+    fn on_key(&self, key: KeyEvent) -> bool {
+        if self.is_typing() {
+            self.on_typing_key_event(key)
+        } else {
+            self.on_results_key_event(key)
+        }
+    }
+}
+
+impl<'a> Search<'a> {
+    fn on_typing_key_event(&self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc => {
+                self.typing.store(false, Ordering::Relaxed);
+            }
+            KeyCode::Enter => {
+                self.select(key);
+            }
+            KeyCode::Down => {
+                self.select_next();
+            }
+            KeyCode::Up => {
+                self.select_previous();
+            }
+            KeyCode::Backspace => {
+                let mut query = self.query.lock().unwrap();
+                if key.modifiers == KeyModifiers::ALT {
+                    query.clear();
+                } else {
+                    query.pop();
+                }
+                drop(query);
+                self.refresh_results();
+            }
+            KeyCode::Char(char) if key.modifiers == KeyModifiers::NONE || key.modifiers == KeyModifiers::SHIFT => {
+                self.query.lock().unwrap().push(char);
+                self.refresh_results();
+            }
+            _ => {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn on_results_key_event(&self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Down => {
+                self.select_next();
+            }
+            KeyCode::Up => {
+                self.select_previous();
+            }
+            KeyCode::Home => {
+                self.selected_index.store(0, Ordering::Relaxed);
+            }
+            KeyCode::End => {
+                let len = self.results.lock().unwrap().len();
+                self.selected_index.store(len.saturating_sub(1), Ordering::Relaxed);
+            }
+            KeyCode::Char('/') => {
+                self.typing.store(true, Ordering::Relaxed);
+            }
+            KeyCode::Char('f') if key.modifiers == KeyModifiers::CONTROL => {
+                self.typing.store(true, Ordering::Relaxed);
+            }
+            KeyCode::Char('g') if key.modifiers == KeyModifiers::NONE => {
+                *self.jump_target.lock().unwrap() = self.selected_result();
+            }
+            KeyCode::Enter | KeyCode::Char('a') | KeyCode::Char('y') => {
+                self.select(key);
+            }
+            _ => {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn select(&self, key: KeyEvent) {
+        if let Some(result) = self.selected_result() {
+            self.on_select_fn.lock().unwrap()((result.song, key));
+        }
+    }
+
+    fn select_next(&self) {
+        let len = self.results.lock().unwrap().len();
+        let _ = self.selected_index.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |i| {
+            Some(i.saturating_add(1).min(len.saturating_sub(1)))
+        });
+    }
+
+    fn select_previous(&self) {
+        let _ = self.selected_index.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |i| Some(i.saturating_sub(1)));
+    }
+}
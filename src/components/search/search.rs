@@ -0,0 +1,156 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc,
+    Mutex,
+};
+
+use crossterm::event::KeyEvent;
+
+use crate::{
+    config::Theme,
+    structs::Song,
+    ui::Playlists,
+};
+
+use super::{
+    super::Library,
+    fuzzy::fuzzy_score_fields,
+};
+
+/// More results than fit on any screen, but few enough to keep typing snappy.
+const MAX_RESULTS: usize = 500;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SearchResultSource {
+    Library,
+    /// The name of the playlist the song is in.
+    Playlist(String),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SearchResult {
+    pub song: Song,
+    pub source: SearchResultSource,
+}
+
+/// Fuzzy search across the songs of the whole library and all playlists.
+pub struct Search<'a> {
+    pub(super) theme: Theme,
+
+    library: Arc<Library<'a>>,
+    playlists: Arc<Playlists<'a>>,
+
+    pub(super) query: Mutex<String>,
+    /// Whether keys edit the query, or act on the selected result.
+    pub(super) typing: AtomicBool,
+
+    /// Everything that can be found, collected when the search is opened.
+    corpus: Mutex<Vec<SearchResult>>,
+    pub(super) results: Mutex<Vec<SearchResult>>,
+    pub(super) selected_index: AtomicUsize,
+    pub(super) offset: AtomicUsize,
+
+    pub(super) jump_target: Mutex<Option<SearchResult>>,
+    pub(super) on_select_fn: Mutex<Box<dyn FnMut((Song, KeyEvent)) + 'a>>,
+}
+
+impl<'a> Search<'a> {
+    pub fn new(theme: Theme, library: Arc<Library<'a>>, playlists: Arc<Playlists<'a>>) -> Self {
+        Self {
+            theme,
+
+            library,
+            playlists,
+
+            query: Mutex::new(String::new()),
+            typing: AtomicBool::new(true),
+
+            corpus: Mutex::new(vec![]),
+            results: Mutex::new(vec![]),
+            selected_index: AtomicUsize::new(0),
+            offset: AtomicUsize::new(0),
+
+            jump_target: Mutex::new(None),
+            on_select_fn: Mutex::new(Box::new(|_| {}) as _),
+        }
+    }
+
+    pub fn on_select(&self, cb: impl FnMut((Song, KeyEvent)) + 'a) {
+        *self.on_select_fn.lock().unwrap() = Box::new(cb);
+    }
+
+    pub fn is_typing(&self) -> bool {
+        self.typing.load(Ordering::Relaxed)
+    }
+
+    /// Collects the songs of the library and playlists, and starts editing the query.
+    pub fn open(&self) {
+        let mut corpus: Vec<SearchResult> = self
+            .library
+            .songs()
+            .into_iter()
+            .map(|song| SearchResult { song, source: SearchResultSource::Library })
+            .collect();
+
+        corpus.sort_by_key(|r| (r.song.library_artist(), r.song.album.clone(), r.song.disc, r.song.track));
+
+        for playlist in self.playlists.playlists() {
+            corpus.extend(playlist.songs.into_iter().map(|song| SearchResult {
+                song,
+                source: SearchResultSource::Playlist(playlist.name.clone()),
+            }));
+        }
+
+        *self.corpus.lock().unwrap() = corpus;
+        self.typing.store(true, Ordering::Relaxed);
+        self.refresh_results();
+    }
+
+    /// The result the user asked to be taken to, in the library or its playlist. Taking it clears it.
+    pub fn take_jump_target(&self) -> Option<SearchResult> {
+        self.jump_target.lock().unwrap().take()
+    }
+
+    pub(super) fn selected_result(&self) -> Option<SearchResult> {
+        let results = self.results.lock().unwrap();
+        results.get(self.selected_index.load(Ordering::Relaxed)).cloned()
+    }
+
+    /// Ranks everything against the query, best matches first.
+    pub(super) fn refresh_results(&self) {
+        let query = self.query.lock().unwrap().clone();
+        let corpus = self.corpus.lock().unwrap();
+
+        let mut results: Vec<(i64, &SearchResult)> = if query.trim().is_empty() {
+            vec![]
+        } else {
+            corpus
+                .iter()
+                .filter_map(|result| {
+                    let song = &result.song;
+                    let fields = [
+                        song.artist.as_deref().unwrap_or_default(),
+                        song.album.as_deref().unwrap_or_default(),
+                        song.title.as_str(),
+                    ];
+
+                    fuzzy_score_fields(&query, &fields).map(|score| (score, result))
+                })
+                .collect()
+        };
+
+        // Stable, so equally good matches stay sorted by artist and album
+        results.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        results.truncate(MAX_RESULTS);
+
+        *self.results.lock().unwrap() = results.into_iter().map(|(_, result)| result.clone()).collect();
+        self.selected_index.store(0, Ordering::Relaxed);
+        self.offset.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Search<'_> {
+    fn drop(&mut self) {
+        log::trace!("Search.drop()");
+    }
+}
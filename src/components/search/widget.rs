@@ -0,0 +1,85 @@
+use std::sync::atomic::Ordering;
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Alignment, Constraint, Layout, Rect},
+    style::Style,
+    text::{Line, Span},
+    widgets::WidgetRef,
+};
+
+use super::{Search, SearchResultSource};
+
+impl<'a> WidgetRef for Search<'a> {
+    fn render_ref(&self, area: Rect, buf: &mut Buffer) {
+        let [area_query, _, area_results] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ])
+            .horizontal_margin(2)
+            .areas(area);
+
+        let query = self.query.lock().unwrap();
+        let caret = if self.is_typing() { "⎸" } else { "" };
+
+        Line::from(vec![
+            Span::styled("Search: ", Style::default().fg(self.theme.foreground)),
+            Span::styled(format!("{query}{caret}"), Style::default().fg(self.theme.search)),
+        ]).render_ref(area_query, buf);
+
+        let results = self.results.lock().unwrap();
+
+        if results.is_empty() {
+            let message = if query.trim().is_empty() {
+                "Type to search artists, albums and titles in the library and playlists"
+            } else {
+                "No results"
+            };
+            Line::from(message).style(Style::default().fg(self.theme.foreground_secondary)).render_ref(area_results, buf);
+            return;
+        }
+
+        let selected_index = self.selected_index.load(Ordering::Relaxed);
+
+        // Keep the selected result visible
+        let height = area_results.height as usize;
+        let mut offset = self.offset.load(Ordering::Relaxed);
+        if selected_index < offset {
+            offset = selected_index;
+        } else if height > 0 && selected_index >= offset + height {
+            offset = selected_index + 1 - height;
+        }
+        self.offset.store(offset, Ordering::Relaxed);
+
+        for (i, result) in results.iter().enumerate().skip(offset).take(height) {
+            let area = Rect {
+                y: area_results.y + (i - offset) as u16,
+                height: 1,
+                ..area_results
+            };
+
+            let style = if i == selected_index {
+                Style::default().fg(self.theme.foreground_selected).bg(self.theme.background_selected)
+            } else {
+                Style::default().fg(self.theme.foreground_secondary).bg(self.theme.background)
+            };
+
+            let song = &result.song;
+            let text = [song.artist.as_deref(), song.album.as_deref(), Some(song.title.as_str())]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<&str>>()
+                .join(" - ");
+
+            Line::from(text).style(style).render_ref(area, buf);
+
+            let source = match &result.source {
+                SearchResultSource::Library => "Library".to_string(),
+                SearchResultSource::Playlist(name) => format!("Playlist: {name}"),
+            };
+
+            Line::from(source).style(style).alignment(Alignment::Right).render_ref(area, buf);
+        }
+    }
+}
@@ -1,13 +1,23 @@
+use std::path::PathBuf;
+
 use ratatui::style::Color;
 use serde::{Deserialize, Serialize};
 use serde_default::DefaultFromSerde;
 
-use crate::toml::read_toml_file_or_default;
+use crate::{playlist_files::PlaylistFormat, toml::read_toml_file_or_default};
 
-#[derive(Serialize, Deserialize, Debug, Copy, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Config {
     #[serde(default)]
     pub theme: Theme,
+    #[serde(default)]
+    pub auto_dj: AutoDjConfig,
+    #[serde(default)]
+    pub library: LibraryConfig,
+    #[serde(default)]
+    pub album_art: AlbumArtConfig,
+    #[serde(default)]
+    pub playlists: PlaylistsConfig,
 }
 
 #[serde_inline_default::serde_inline_default]
@@ -41,6 +51,96 @@ pub struct Theme {
     pub search: Color,
 }
 
+#[serde_inline_default::serde_inline_default]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, DefaultFromSerde)]
+pub struct AutoDjConfig {
+    #[serde_inline_default(false)]
+    pub enabled: bool,
+
+    #[serde_inline_default(AutoDjStrategy::Random)]
+    pub strategy: AutoDjStrategy,
+
+    /// How many of the most recently played songs the auto-DJ avoids picking again.
+    #[serde_inline_default(100)]
+    pub history_size: usize,
+}
+
+#[serde_inline_default::serde_inline_default]
+#[derive(Serialize, Deserialize, Debug, Clone, DefaultFromSerde)]
+pub struct LibraryConfig {
+    /// Folders scanned for music on startup. New and changed files are added to the library, and songs whose files
+    /// are gone are removed from it.
+    #[serde(default)]
+    pub folders: Vec<PathBuf>,
+
+    /// Whether to keep watching the folders for changes while the app runs.
+    #[serde_inline_default(true)]
+    pub watch: bool,
+}
+
+#[serde_inline_default::serde_inline_default]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, DefaultFromSerde)]
+pub struct AlbumArtConfig {
+    #[serde_inline_default(true)]
+    pub enabled: bool,
+
+    #[serde_inline_default(GraphicsProtocol::Auto)]
+    pub protocol: GraphicsProtocol,
+}
+
+#[serde_inline_default::serde_inline_default]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, DefaultFromSerde)]
+pub struct PlaylistsConfig {
+    /// The format playlists and the queue are exported to.
+    #[serde_inline_default(PlaylistFormat::M3u8)]
+    pub export_format: PlaylistFormat,
+}
+
+/// How album art is drawn in the terminal.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphicsProtocol {
+    /// Whichever of the others the terminal is known to support.
+    Auto,
+    Kitty,
+    Sixel,
+    /// Unicode half blocks, colored two pixels per cell. Works in any terminal with true color.
+    HalfBlocks,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoDjStrategy {
+    Random,
+    SameArtist,
+    SameGenre,
+    /// Songs released in the same decade.
+    SameEra,
+    LeastRecentlyPlayed,
+}
+
+impl AutoDjStrategy {
+    pub fn next(&self) -> Self {
+        match self {
+            AutoDjStrategy::Random => AutoDjStrategy::SameArtist,
+            AutoDjStrategy::SameArtist => AutoDjStrategy::SameGenre,
+            AutoDjStrategy::SameGenre => AutoDjStrategy::SameEra,
+            AutoDjStrategy::SameEra => AutoDjStrategy::LeastRecentlyPlayed,
+            AutoDjStrategy::LeastRecentlyPlayed => AutoDjStrategy::Random,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AutoDjStrategy::Random => "random",
+            AutoDjStrategy::SameArtist => "same artist",
+            AutoDjStrategy::SameGenre => "same genre",
+            AutoDjStrategy::SameEra => "same era",
+            AutoDjStrategy::LeastRecentlyPlayed => "least recently played",
+        }
+    }
+}
+
 impl Config {
     pub fn from_file() -> Self {
         read_toml_file_or_default("config")
@@ -1,3 +1,3 @@
 pub static SECONDS_PER_MINUTE: u64 = 60;
 pub static SECONDS_PER_HOUR: u64 = SECONDS_PER_MINUTE * 60;
-pub static MAIN_SECTIONS: [&str; 4] = ["Library", "Playlists", "Files", "Help"];
+pub static MAIN_SECTIONS: [&str; 5] = ["Library", "Playlists", "Files", "Search", "Help"];
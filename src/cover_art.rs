@@ -0,0 +1,537 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    fmt::Write as _,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Sender},
+        Arc,
+        Mutex,
+    },
+    thread,
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use image::{imageops::FilterType, RgbImage};
+use lofty::{PictureType, Probe, TaggedFileExt};
+// The crossterm ratatui draws with, whose colors ratatui's convert into
+use ratatui::{
+    buffer::Buffer,
+    crossterm::{
+        cursor::{MoveTo, RestorePosition, SavePosition},
+        queue,
+        style::{Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    },
+    layout::Rect,
+    style::Color,
+};
+
+use crate::{
+    config::{AlbumArtConfig, GraphicsProtocol},
+    structs::Song,
+};
+
+/// Files looked for in the folder of songs without embedded art, in this order, with any of the extensions.
+const COVER_FILE_NAMES: [&str; 4] = ["cover", "folder", "front", "album"];
+const COVER_FILE_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+
+/// Covers are downscaled to this when loaded: more than any terminal shows them at, and little enough to keep
+/// the covers of a whole library session in memory.
+const MAX_COVER_SIZE: u32 = 600;
+
+/// How many rendered covers are kept, for all the sizes and protocols they were drawn at.
+const MAX_RENDERED: usize = 32;
+
+/// Used when the terminal doesn't tell its size in pixels.
+const DEFAULT_CELL_SIZE: (u32, u32) = (10, 20);
+
+/// Albums are told apart by the folder of their songs and their name, since art is usually one per album.
+type CoverKey = (PathBuf, Option<String>);
+
+fn cover_key(song: &Song) -> CoverKey {
+    (song.path.parent().map(Path::to_path_buf).unwrap_or_default(), song.album.clone())
+}
+
+/// The places covers are drawn in. Each one shows one cover at a time.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum CoverSlot {
+    NowPlaying,
+    Library,
+}
+
+impl CoverSlot {
+    const ALL: [CoverSlot; 2] = [CoverSlot::NowPlaying, CoverSlot::Library];
+
+    /// The id of the image shown in the slot, for the kitty graphics protocol.
+    fn image_id(&self) -> u32 {
+        match self {
+            CoverSlot::NowPlaying => 1,
+            CoverSlot::Library => 2,
+        }
+    }
+}
+
+enum Cover {
+    Loading,
+    Missing,
+    Loaded(Arc<RgbImage>),
+}
+
+enum Rendered {
+    /// The top and bottom color of each cell, row by row.
+    HalfBlocks { width: u16, cells: Vec<(Color, Color)> },
+    /// Raw RGB pixels, base64 encoded.
+    Kitty { width: u32, height: u32, data: String },
+    Sixel(String),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Placement {
+    key: CoverKey,
+    area: Rect,
+}
+
+/// Album art: embedded in the songs, or in a cover file next to them, loaded in the background and drawn with
+/// whichever graphics protocol the terminal supports.
+///
+/// Half blocks are drawn into the buffer, like any other widget. The kitty and sixel protocols draw images outside
+/// of it, so rendering only leaves their area blank, and [`CoverArt::flush`] draws them once the frame is on screen,
+/// only when they changed.
+pub struct CoverArt {
+    /// None when album art is disabled.
+    protocol: Option<GraphicsProtocol>,
+    cell_size: (u32, u32),
+    covers: Arc<Mutex<HashMap<CoverKey, Cover>>>,
+    rendered: Mutex<HashMap<(CoverKey, u16, u16), Rendered>>,
+    loader: Sender<(CoverKey, PathBuf)>,
+    /// Where covers were rendered in the current frame, and where they're on screen, since the last flush.
+    placements: Mutex<HashMap<CoverSlot, Placement>>,
+    drawn: Mutex<HashMap<CoverSlot, Placement>>,
+    screen: Mutex<Rect>,
+}
+
+impl CoverArt {
+    pub fn new(config: AlbumArtConfig) -> Self {
+        let protocol = match (config.enabled, config.protocol) {
+            (false, _) => None,
+            (true, GraphicsProtocol::Auto) => Some(detect_protocol()),
+            (true, protocol) => Some(protocol),
+        };
+
+        log::info!("album art protocol: {:?}", protocol);
+
+        let cell_size = match crossterm::terminal::window_size() {
+            Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => {
+                ((size.width / size.columns) as u32, (size.height / size.rows) as u32)
+            }
+            _ => DEFAULT_CELL_SIZE,
+        };
+
+        let covers = Arc::new(Mutex::new(HashMap::new()));
+        let (loader, requests) = channel::<(CoverKey, PathBuf)>();
+
+        let spawned = thread::Builder::new().name("cover_art".to_string()).spawn({
+            let covers = covers.clone();
+            move || {
+                for (key, path) in requests {
+                    let cover = match load_cover(&path) {
+                        Some(image) => Cover::Loaded(Arc::new(image)),
+                        None => Cover::Missing,
+                    };
+                    covers.lock().unwrap().insert(key, cover);
+                }
+            }
+        });
+
+        if let Err(err) = spawned {
+            log::error!("Could not spawn the cover art thread: {err}");
+        }
+
+        Self {
+            protocol,
+            cell_size,
+            covers,
+            rendered: Mutex::new(HashMap::new()),
+            loader,
+            placements: Mutex::new(HashMap::new()),
+            drawn: Mutex::new(HashMap::new()),
+            screen: Mutex::new(Rect::default()),
+        }
+    }
+
+    /// Whether the cover of the song's album is loaded, and can be rendered. Covers not loaded yet start loading.
+    pub fn has_cover(&self, song: &Song) -> bool {
+        if self.protocol.is_none() {
+            return false;
+        }
+
+        let key = cover_key(song);
+        let mut covers = self.covers.lock().unwrap();
+
+        match covers.get(&key) {
+            Some(Cover::Loaded(_)) => true,
+            Some(Cover::Loading | Cover::Missing) => false,
+            None => {
+                covers.insert(key.clone(), Cover::Loading);
+                let _ = self.loader.send((key, song.path.clone()));
+                false
+            }
+        }
+    }
+
+    /// Renders the cover of the song's album in the area, if it's loaded.
+    pub fn render(&self, slot: CoverSlot, song: &Song, area: Rect, buf: &mut Buffer) {
+        let Some(protocol) = self.protocol else {
+            return;
+        };
+
+        if area.is_empty() {
+            return;
+        }
+
+        let key = cover_key(song);
+
+        if protocol != GraphicsProtocol::HalfBlocks {
+            self.placements.lock().unwrap().insert(slot, Placement { key, area });
+            return;
+        }
+
+        let Some(image) = self.cover(&key) else {
+            return;
+        };
+
+        let rendered = self.rendered(key, area.width, area.height, || half_blocks(&image, area.width, area.height));
+
+        let Rendered::HalfBlocks { width, cells } = &*rendered else {
+            return;
+        };
+
+        for (i, (top, bottom)) in cells.iter().enumerate() {
+            let x = area.x + (i % *width as usize) as u16;
+            let y = area.y + (i / *width as usize) as u16;
+            buf.get_mut(x, y).set_symbol("▀").set_fg(*top).set_bg(*bottom);
+        }
+    }
+
+    /// Draws the covers rendered since the last flush with the kitty or sixel protocols, removing the ones that
+    /// aren't rendered anymore. The buffer is the one just drawn, to redraw the text that sixel covers were over.
+    pub fn flush(&self, out: &mut impl Write, buffer: &Buffer) -> io::Result<()> {
+        let Some(protocol) = self.protocol.filter(|p| *p != GraphicsProtocol::HalfBlocks) else {
+            return Ok(());
+        };
+
+        let placements = std::mem::take(&mut *self.placements.lock().unwrap());
+        let mut drawn = self.drawn.lock().unwrap();
+
+        // Resizing clears the screen, covers included
+        let mut screen = self.screen.lock().unwrap();
+        if *screen != buffer.area {
+            *screen = buffer.area;
+            drawn.clear();
+        }
+
+        for slot in CoverSlot::ALL {
+            let placement = placements.get(&slot);
+
+            if drawn.get(&slot) == placement {
+                continue;
+            }
+
+            if let Some(old) = drawn.remove(&slot) {
+                match protocol {
+                    GraphicsProtocol::Kitty => write!(out, "\x1b_Ga=d,d=I,i={},q=2\x1b\\", slot.image_id())?,
+                    _ => redraw_cells(out, buffer, old.area)?,
+                }
+            }
+
+            let Some(placement) = placement else {
+                continue;
+            };
+
+            let Some(image) = self.cover(&placement.key) else {
+                continue;
+            };
+
+            let Placement { key, area } = placement.clone();
+            let (cell_width, cell_height) = self.cell_size;
+            let rendered = self.rendered(key, area.width, area.height, || {
+                let image = fit(&image, area.width as u32 * cell_width, area.height as u32 * cell_height);
+
+                match protocol {
+                    GraphicsProtocol::Kitty => Rendered::Kitty {
+                        width: image.width(),
+                        height: image.height(),
+                        data: BASE64.encode(image.as_raw()),
+                    },
+                    _ => Rendered::Sixel(sixel(&image)),
+                }
+            });
+
+            queue!(out, SavePosition, MoveTo(area.x, area.y))?;
+
+            match &*rendered {
+                Rendered::Kitty { width, height, data } => {
+                    let id = slot.image_id();
+                    let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+
+                    for (i, chunk) in chunks.iter().enumerate() {
+                        let more = (i + 1 < chunks.len()) as u8;
+                        let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+
+                        if i == 0 {
+                            write!(out, "\x1b_Ga=T,f=24,s={width},v={height},i={id},q=2,C=1,m={more};{chunk}\x1b\\")?;
+                        } else {
+                            write!(out, "\x1b_Gm={more};{chunk}\x1b\\")?;
+                        }
+                    }
+                }
+                Rendered::Sixel(sixel) => out.write_all(sixel.as_bytes())?,
+                Rendered::HalfBlocks { .. } => {}
+            }
+
+            queue!(out, RestorePosition)?;
+            drawn.insert(slot, placement.clone());
+        }
+
+        out.flush()
+    }
+
+    fn cover(&self, key: &CoverKey) -> Option<Arc<RgbImage>> {
+        match self.covers.lock().unwrap().get(key) {
+            Some(Cover::Loaded(image)) => Some(image.clone()),
+            _ => None,
+        }
+    }
+
+    /// The cover rendered at the given size, rendering it if it wasn't already.
+    fn rendered(
+        &self,
+        key: CoverKey,
+        width: u16,
+        height: u16,
+        render: impl FnOnce() -> Rendered,
+    ) -> RenderedGuard<'_> {
+        let mut rendered = self.rendered.lock().unwrap();
+        let key = (key, width, height);
+
+        if !rendered.contains_key(&key) {
+            if rendered.len() >= MAX_RENDERED {
+                rendered.clear();
+            }
+
+            rendered.insert(key.clone(), render());
+        }
+
+        RenderedGuard { rendered, key }
+    }
+}
+
+/// A rendered cover, borrowed from the cache.
+struct RenderedGuard<'a> {
+    rendered: std::sync::MutexGuard<'a, HashMap<(CoverKey, u16, u16), Rendered>>,
+    key: (CoverKey, u16, u16),
+}
+
+impl std::ops::Deref for RenderedGuard<'_> {
+    type Target = Rendered;
+
+    fn deref(&self) -> &Rendered {
+        &self.rendered[&self.key]
+    }
+}
+
+/// Guesses the graphics protocol from the environment, since asking the terminal means waiting for its answer.
+fn detect_protocol() -> GraphicsProtocol {
+    let var = |name: &str| env::var(name).unwrap_or_default();
+    let term = var("TERM");
+    let term_program = var("TERM_PROGRAM");
+
+    // Their escape sequences would have to be wrapped to get through tmux and screen
+    if env::var_os("TMUX").is_some() || term.starts_with("screen") {
+        return GraphicsProtocol::HalfBlocks;
+    }
+
+    if env::var_os("KITTY_WINDOW_ID").is_some()
+        || term == "xterm-kitty"
+        || term == "xterm-ghostty"
+        || term_program == "WezTerm"
+        || term_program == "ghostty"
+    {
+        return GraphicsProtocol::Kitty;
+    }
+
+    if ["foot", "foot-extra", "mlterm", "contour"].contains(&term.as_str())
+        || term.contains("sixel")
+        || term_program == "iTerm.app"
+        || env::var_os("KONSOLE_VERSION").is_some()
+        || env::var_os("WT_SESSION").is_some()
+    {
+        return GraphicsProtocol::Sixel;
+    }
+
+    GraphicsProtocol::HalfBlocks
+}
+
+fn load_cover(path: &Path) -> Option<RgbImage> {
+    let data = embedded_cover(path).or_else(|| folder_cover(path))?;
+
+    let image = image::load_from_memory(&data)
+        .inspect_err(|err| log::warn!("Could not decode the cover of {:?}: {err}", path))
+        .ok()?;
+
+    Some(image.thumbnail(MAX_COVER_SIZE, MAX_COVER_SIZE).to_rgb8())
+}
+
+/// The front cover embedded in the song's tags or, if there's none, any picture in them.
+fn embedded_cover(path: &Path) -> Option<Vec<u8>> {
+    let tagged_file = Probe::open(path).ok()?.read().ok()?;
+    let pictures: Vec<_> = tagged_file.tags().iter().flat_map(|tag| tag.pictures()).collect();
+
+    pictures
+        .iter()
+        .find(|p| p.pic_type() == PictureType::CoverFront)
+        .or(pictures.first())
+        .map(|p| p.data().to_vec())
+}
+
+fn folder_cover(path: &Path) -> Option<Vec<u8>> {
+    let files: Vec<PathBuf> = fs::read_dir(path.parent()?).ok()?.flatten().map(|e| e.path()).collect();
+
+    let lowercase = |s: Option<&std::ffi::OsStr>| s.and_then(|s| s.to_str()).map(str::to_lowercase).unwrap_or_default();
+
+    COVER_FILE_NAMES.iter().find_map(|name| {
+        files
+            .iter()
+            .find(|f| {
+                lowercase(f.file_stem()) == *name && COVER_FILE_EXTENSIONS.contains(&lowercase(f.extension()).as_str())
+            })
+            .and_then(|f| fs::read(f).ok())
+    })
+}
+
+/// Scales the image to fit in the given size, keeping its aspect ratio.
+fn fit(image: &RgbImage, width: u32, height: u32) -> RgbImage {
+    let scale = f64::min(width as f64 / image.width() as f64, height as f64 / image.height() as f64);
+    let width = ((image.width() as f64 * scale) as u32).max(1);
+    let height = ((image.height() as f64 * scale) as u32).max(1);
+
+    image::imageops::resize(image, width, height, FilterType::Triangle)
+}
+
+/// Two pixels per cell: the top one as the foreground of "▀", and the bottom one as its background.
+fn half_blocks(image: &RgbImage, width: u16, height: u16) -> Rendered {
+    let image = fit(image, width as u32, height as u32 * 2);
+    let pixel = |x: u32, y: u32| {
+        let [r, g, b] = image.get_pixel(x, y.min(image.height() - 1)).0;
+        Color::Rgb(r, g, b)
+    };
+
+    let rows = image.height().div_ceil(2);
+    let cells = (0..rows)
+        .flat_map(|row| (0..image.width()).map(move |x| (x, row)))
+        .map(|(x, row)| (pixel(x, row * 2), pixel(x, row * 2 + 1)))
+        .collect();
+
+    Rendered::HalfBlocks { width: image.width() as u16, cells }
+}
+
+/// Encodes the image as sixels, with a palette of 6 levels of each of red, green and blue.
+fn sixel(image: &RgbImage) -> String {
+    let (width, height) = image.dimensions();
+    let level = |c: u8| (c as usize * 5 + 127) / 255;
+
+    let mut out = String::new();
+    let _ = write!(out, "\x1bPq\"1;1;{width};{height}");
+
+    for i in 0..216 {
+        let _ = write!(out, "#{i};2;{};{};{}", i / 36 * 20, i / 6 % 6 * 20, i % 6 * 20);
+    }
+
+    for band in (0..height).step_by(6) {
+        // The rows of the band each color is in, for each column
+        let mut colors: BTreeMap<usize, Vec<u8>> = BTreeMap::new();
+
+        for y in band..(band + 6).min(height) {
+            for x in 0..width {
+                let [r, g, b] = image.get_pixel(x, y).0;
+                let color = level(r) * 36 + level(g) * 6 + level(b);
+                colors.entry(color).or_insert_with(|| vec![0; width as usize])[x as usize] |= 1 << (y - band);
+            }
+        }
+
+        for (color, columns) in colors {
+            let _ = write!(out, "#{color}");
+
+            let mut x = 0;
+            while x < columns.len() {
+                let run = columns[x..].iter().take_while(|c| **c == columns[x]).count();
+                let sixel = (63 + columns[x]) as char;
+
+                if run > 3 {
+                    let _ = write!(out, "!{run}{sixel}");
+                } else {
+                    out.extend(std::iter::repeat_n(sixel, run));
+                }
+
+                x += run;
+            }
+
+            out.push('$');
+        }
+
+        out.push('-');
+    }
+
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Writes the cells of the area again, as they are in the buffer, over the sixels drawn there.
+fn redraw_cells(out: &mut impl Write, buffer: &Buffer, area: Rect) -> io::Result<()> {
+    let area = area.intersection(buffer.area);
+
+    queue!(out, SavePosition)?;
+
+    for y in area.top()..area.bottom() {
+        queue!(out, MoveTo(area.x, y))?;
+
+        for x in area.left()..area.right() {
+            let cell = buffer.get(x, y);
+            queue!(out, SetForegroundColor(cell.fg.into()), SetBackgroundColor(cell.bg.into()), Print(cell.symbol()))?;
+        }
+    }
+
+    queue!(out, ResetColor, RestorePosition)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgb;
+
+    use super::*;
+
+    #[test]
+    fn half_blocks_keep_the_aspect_ratio() {
+        let image = RgbImage::from_fn(100, 100, |x, _| if x < 50 { Rgb([255, 0, 0]) } else { Rgb([0, 0, 255]) });
+
+        let Rendered::HalfBlocks { width, cells } = half_blocks(&image, 20, 5) else {
+            panic!("not half blocks");
+        };
+
+        assert_eq!(width, 10);
+        assert_eq!(cells.len(), 50);
+        assert_eq!(cells[0], (Color::Rgb(255, 0, 0), Color::Rgb(255, 0, 0)));
+        assert_eq!(cells[9], (Color::Rgb(0, 0, 255), Color::Rgb(0, 0, 255)));
+    }
+
+    #[test]
+    fn encodes_sixel_bands_with_run_lengths() {
+        let image = RgbImage::from_pixel(8, 7, Rgb([255, 255, 255]));
+        let sixel = sixel(&image);
+
+        assert!(sixel.starts_with("\x1bPq\"1;1;8;7#0;2;0;0;0"));
+        assert!(sixel.ends_with("#215!8~$-#215!8@$-\x1b\\"));
+    }
+}
@@ -156,6 +156,19 @@ impl CueSheet {
     pub fn performer(&self) -> Option<String> {
         self.performer.clone()
     }
+
+    /// The value of a `REM` line, such as `REM GENRE Folk/Blues` or `REM DATE 1969`, without quotes.
+    pub fn remark(&self, key: &str) -> Option<String> {
+        self.comments.iter().find_map(|comment| {
+            let (k, value) = comment.split_once(' ')?;
+
+            if k.eq_ignore_ascii_case(key) {
+                Some(value.trim().trim_matches('"').to_string())
+            } else {
+                None
+            }
+        })
+    }
 }
 
 #[cfg(test)]
@@ -182,6 +195,11 @@ mod tests {
 
         assert_eq!(cue.performer, Some("Tim Buckley".to_string()));
 
+        assert_eq!(cue.remark("GENRE"), Some("Folk/Blues".to_string()));
+        assert_eq!(cue.remark("date"), Some("1969".to_string()));
+        assert_eq!(cue.remark("COMMENT"), Some("ExactAudioCopy v0.99pb4".to_string()));
+        assert_eq!(cue.remark("LABEL"), None);
+
         let Some(file) = cue.file else { panic!() };
 
         assert_eq!(file.tracks.len(), 6, "{:#?}", file);
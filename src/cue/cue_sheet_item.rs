@@ -47,7 +47,11 @@ impl CueSheetItem {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::VecDeque;
+    use std::path::Path;
+
     use super::*;
+    use crate::cue::cue_line::CueLine;
     use crate::cue::cue_line_node::CueLineNode;
 
     #[test]
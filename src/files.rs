@@ -1,3 +1,9 @@
 mod library;
+mod library_store;
+mod playlist_store;
+mod smart_playlists;
 
 pub use library::Library;
+pub use library_store::LibraryStore;
+pub use playlist_store::PlaylistStore;
+pub use smart_playlists::SmartPlaylists;
@@ -1,28 +1,49 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    structs::{Song, Playlist},
-    toml::{read_toml_file_or_default, write_toml_file, TomlFileError},
+    structs::{FileStamp, MetadataOverrides, RemovedSong, Song, SongId, SongStats, VirtualFolder},
+    toml::{write_toml_file, TomlFileError},
 };
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Library {
     pub songs: Vec<Song>,
+    #[serde(default)]
+    pub virtual_folders: Vec<VirtualFolder>,
+    #[serde(default, skip_serializing_if = "MetadataOverrides::is_empty")]
+    pub overrides: MetadataOverrides,
+    /// Stamps of the files read from the library folders, to tell which ones changed since.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub file_stamps: BTreeMap<PathBuf, FileStamp>,
+    /// Play counts, skips and favorites.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stats: Vec<SongStats>,
+    /// Songs kept in the library, but left out of it, such as unwanted duplicates.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hidden: Vec<SongId>,
+    /// Songs removed from the library recently, to be restored.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<RemovedSong>,
 }
 
 impl Default for Library {
     fn default() -> Self {
         Self {
             songs: vec![],
+            virtual_folders: vec![],
+            overrides: MetadataOverrides::default(),
+            file_stamps: BTreeMap::new(),
+            stats: vec![],
+            hidden: vec![],
+            removed: vec![],
         }
     }
 }
 
 impl Library {
-    pub fn from_file() -> Self {
-        read_toml_file_or_default("library")
-    }
-
+    /// Writes the library to `library.toml`, the format older versions kept it in.
     pub fn to_file(&self) -> Result<(), TomlFileError> {
         write_toml_file("library", self)
     }
@@ -0,0 +1,570 @@
+use std::{
+    collections::HashMap,
+    fmt::Formatter,
+    fs::{create_dir_all, read, rename, File, OpenOptions},
+    io::{BufWriter, ErrorKind, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    structs::{FileStamp, MetadataOverrides, RemovedSong, Song, SongId, SongStats, VirtualFolder},
+    toml::{get_config_dir_path, read_toml_file, TomlFileError},
+};
+
+use super::Library;
+
+/// Written at the start of the file, so files from other versions (or other programs) aren't mistaken for a library.
+const MAGIC: &[u8; 8] = b"JOLTLIB1";
+
+/// Songs are told apart by their file and, for songs of cue sheets, their start time in it.
+type SongKey = (PathBuf, Duration);
+
+fn song_key(song: &Song) -> SongKey {
+    (song.path.clone(), song.start_time)
+}
+
+fn stats_key(stats: &SongStats) -> SongKey {
+    (stats.path.clone(), stats.start_time)
+}
+
+#[derive(Debug)]
+#[allow(dead_code)]
+pub enum LibraryStoreError {
+    Toml(TomlFileError),
+    IoError(std::io::Error),
+    EncodeError(rmp_serde::encode::Error),
+    InvalidFile(PathBuf),
+}
+
+impl std::fmt::Display for LibraryStoreError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#?}", self)
+    }
+}
+
+impl std::error::Error for LibraryStoreError {}
+
+impl From<TomlFileError> for LibraryStoreError {
+    fn from(value: TomlFileError) -> Self {
+        LibraryStoreError::Toml(value)
+    }
+}
+
+impl From<std::io::Error> for LibraryStoreError {
+    fn from(value: std::io::Error) -> Self {
+        LibraryStoreError::IoError(value)
+    }
+}
+
+impl From<rmp_serde::encode::Error> for LibraryStoreError {
+    fn from(value: rmp_serde::encode::Error) -> Self {
+        LibraryStoreError::EncodeError(value)
+    }
+}
+
+/// Everything in the library but its songs, their stats, the stamps of its files and the removed songs. It's small, so
+/// it's written whole whenever any of it changes.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct LibraryMeta {
+    virtual_folders: Vec<VirtualFolder>,
+    overrides: MetadataOverrides,
+    #[serde(default)]
+    hidden: Vec<SongId>,
+}
+
+/// An entry of the library file. Later entries win over earlier ones.
+#[derive(Serialize, Deserialize, Debug)]
+enum Record {
+    Song(Song),
+    RemoveSong(SongKey),
+    Meta(LibraryMeta),
+    Stats(SongStats),
+    RemoveStats(SongKey),
+    RemovedSong(RemovedSong),
+    /// The removed song was restored or forgotten.
+    ForgetRemovedSong(SongKey),
+    FileStamp(PathBuf, FileStamp),
+    RemoveFileStamp(PathBuf),
+}
+
+/// The library, stored in `library.db` as a log of records: every save appends only the songs that were added,
+/// changed or removed since the last one, rather than rewriting the whole library. When the log has grown much
+/// bigger than the library itself, it's compacted into a new file.
+///
+/// Records are MessagePack-encoded, with their field names, so songs with fields added in later versions can still
+/// be read, and each one is prefixed by its length, so a record cut short by a crash is simply ignored.
+pub struct LibraryStore {
+    path: PathBuf,
+    songs: HashMap<SongKey, Song>,
+    stats: HashMap<SongKey, SongStats>,
+    removed: HashMap<SongKey, RemovedSong>,
+    file_stamps: HashMap<PathBuf, FileStamp>,
+    meta: LibraryMeta,
+    /// The encoded `meta`, to tell whether it changed without comparing it field by field.
+    meta_bytes: Vec<u8>,
+    /// Records in the file, including the ones later records made obsolete.
+    record_count: usize,
+}
+
+impl LibraryStore {
+    /// Opens the library file in the config directory. The first time, it imports the library from the `library.toml`
+    /// file older versions kept it in.
+    pub fn open() -> Result<Self, LibraryStoreError> {
+        let path = get_config_dir_path()?.join("library.db");
+
+        if path.exists() {
+            return Self::open_path(path);
+        }
+
+        let mut store = Self::empty(path);
+
+        match read_toml_file::<Library>("library") {
+            Ok(library) => {
+                log::info!("importing {} songs from library.toml into library.db", library.songs.len());
+                store.save(&library)?;
+            }
+            Err(TomlFileError::IoError(err)) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => {
+                log::error!("Error in 'library' file. Will start with an empty library. Error was: \n{:#?}", err);
+            }
+        }
+
+        Ok(store)
+    }
+
+    /// Opens the library, or starts an empty one if it can't be read. The unreadable file is kept aside, as
+    /// `library.db.broken`, rather than overwritten.
+    pub fn open_or_default() -> Self {
+        Self::open().unwrap_or_else(|err| {
+            log::error!("Error reading the library. Will start with an empty library. Error was: \n{:#?}", err);
+
+            let path = get_config_dir_path().unwrap_or_default().join("library.db");
+
+            if let Err(err) = rename(&path, path.with_extension("db.broken")) {
+                log::error!("Could not move the library file aside: {:#?}", err);
+            }
+
+            Self::empty(path)
+        })
+    }
+
+    fn empty(path: PathBuf) -> Self {
+        Self {
+            path,
+            songs: HashMap::new(),
+            stats: HashMap::new(),
+            removed: HashMap::new(),
+            file_stamps: HashMap::new(),
+            meta: LibraryMeta::default(),
+            meta_bytes: vec![],
+            record_count: 0,
+        }
+    }
+
+    fn open_path(path: PathBuf) -> Result<Self, LibraryStoreError> {
+        let bytes = read(&path)?;
+
+        let Some(mut rest) = bytes.strip_prefix(MAGIC) else {
+            return Err(LibraryStoreError::InvalidFile(path));
+        };
+
+        let mut store = Self::empty(path);
+        let mut is_damaged = false;
+
+        while !rest.is_empty() {
+            let record = rest
+                .split_first_chunk::<4>()
+                .map(|(len, rest)| (u32::from_le_bytes(*len) as usize, rest))
+                .filter(|(len, rest)| *len <= rest.len())
+                .and_then(|(len, rest)| rmp_serde::from_slice::<Record>(&rest[..len]).ok().map(|r| (r, len, rest)));
+
+            let Some((record, len, tail)) = record else {
+                log::warn!("library.db ends in a damaged record, likely from a crash while saving. Ignoring it.");
+                is_damaged = true;
+                break;
+            };
+
+            store.apply(record);
+            rest = &tail[len..];
+        }
+
+        if is_damaged || store.needs_compaction() {
+            store.compact()?;
+        }
+
+        Ok(store)
+    }
+
+    fn apply(&mut self, record: Record) {
+        match record {
+            Record::Song(song) => {
+                self.songs.insert(song_key(&song), song);
+            }
+            Record::RemoveSong(key) => {
+                self.songs.remove(&key);
+            }
+            Record::Meta(meta) => {
+                self.meta_bytes = rmp_serde::to_vec_named(&meta).unwrap_or_default();
+                self.meta = meta;
+            }
+            Record::Stats(stats) => {
+                self.stats.insert(stats_key(&stats), stats);
+            }
+            Record::RemoveStats(key) => {
+                self.stats.remove(&key);
+            }
+            Record::RemovedSong(removed) => {
+                self.removed.insert(song_key(&removed.song), removed);
+            }
+            Record::ForgetRemovedSong(key) => {
+                self.removed.remove(&key);
+            }
+            Record::FileStamp(path, stamp) => {
+                self.file_stamps.insert(path, stamp);
+            }
+            Record::RemoveFileStamp(path) => {
+                self.file_stamps.remove(&path);
+            }
+        }
+
+        self.record_count += 1;
+    }
+
+    pub fn library(&self) -> Library {
+        let mut songs: Vec<Song> = self.songs.values().cloned().collect();
+        songs.sort_by(|a, b| (&a.path, a.start_time).cmp(&(&b.path, b.start_time)));
+
+        let mut stats: Vec<SongStats> = self.stats.values().cloned().collect();
+        stats.sort_by(|a, b| (&a.path, a.start_time).cmp(&(&b.path, b.start_time)));
+
+        let mut removed: Vec<RemovedSong> = self.removed.values().cloned().collect();
+        removed.sort_by_key(|r| r.removed_at);
+
+        Library {
+            songs,
+            virtual_folders: self.meta.virtual_folders.clone(),
+            overrides: self.meta.overrides.clone(),
+            file_stamps: self.file_stamps.iter().map(|(path, stamp)| (path.clone(), *stamp)).collect(),
+            stats,
+            hidden: self.meta.hidden.clone(),
+            removed,
+        }
+    }
+
+    /// Writes the changes between the given library and the stored one.
+    pub fn save(&mut self, library: &Library) -> Result<(), LibraryStoreError> {
+        let songs: HashMap<SongKey, &Song> = library.songs.iter().map(|s| (song_key(s), s)).collect();
+        let mut records = vec![];
+
+        for (key, song) in &songs {
+            if self.songs.get(key) != Some(*song) {
+                records.push(Record::Song((*song).clone()));
+            }
+        }
+
+        for key in self.songs.keys() {
+            if !songs.contains_key(key) {
+                records.push(Record::RemoveSong(key.clone()));
+            }
+        }
+
+        let stats: HashMap<SongKey, &SongStats> = library.stats.iter().map(|s| (stats_key(s), s)).collect();
+
+        for (key, song_stats) in &stats {
+            if self.stats.get(key) != Some(*song_stats) {
+                records.push(Record::Stats((*song_stats).clone()));
+            }
+        }
+
+        for key in self.stats.keys() {
+            if !stats.contains_key(key) {
+                records.push(Record::RemoveStats(key.clone()));
+            }
+        }
+
+        let removed: HashMap<SongKey, &RemovedSong> = library.removed.iter().map(|r| (song_key(&r.song), r)).collect();
+
+        for (key, removed_song) in &removed {
+            if self.removed.get(key) != Some(*removed_song) {
+                records.push(Record::RemovedSong((*removed_song).clone()));
+            }
+        }
+
+        for key in self.removed.keys() {
+            if !removed.contains_key(key) {
+                records.push(Record::ForgetRemovedSong(key.clone()));
+            }
+        }
+
+        for (path, stamp) in &library.file_stamps {
+            if self.file_stamps.get(path) != Some(stamp) {
+                records.push(Record::FileStamp(path.clone(), *stamp));
+            }
+        }
+
+        for path in self.file_stamps.keys() {
+            if !library.file_stamps.contains_key(path) {
+                records.push(Record::RemoveFileStamp(path.clone()));
+            }
+        }
+
+        let meta = LibraryMeta {
+            virtual_folders: library.virtual_folders.clone(),
+            overrides: library.overrides.clone(),
+            hidden: library.hidden.clone(),
+        };
+
+        if rmp_serde::to_vec_named(&meta)? != self.meta_bytes {
+            records.push(Record::Meta(meta));
+        }
+
+        self.append(records)
+    }
+
+    /// Writes the stats of the given songs, if they changed, without going through the rest of the library, as
+    /// songs are played much more often than the library changes.
+    pub fn save_stats(&mut self, stats: &[SongStats]) -> Result<(), LibraryStoreError> {
+        let records = stats
+            .iter()
+            .filter(|song_stats| self.stats.get(&stats_key(song_stats)) != Some(*song_stats))
+            .map(|song_stats| Record::Stats(song_stats.clone()))
+            .collect();
+
+        self.append(records)
+    }
+
+    /// Appends the records to the file, and applies them.
+    fn append(&mut self, records: Vec<Record>) -> Result<(), LibraryStoreError> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        log::debug!("saving {} changes to library.db", records.len());
+
+        let is_new = !self.path.exists();
+
+        if is_new {
+            if let Some(parent) = self.path.parent() {
+                create_dir_all(parent)?;
+            }
+        }
+
+        let mut file = BufWriter::new(OpenOptions::new().create(true).append(true).open(&self.path)?);
+
+        if is_new {
+            file.write_all(MAGIC)?;
+        }
+
+        for record in &records {
+            write_record(&mut file, record)?;
+        }
+
+        file.flush()?;
+        file.get_ref().sync_data()?;
+
+        for record in records {
+            self.apply(record);
+        }
+
+        if self.needs_compaction() {
+            self.compact()?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether most of the records in the file are obsolete.
+    fn needs_compaction(&self) -> bool {
+        self.record_count > 1000 && self.record_count > self.live_record_count() * 2
+    }
+
+    /// One record per song, per song's stats, per removed song and per file stamp, and the meta.
+    fn live_record_count(&self) -> usize {
+        self.songs.len() + self.stats.len() + self.removed.len() + self.file_stamps.len() + 1
+    }
+
+    /// Rewrites the file with one record per song, per song's stats, per removed song and per file stamp. The new file is written next to the old one and then moved over it,
+    /// so a crash halfway through leaves the old file intact.
+    fn compact(&mut self) -> Result<(), LibraryStoreError> {
+        log::debug!("compacting library.db: {} records, {} songs", self.record_count, self.songs.len());
+
+        let tmp_path = self.path.with_extension("db.tmp");
+        let mut file = BufWriter::new(File::create(&tmp_path)?);
+
+        file.write_all(MAGIC)?;
+
+        let mut songs: Vec<&Song> = self.songs.values().collect();
+        songs.sort_by_key(|s| (&s.path, s.start_time));
+
+        for song in songs {
+            write_record(&mut file, &Record::Song(song.clone()))?;
+        }
+
+        for song_stats in self.stats.values() {
+            write_record(&mut file, &Record::Stats(song_stats.clone()))?;
+        }
+
+        for removed_song in self.removed.values() {
+            write_record(&mut file, &Record::RemovedSong(removed_song.clone()))?;
+        }
+
+        for (path, stamp) in &self.file_stamps {
+            write_record(&mut file, &Record::FileStamp(path.clone(), *stamp))?;
+        }
+
+        write_record(&mut file, &Record::Meta(self.meta.clone()))?;
+
+        file.flush()?;
+        file.get_ref().sync_all()?;
+        drop(file);
+
+        rename(&tmp_path, &self.path)?;
+
+        self.meta_bytes = rmp_serde::to_vec_named(&self.meta)?;
+        self.record_count = self.live_record_count();
+
+        Ok(())
+    }
+}
+
+fn write_record(w: &mut impl Write, record: &Record) -> Result<(), LibraryStoreError> {
+    let bytes = rmp_serde::to_vec_named(record)?;
+    w.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    w.write_all(&bytes)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{remove_file, write},
+        path::Path,
+    };
+
+    use super::*;
+
+    fn song(path: &str, title: &str) -> Song {
+        Song {
+            path: PathBuf::from(path),
+            title: title.to_string(),
+            length: Duration::from_secs(60),
+            ..Default::default()
+        }
+    }
+
+    fn sorted_titles(store: &LibraryStore) -> Vec<String> {
+        let mut titles: Vec<String> = store.songs.values().map(|s| s.title.clone()).collect();
+        titles.sort();
+        titles
+    }
+
+    #[test]
+    fn replays_appended_changes_and_ignores_damaged_tails() {
+        let path = std::env::temp_dir().join(format!("jolteon-library-store-test-{}.db", std::process::id()));
+        let _ = remove_file(&path);
+
+        let mut store = LibraryStore::empty(path.clone());
+        let mut library = Library {
+            songs: vec![song("/music/a.flac", "A"), song("/music/b.flac", "B")],
+            ..Default::default()
+        };
+
+        store.save(&library).unwrap();
+
+        library.songs[0].title = "A2".to_string();
+        library.songs.remove(1);
+        library.songs.push(song("/music/c.flac", "C"));
+        library.stats.push(SongStats {
+            play_count: 3,
+            ..SongStats::new(&library.songs[0])
+        });
+        store.save(&library).unwrap();
+
+        // 2 songs and the meta, then a changed, a new and a removed song, and the stats of a song
+        assert_eq!(store.record_count, 7);
+
+        let reopened = LibraryStore::open_path(path.clone()).unwrap();
+        assert_eq!(sorted_titles(&reopened), vec!["A2", "C"]);
+        assert_eq!(reopened.library().stats, library.stats);
+        assert_eq!(reopened.record_count, 7);
+
+        let mut bytes = read(&path).unwrap();
+        bytes.extend_from_slice(&[200, 0, 0, 0, 1, 2, 3]);
+        write(&path, bytes).unwrap();
+
+        let reopened = LibraryStore::open_path(path.clone()).unwrap();
+        assert_eq!(sorted_titles(&reopened), vec!["A2", "C"]);
+        // Damaged files are compacted right away
+        assert_eq!(reopened.record_count, 4);
+
+        remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn keeps_removed_songs_apart_from_the_meta() {
+        let path = std::env::temp_dir().join(format!("jolteon-library-store-removed-test-{}.db", std::process::id()));
+        let _ = remove_file(&path);
+
+        let mut store = LibraryStore::empty(path.clone());
+        let mut library = Library {
+            songs: vec![song("/music/a.flac", "A")],
+            removed: vec![
+                RemovedSong { song: song("/music/b.flac", "B"), removed_at: 1 },
+                RemovedSong { song: song("/music/c.flac", "C"), removed_at: 2 },
+            ],
+            ..Default::default()
+        };
+
+        store.save(&library).unwrap();
+        assert_eq!(store.record_count, 4);
+
+        library.removed.remove(0);
+        store.save(&library).unwrap();
+
+        // Restoring a song forgets it alone, rather than writing the meta again
+        assert_eq!(store.record_count, 5);
+
+        let reopened = LibraryStore::open_path(path.clone()).unwrap();
+        assert_eq!(reopened.library().removed, library.removed);
+
+        remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn saves_file_stamps_and_stats_one_by_one() {
+        let path = std::env::temp_dir().join(format!("jolteon-library-store-stamps-test-{}.db", std::process::id()));
+        let _ = remove_file(&path);
+
+        let stamp = |modified: u64| FileStamp { modified, size: 1000 };
+        let mut store = LibraryStore::empty(path.clone());
+        let mut library = Library {
+            songs: vec![song("/music/a.flac", "A")],
+            file_stamps: [(PathBuf::from("/music/a.flac"), stamp(1)), (PathBuf::from("/music/b.flac"), stamp(1))].into(),
+            ..Default::default()
+        };
+
+        store.save(&library).unwrap();
+        assert_eq!(store.record_count, 4);
+
+        library.file_stamps.insert(PathBuf::from("/music/a.flac"), stamp(2));
+        library.file_stamps.remove(Path::new("/music/b.flac"));
+        store.save(&library).unwrap();
+
+        // A changed and a removed stamp, rather than the meta again
+        assert_eq!(store.record_count, 6);
+
+        let stats = SongStats { play_count: 1, ..SongStats::new(&library.songs[0]) };
+        store.save_stats(std::slice::from_ref(&stats)).unwrap();
+        store.save_stats(std::slice::from_ref(&stats)).unwrap();
+        assert_eq!(store.record_count, 7);
+
+        let reopened = LibraryStore::open_path(path.clone()).unwrap().library();
+        assert_eq!(reopened.file_stamps, library.file_stamps);
+        assert_eq!(reopened.stats, vec![stats]);
+
+        remove_file(&path).unwrap();
+    }
+}
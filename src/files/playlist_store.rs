@@ -0,0 +1,274 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{create_dir_all, read_dir, read_to_string, remove_file},
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+};
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Deserialize;
+
+use crate::{
+    playlist_files::free_playlist_path,
+    structs::{Playlist, Song},
+    toml::{get_config_dir_path, write_file_atomically, TomlFileError},
+};
+
+/// A playlist file as it's read. Files written by hand may leave the name out, and get the one of the file.
+#[derive(Deserialize)]
+struct PlaylistToml {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    songs: Vec<Song>,
+}
+
+/// A file of the playlists folder, as it was last read or written.
+struct StoredFile {
+    playlist: Playlist,
+    contents: String,
+}
+
+/// The regular playlists, kept in the `playlists` folder of the config dir, one TOML file each, so a bad write can
+/// only cost one of them, and they can be shared, or kept under version control.
+///
+/// Files are only written when their playlist changed, and only deleted when their playlist was. Files that can't be
+/// read are left alone.
+pub struct PlaylistStore {
+    folder: PathBuf,
+    files: BTreeMap<PathBuf, StoredFile>,
+    watcher: Option<RecommendedWatcher>,
+    events: Option<Receiver<notify::Result<Event>>>,
+}
+
+impl PlaylistStore {
+    pub fn new(folder: PathBuf) -> Self {
+        Self {
+            folder,
+            files: BTreeMap::new(),
+            watcher: None,
+            events: None,
+        }
+    }
+
+    pub fn in_config_dir() -> Self {
+        Self::new(get_config_dir_path().unwrap_or_default().join("playlists"))
+    }
+
+    /// Reads the playlists in the folder, in the given order of their names, and the ones not in it after them, by name.
+    pub fn load(&mut self, order: &[String]) -> Vec<Playlist> {
+        self.files = read_folder(&self.folder);
+        self.playlists(order)
+    }
+
+    /// Writes the files of the playlists that changed since they were last read or written, and deletes the ones of
+    /// the playlists that are gone.
+    pub fn save(&mut self, playlists: &[Playlist]) -> Result<(), TomlFileError> {
+        create_dir_all(&self.folder)?;
+
+        let mut files = BTreeMap::new();
+
+        for playlist in playlists {
+            let path = self.path_for(&playlist.name, &files);
+            let contents = toml::to_string(playlist)?;
+
+            if self.files.get(&path).map(|f| &f.contents) != Some(&contents) {
+                write_file_atomically(&path, contents.as_bytes())?;
+            }
+
+            files.insert(path, StoredFile { playlist: playlist.clone(), contents });
+        }
+
+        for path in self.files.keys().filter(|path| !files.contains_key(*path)) {
+            if let Err(err) = remove_file(path) {
+                log::error!("Could not delete playlist file {:?}: {:?}", path, err);
+            }
+        }
+
+        self.files = files;
+
+        Ok(())
+    }
+
+    /// The file of the playlist with the given name: the one it was read from or last written to, if any, or a new one
+    /// named after it.
+    fn path_for(&self, name: &str, taken: &BTreeMap<PathBuf, StoredFile>) -> PathBuf {
+        if let Some((path, _)) = self.files.iter().find(|(path, file)| file.playlist.name == name && !taken.contains_key(*path)) {
+            return path.clone();
+        }
+
+        free_playlist_path(&self.folder, name, "toml", |path| {
+            taken.contains_key(path) || self.files.contains_key(path) || path.exists()
+        })
+    }
+
+    /// Keeps watching the folder for changes made by other programs, which `external_changes` picks up.
+    pub fn watch(&mut self) {
+        if let Err(err) = create_dir_all(&self.folder) {
+            log::error!("Could not create the playlists folder {:?}: {:?}", self.folder, err);
+            return;
+        }
+
+        let (sender, receiver) = channel();
+
+        let mut watcher = match notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        }) {
+            Ok(watcher) => watcher,
+            Err(err) => {
+                log::error!("Could not watch the playlists folder: {:?}", err);
+                return;
+            }
+        };
+
+        if let Err(err) = watcher.watch(&self.folder, RecursiveMode::NonRecursive) {
+            log::error!("Could not watch the playlists folder {:?}: {:?}", self.folder, err);
+            return;
+        }
+
+        self.watcher = Some(watcher);
+        self.events = Some(receiver);
+    }
+
+    /// The playlists, if files of the folder changed since they were last read or written, other than by this store.
+    /// They're sorted as the names given by `order`, which is only called then.
+    pub fn external_changes(&mut self, order: impl FnOnce() -> Vec<String>) -> Option<Vec<Playlist>> {
+        let events = self.events.as_ref()?;
+
+        if events.try_iter().count() == 0 {
+            return None;
+        }
+
+        let files = read_folder(&self.folder);
+
+        if files.iter().map(|(path, file)| (path, &file.contents)).eq(self.files.iter().map(|(path, file)| (path, &file.contents))) {
+            return None;
+        }
+
+        log::debug!("playlists folder changed");
+        self.files = files;
+
+        Some(self.playlists(&order()))
+    }
+
+    fn playlists(&self, order: &[String]) -> Vec<Playlist> {
+        let mut playlists: Vec<Playlist> = self.files.values().map(|file| file.playlist.clone()).collect();
+
+        playlists.sort_by_cached_key(|playlist| {
+            let position = order.iter().position(|name| *name == playlist.name).unwrap_or(order.len());
+            (position, playlist.name.clone())
+        });
+        playlists
+    }
+}
+
+/// The playlist files in the folder that could be read, by path. Hidden files, such as the temporary ones of atomic
+/// writes, are skipped.
+fn read_folder(folder: &Path) -> BTreeMap<PathBuf, StoredFile> {
+    let Ok(entries) = read_dir(folder) else {
+        return BTreeMap::new();
+    };
+
+    let paths: BTreeSet<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .filter(|path| path.file_name().is_some_and(|name| !name.to_string_lossy().starts_with('.')))
+        .collect();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let contents = match read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(err) => {
+                    log::error!("Could not read playlist file {:?}: {:?}", path, err);
+                    return None;
+                }
+            };
+
+            let playlist: PlaylistToml = match toml::from_str(&contents) {
+                Ok(playlist) => playlist,
+                Err(err) => {
+                    log::error!("Error in playlist file {:?}. It will be left alone. Error was: \n{:#?}", path, err);
+                    return None;
+                }
+            };
+
+            let name = match playlist.name {
+                name if !name.is_empty() => name,
+                _ => path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+            };
+
+            Some((path, StoredFile { playlist: Playlist { name, songs: playlist.songs }, contents }))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::{remove_dir_all, write}, time::Duration};
+
+    use super::*;
+
+    fn playlist(name: &str, titles: &[&str]) -> Playlist {
+        Playlist {
+            name: name.to_string(),
+            songs: titles
+                .iter()
+                .map(|title| Song {
+                    path: PathBuf::from(format!("/music/{title}.flac")),
+                    title: title.to_string(),
+                    length: Duration::from_secs(200),
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    fn names(playlists: &[Playlist]) -> Vec<&str> {
+        playlists.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn keeps_one_file_per_playlist() {
+        let folder = std::env::temp_dir().join(format!("jolteon-playlist-store-test-{}", std::process::id()));
+        let _ = remove_dir_all(&folder);
+
+        let mut store = PlaylistStore::new(folder.clone());
+        store.save(&[playlist("Mix", &["A"]), playlist("Mix", &["B"]), playlist("AC/DC", &["C"])]).unwrap();
+
+        assert!(folder.join("Mix.toml").is_file());
+        assert!(folder.join("Mix (2).toml").is_file());
+        assert!(folder.join("AC_DC.toml").is_file());
+
+        // Names that would make hidden files, which aren't read back. Files without a name get the one of the file
+        let mut store = PlaylistStore::new(folder.join("unnamed"));
+        store.save(&[playlist("", &["A"]), playlist(".hidden", &["B"])]).unwrap();
+
+        assert!(folder.join("unnamed/Untitled.toml").is_file());
+        assert!(folder.join("unnamed/_.hidden.toml").is_file());
+        assert_eq!(names(&PlaylistStore::new(folder.join("unnamed")).load(&[])), vec![".hidden", "Untitled"]);
+
+        // Written by hand, without a name, and broken
+        write(folder.join("Road Trip.toml"), "[[songs]]\npath = \"/music/D.flac\"\ntitle = \"D\"\nstart_time = { secs = 0, nanos = 0 }\nlength = { secs = 1, nanos = 0 }\n").unwrap();
+        write(folder.join("Broken.toml"), "songs = [").unwrap();
+
+        let mut reopened = PlaylistStore::new(folder.clone());
+        let playlists = reopened.load(&["Mix".to_string(), "AC/DC".to_string()]);
+        assert_eq!(names(&playlists), vec!["Mix", "Mix", "AC/DC", "Road Trip"]);
+        assert_eq!(playlists[3].songs[0].title, "D");
+
+        // Renaming one moves it to a new file, and deleting one deletes its file, leaving the one that couldn't be read
+        let mut playlists = playlists;
+        playlists[2].name = "Rock".to_string();
+        playlists.remove(1);
+        reopened.save(&playlists).unwrap();
+
+        assert!(!folder.join("AC_DC.toml").exists());
+        assert!(folder.join("Rock.toml").is_file());
+        assert!(folder.join("Broken.toml").is_file());
+        assert_eq!(names(&PlaylistStore::new(folder.clone()).load(&[])), vec!["Mix", "Road Trip", "Rock"]);
+
+        remove_dir_all(&folder).unwrap();
+    }
+}
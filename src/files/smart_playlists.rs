@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    structs::SmartPlaylist,
+    toml::{read_toml_file_or_default, write_toml_file, TomlFileError},
+};
+
+/// Smart playlists, kept in `smart_playlists.toml`, so they can be written by hand as well as in the playlists tab.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SmartPlaylists {
+    #[serde(default)]
+    pub smart_playlists: Vec<SmartPlaylist>,
+}
+
+impl SmartPlaylists {
+    pub fn from_file() -> Self {
+        read_toml_file_or_default("smart_playlists")
+    }
+
+    pub fn to_file(&self) -> Result<(), TomlFileError> {
+        write_toml_file("smart_playlists", self)
+    }
+}
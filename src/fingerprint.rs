@@ -0,0 +1,117 @@
+use std::{fs::File, io::BufReader, time::Duration};
+
+use rodio::{Decoder, Source};
+
+use crate::structs::Song;
+
+/// The beginning of songs is often silence, which every song has in common.
+const SKIP: Duration = Duration::from_secs(2);
+const SLICE: Duration = Duration::from_millis(100);
+const SLICES: usize = 129;
+
+/// Fingerprints this similar are taken to be of the same recording.
+pub const SAME_RECORDING: f32 = 0.85;
+
+/// A rough fingerprint of the audio of a song: whether it gets louder or quieter from each slice of a tenth of a
+/// second to the next, over the first seconds of it. Two copies of the same recording have almost the same
+/// fingerprint, even in different formats and bitrates, while different recordings agree about half the time.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Fingerprint(Vec<bool>);
+
+impl Fingerprint {
+    /// Fingerprints interleaved samples.
+    pub fn from_samples(samples: impl Iterator<Item = i16>, sample_rate: u32, channels: u16) -> Self {
+        let frames_per_slice = (sample_rate as u128 * SLICE.as_millis() / 1000).max(1) as usize;
+        let samples_per_slice = frames_per_slice * channels.max(1) as usize;
+
+        let mut energies = Vec::with_capacity(SLICES);
+        let mut energy = 0f64;
+        let mut n = 0;
+
+        for sample in samples {
+            energy += (sample as f64).powi(2);
+            n += 1;
+
+            if n == samples_per_slice {
+                energies.push(energy);
+                energy = 0.;
+                n = 0;
+
+                if energies.len() == SLICES {
+                    break;
+                }
+            }
+        }
+
+        Self(energies.windows(2).map(|w| w[1] > w[0]).collect())
+    }
+
+    /// Decodes the beginning of the song and fingerprints it.
+    pub fn from_song(song: &Song) -> Option<Self> {
+        let file = File::open(&song.path).inspect_err(|err| log::warn!("Could not open {:?}: {err}", song.path)).ok()?;
+        let mut decoder = Decoder::new(BufReader::new(file))
+            .inspect_err(|err| log::warn!("Could not decode {:?}: {err}", song.path))
+            .ok()?;
+
+        let sample_rate = decoder.sample_rate();
+        let channels = decoder.channels();
+        let start = song.start_time + SKIP;
+
+        // Decoders that can't seek are read up to the start instead
+        let samples_to_skip = match decoder.try_seek(start) {
+            Ok(()) => 0,
+            Err(_) => (start.as_millis() * sample_rate as u128 / 1000) as usize * channels as usize,
+        };
+
+        let fingerprint = Self::from_samples(decoder.skip(samples_to_skip), sample_rate, channels);
+
+        if fingerprint.0.len() < SLICES - 1 {
+            log::debug!("{:?} is too short to fingerprint", song.path);
+            return None;
+        }
+
+        Some(fingerprint)
+    }
+
+    /// The share of the fingerprints that agree, from 0 to 1.
+    pub fn similarity(&self, other: &Self) -> f32 {
+        let len = self.0.len().min(other.0.len());
+
+        if len == 0 {
+            return 0.;
+        }
+
+        let same = self.0.iter().zip(&other.0).filter(|(a, b)| a == b).count();
+        same as f32 / len as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A tone that swells and fades irregularly, like music does.
+    fn samples(sample_rate: u32, channels: u16, gain: f64, seed: u64) -> Vec<i16> {
+        let frames = sample_rate as usize * 14;
+
+        (0..frames)
+            .flat_map(|i| {
+                let t = i as f64 / sample_rate as f64;
+                let envelope = ((t * 1.3 + seed as f64).sin() + (t * 3.7 * (seed + 1) as f64).sin() + 2.) / 4.;
+                let sample = ((t * 440. * std::f64::consts::TAU).sin() * envelope * gain * 8000.) as i16;
+                std::iter::repeat_n(sample, channels as usize)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn copies_match_and_different_songs_dont() {
+        let original = Fingerprint::from_samples(samples(44100, 2, 1., 0).into_iter(), 44100, 2);
+        let copy = Fingerprint::from_samples(samples(48000, 1, 0.7, 0).into_iter(), 48000, 1);
+        let other = Fingerprint::from_samples(samples(44100, 2, 1., 5).into_iter(), 44100, 2);
+
+        assert_eq!(original.0.len(), SLICES - 1);
+        assert!(original.similarity(&copy) >= SAME_RECORDING);
+        assert!(original.similarity(&other) < SAME_RECORDING);
+    }
+}
@@ -0,0 +1,502 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    panic,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+        Arc,
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{
+    components::{path_has_cue_extension, path_has_song_extension, path_is_not_hidden},
+    cue::CueSheet,
+    structs::{FileStamp, Song, JOLT_FILE_NAME},
+};
+
+/// How long to wait for a burst of file system events to settle before rescanning, so a file being copied or
+/// tagged is read once, rather than once per write.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Reading tags is mostly waiting on the disk, so a few more workers than cores doesn't hurt, but there's
+/// little to gain beyond this.
+const MAX_WORKERS: usize = 8;
+
+/// A change to the library, found by scanning or watching the library folders.
+#[derive(Debug)]
+pub enum LibraryUpdate {
+    /// The songs read from a new or changed file. They replace the songs the library had from that file, if any.
+    Songs {
+        path: PathBuf,
+        stamp: FileStamp,
+        songs: Vec<Song>,
+    },
+    /// A file or folder that no longer exists. Every song in it is listed among the missing files.
+    Removed(PathBuf),
+    /// The folder of a `.jolt` file that was created, changed or deleted. Its songs get its overrides anew.
+    JoltFileChanged(PathBuf),
+}
+
+/// How far along the scan in progress is.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScanProgress {
+    pub scanned: usize,
+    pub total: usize,
+    pub current_path: Option<PathBuf>,
+}
+
+/// A file to be read by one of the workers. Jobs of an older generation than the current one were cancelled.
+struct ScanJob {
+    generation: usize,
+    path: PathBuf,
+}
+
+/// State shared by the scanner, the threads walking folders and the workers.
+struct ScanState {
+    stamps: Mutex<BTreeMap<PathBuf, FileStamp>>,
+    generation: AtomicUsize,
+    scanned: AtomicUsize,
+    total: AtomicUsize,
+    current_path: Mutex<Option<PathBuf>>,
+}
+
+/// Finds the files to read in folders, and queues them up for the workers.
+#[derive(Clone)]
+struct ScanQueue {
+    state: Arc<ScanState>,
+    jobs: Sender<ScanJob>,
+    updates: Sender<LibraryUpdate>,
+}
+
+/// Reads songs and cue sheets into the library with a pool of worker threads, streaming the songs back as they're
+/// read, so large folders don't freeze the UI. Also keeps watching the library folders for changes, if so configured.
+pub struct LibraryScanner {
+    queue: ScanQueue,
+    receiver: Mutex<Receiver<LibraryUpdate>>,
+    watcher: Mutex<Option<RecommendedWatcher>>,
+}
+
+impl LibraryScanner {
+    /// Starts the workers. Files whose stamp matches the given one aren't read again when scanning library folders.
+    pub fn new(stamps: BTreeMap<PathBuf, FileStamp>) -> Self {
+        let (updates, receiver) = channel();
+        let (jobs, job_receiver) = channel::<ScanJob>();
+
+        let state = Arc::new(ScanState {
+            stamps: Mutex::new(stamps),
+            generation: AtomicUsize::new(0),
+            scanned: AtomicUsize::new(0),
+            total: AtomicUsize::new(0),
+            current_path: Mutex::new(None),
+        });
+
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let workers = thread::available_parallelism().map(|n| n.get()).unwrap_or(4).min(MAX_WORKERS);
+
+        for i in 0..workers {
+            let state = state.clone();
+            let job_receiver = job_receiver.clone();
+            let updates = updates.clone();
+
+            let t = thread::Builder::new().name(format!("library_scan_{i}")).spawn(move || {
+                loop {
+                    let job = job_receiver.lock().unwrap().recv();
+
+                    let Ok(job) = job else {
+                        break;
+                    };
+
+                    if job.generation != state.generation.load(Ordering::SeqCst) {
+                        continue;
+                    }
+
+                    *state.current_path.lock().unwrap() = Some(job.path.clone());
+
+                    // A file that can't be read mustn't take its worker down with it, nor leave the scan unfinished
+                    let update = panic::catch_unwind(|| read_file(&job.path)).unwrap_or_else(|_| {
+                        log::error!("reading {:?} panicked", job.path);
+                        unreadable_file(&job.path)
+                    });
+
+                    // The scan may have been cancelled while reading
+                    if job.generation != state.generation.load(Ordering::SeqCst) {
+                        continue;
+                    }
+
+                    if let LibraryUpdate::Songs { path, stamp, .. } = &update {
+                        state.stamps.lock().unwrap().insert(path.clone(), *stamp);
+                    }
+
+                    state.scanned.fetch_add(1, Ordering::SeqCst);
+
+                    if updates.send(update).is_err() {
+                        break;
+                    }
+                }
+
+                log::trace!("library_scan thread exit");
+            });
+
+            if let Err(err) = t {
+                log::error!("Error spawning library_scan thread! {:?}", err);
+            }
+        }
+
+        Self {
+            queue: ScanQueue { state, jobs, updates },
+            receiver: Mutex::new(receiver),
+            watcher: Mutex::new(None),
+        }
+    }
+
+    /// Scans the library folders in the background, reading only new and changed files. Known files inside
+    /// the folders that don't exist anymore are reported as removed.
+    pub fn scan_folders(&self, folders: Vec<PathBuf>, known_files: HashSet<PathBuf>) {
+        let queue = self.queue.clone();
+
+        self.spawn_walk(move || {
+            for path in missing_files(&known_files, &folders, |p| p.exists()) {
+                queue.remove(path);
+            }
+
+            for folder in &folders {
+                log::debug!("scanning library folder {:?}", folder);
+                queue.scan_folder(folder, true);
+            }
+        });
+    }
+
+    /// Reads every song and cue sheet in a folder and its sub-folders into the library, in the background.
+    pub fn add_directory(&self, path: PathBuf) {
+        let queue = self.queue.clone();
+
+        self.spawn_walk(move || {
+            queue.scan_folder(&path, false);
+        });
+    }
+
+    /// Keeps watching the folders for changes, rescanning whatever changes in them.
+    pub fn watch(&self, folders: &[PathBuf]) {
+        let (sender, receiver) = channel();
+
+        let mut watcher = match notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        }) {
+            Ok(watcher) => watcher,
+            Err(err) => {
+                log::error!("Could not watch the library folders: {:?}", err);
+                return;
+            }
+        };
+
+        for folder in folders {
+            if let Err(err) = watcher.watch(folder, RecursiveMode::Recursive) {
+                log::error!("Could not watch library folder {:?}: {:?}", folder, err);
+            }
+        }
+
+        *self.watcher.lock().unwrap() = Some(watcher);
+
+        let queue = self.queue.clone();
+
+        self.spawn_walk(move || {
+            watch_loop(receiver, &queue);
+        });
+    }
+
+    fn spawn_walk(&self, f: impl FnOnce() + Send + 'static) {
+        if let Err(err) = thread::Builder::new().name("library_walk".to_string()).spawn(f) {
+            log::error!("Error spawning library_walk thread! {:?}", err);
+        }
+    }
+
+    /// The progress of the scan in progress, if any.
+    pub fn progress(&self) -> Option<ScanProgress> {
+        let state = &self.queue.state;
+        let total = state.total.load(Ordering::SeqCst);
+        let scanned = state.scanned.load(Ordering::SeqCst);
+
+        if scanned >= total {
+            return None;
+        }
+
+        Some(ScanProgress {
+            scanned,
+            total,
+            current_path: state.current_path.lock().unwrap().clone(),
+        })
+    }
+
+    /// Stops the scan in progress. Files already read stay in the library.
+    pub fn cancel(&self) {
+        let state = &self.queue.state;
+
+        if self.progress().is_some() {
+            log::info!("library scan cancelled");
+        }
+
+        state.generation.fetch_add(1, Ordering::SeqCst);
+        state.total.store(0, Ordering::SeqCst);
+        state.scanned.store(0, Ordering::SeqCst);
+        *state.current_path.lock().unwrap() = None;
+    }
+
+    /// The updates found since the last call. Never blocks.
+    pub fn updates(&self) -> Vec<LibraryUpdate> {
+        self.receiver.lock().unwrap().try_iter().collect()
+    }
+}
+
+impl ScanQueue {
+    fn remove(&self, path: PathBuf) {
+        self.state.stamps.lock().unwrap().retain(|p, _| !p.starts_with(&path));
+        let _ = self.updates.send(LibraryUpdate::Removed(path));
+    }
+
+    /// Queues up the files of a folder and its sub-folders. When checking stamps, files that didn't change since they
+    /// were last read are skipped.
+    fn scan_folder(&self, dir: &Path, check_stamps: bool) {
+        let generation = self.state.generation.load(Ordering::SeqCst);
+        let mut files = vec![];
+
+        if !self.find_files(dir, generation, &mut files) {
+            return;
+        }
+
+        if check_stamps {
+            let stamps = self.state.stamps.lock().unwrap();
+            files.retain(|path| FileStamp::from_path(path).is_none_or(|stamp| stamps.get(path) != Some(&stamp)));
+        }
+
+        self.enqueue(generation, files);
+    }
+
+    /// Recursively collects the songs and cue sheets of a folder, leaving out the audio files of cue sheets.
+    /// Returns false if the scan got cancelled along the way.
+    fn find_files(&self, dir: &Path, generation: usize, files: &mut Vec<PathBuf>) -> bool {
+        if generation != self.state.generation.load(Ordering::SeqCst) {
+            return false;
+        }
+
+        let Ok(entries) = dir.read_dir() else {
+            log::warn!("could not read folder {:?}", dir);
+            return true;
+        };
+
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(path_is_not_hidden)
+            .collect();
+
+        paths.sort_unstable();
+
+        let cue_tracks = cue_sheet_audio_files(dir);
+
+        for path in paths {
+            if path.is_dir() {
+                if !self.find_files(&path, generation, files) {
+                    return false;
+                }
+            } else if is_library_file(&path) && !cue_tracks.contains(&path) {
+                files.push(path);
+            }
+        }
+
+        true
+    }
+
+    fn enqueue(&self, generation: usize, files: Vec<PathBuf>) {
+        if files.is_empty() {
+            return;
+        }
+
+        let state = &self.state;
+
+        // Start counting anew once the previous scan is done
+        if state.scanned.load(Ordering::SeqCst) >= state.total.load(Ordering::SeqCst) {
+            state.scanned.store(0, Ordering::SeqCst);
+            state.total.store(0, Ordering::SeqCst);
+        }
+
+        state.total.fetch_add(files.len(), Ordering::SeqCst);
+
+        for path in files {
+            let _ = self.jobs.send(ScanJob { generation, path });
+        }
+    }
+
+    /// Rescans a path touched by a file system event.
+    fn rescan_path(&self, path: &Path) {
+        if path.file_name().is_some_and(|name| name == JOLT_FILE_NAME) {
+            if let Some(dir) = path.parent() {
+                let _ = self.updates.send(LibraryUpdate::JoltFileChanged(dir.to_path_buf()));
+            }
+            return;
+        }
+
+        if !path.exists() {
+            self.remove(path.to_path_buf());
+            return;
+        }
+
+        if path.is_dir() {
+            self.scan_folder(path, true);
+            return;
+        }
+
+        // Changes to audio files played through a cue sheet are picked up when the cue sheet changes.
+        let is_cue_track = path.parent().is_some_and(|dir| cue_sheet_audio_files(dir).contains(path));
+
+        if !is_library_file(path) || is_cue_track {
+            return;
+        }
+
+        let unchanged = FileStamp::from_path(path)
+            .is_some_and(|stamp| self.state.stamps.lock().unwrap().get(path) == Some(&stamp));
+
+        if !unchanged {
+            self.enqueue(self.state.generation.load(Ordering::SeqCst), vec![path.to_path_buf()]);
+        }
+    }
+}
+
+/// Rescans the paths touched by file system events, until the watcher goes away.
+fn watch_loop(receiver: Receiver<notify::Result<Event>>, queue: &ScanQueue) {
+    let mut paths: HashSet<PathBuf> = HashSet::new();
+
+    loop {
+        let event = if paths.is_empty() {
+            receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            receiver.recv_timeout(WATCH_DEBOUNCE)
+        };
+
+        match event {
+            Ok(Ok(event)) => {
+                // Reading files, which the player does all the time, triggers access events.
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) {
+                    paths.extend(event.paths);
+                }
+            }
+            Ok(Err(err)) => {
+                log::error!("library folder watch error: {:?}", err);
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                for path in paths.drain() {
+                    queue.rescan_path(&path);
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                log::trace!("library folder watch exit");
+                return;
+            }
+        }
+    }
+}
+
+fn is_library_file(path: &Path) -> bool {
+    path_is_not_hidden(&path.to_path_buf()) && (path_has_song_extension(path) || path_has_cue_extension(path))
+}
+
+/// The audio files referenced by the cue sheets in a folder.
+fn cue_sheet_audio_files(dir: &Path) -> HashSet<PathBuf> {
+    let Ok(entries) = dir.read_dir() else {
+        return HashSet::new();
+    };
+
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| path_has_cue_extension(p))
+        .filter_map(|p| CueSheet::from_file(&p).ok())
+        .filter_map(|cue_sheet| cue_sheet.file())
+        .map(|file| dir.join(file.name()))
+        .collect()
+}
+
+/// Reads the songs of a song file or cue sheet.
+fn read_file(path: &Path) -> LibraryUpdate {
+    let Some(stamp) = FileStamp::from_path(path) else {
+        return LibraryUpdate::Removed(path.to_path_buf());
+    };
+
+    let songs = if path_has_cue_extension(path) {
+        match CueSheet::from_file(path) {
+            Ok(cue_sheet) => Song::from_cue_sheet(cue_sheet),
+            Err(err) => {
+                log::warn!("could not read cue sheet {:?}: {:?}", path, err);
+                vec![]
+            }
+        }
+    } else {
+        match Song::from_file(&path.to_path_buf()) {
+            Ok(song) => vec![song],
+            Err(err) => {
+                log::warn!("could not read song {:?}: {:?}", path, err);
+                vec![]
+            }
+        }
+    };
+
+    LibraryUpdate::Songs { path: path.to_path_buf(), stamp, songs }
+}
+
+/// A file without songs, as far as the library is concerned, so it isn't read again until it changes.
+fn unreadable_file(path: &Path) -> LibraryUpdate {
+    match FileStamp::from_path(path) {
+        Some(stamp) => LibraryUpdate::Songs { path: path.to_path_buf(), stamp, songs: vec![] },
+        None => LibraryUpdate::Removed(path.to_path_buf()),
+    }
+}
+
+/// The known files inside the library folders that don't exist anymore.
+fn missing_files(known_files: &HashSet<PathBuf>, folders: &[PathBuf], exists: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+    let mut missing: Vec<PathBuf> = known_files
+        .iter()
+        .filter(|path| folders.iter().any(|folder| path.starts_with(folder)))
+        .filter(|path| !exists(path))
+        .cloned()
+        .collect();
+
+    missing.sort_unstable();
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_files_inside_the_library_folders_can_go_missing() {
+        let known_files: HashSet<PathBuf> = [
+            "/music/Pink Floyd/Animals/01.flac",
+            "/music/Pink Floyd/Animals/02.flac",
+            "/downloads/song.mp3",
+        ].into_iter().map(PathBuf::from).collect();
+
+        let folders = [PathBuf::from("/music")];
+        let missing = missing_files(&known_files, &folders, |p| p.ends_with("01.flac"));
+
+        assert_eq!(missing, vec![PathBuf::from("/music/Pink Floyd/Animals/02.flac")]);
+    }
+
+    #[test]
+    fn reads_cue_sheets_without_a_file_as_no_songs() {
+        let path = std::env::temp_dir().join(format!("jolteon-library-scanner-test-{}.cue", std::process::id()));
+        std::fs::write(&path, "PERFORMER \"Pink Floyd\"\nTITLE \"Animals\"\n").unwrap();
+
+        match read_file(&path) {
+            LibraryUpdate::Songs { songs, .. } => assert!(songs.is_empty()),
+            update => panic!("expected no songs, got {update:?}"),
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,4 +1,5 @@
 mod app;
+mod auto_dj;
 mod config;
 mod constants;
 mod cue;
@@ -14,7 +15,13 @@ mod source;
 mod components;
 mod bye;
 mod files;
+mod library_scanner;
+mod fingerprint;
+mod cover_art;
+mod missing_files;
+mod playlist_files;
 
+use std::env;
 use std::error::Error;
 use std::io::stdout;
 use std::sync::mpsc::channel;
@@ -30,7 +37,7 @@ use futures::{
 };
 use log::{debug, error, info, Record};
 
-use crate::{app::App, mpris::create_mpris_player, term::reset_terminal, bye::bye};
+use crate::{app::App, files::LibraryStore, mpris::create_mpris_player, term::reset_terminal, bye::bye};
 
 pub enum Command {
     PlayPause,
@@ -75,6 +82,10 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     info!("Starting");
 
+    if env::args().any(|arg| arg == "--export-library") {
+        return export_library();
+    }
+
     let (player_command_sender, player_command_receiver) = channel();
 
     debug!("Starting mpris and player");
@@ -120,6 +131,15 @@ async fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// Writes the library to `library.toml`, for reading it or taking it elsewhere, without starting the player.
+fn export_library() -> Result<(), Box<dyn Error>> {
+    let library = LibraryStore::open()?.library();
+    library.to_file()?;
+
+    println!("Exported {} songs to ~/.config/jolteon/library.toml", library.songs.len());
+    Ok(())
+}
+
 fn set_panic_hook() {
     debug!("set_panic_hook");
     let original_hook = std::panic::take_hook();
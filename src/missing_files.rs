@@ -0,0 +1,339 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    components::{path_has_song_extension, path_is_not_hidden},
+    structs::{normalize_tag, Song, SongId},
+};
+
+/// A file found by its tags is expected to be about as long as the missing one. See [`crate::structs::find_duplicates`].
+const MAX_LENGTH_DIFFERENCE: Duration = Duration::from_secs(2);
+
+/// Keeps track of the songs of the library, playlists and queue whose files no longer exist, and searches for where
+/// they went. Both checking and searching happen in the background, since they can take a while on large or slow drives.
+#[derive(Default)]
+pub struct MissingFiles {
+    missing: Arc<Mutex<MissingSongs>>,
+    /// The new path of each missing file found so far.
+    new_paths: Arc<Mutex<BTreeMap<PathBuf, PathBuf>>>,
+    searching: Arc<AtomicBool>,
+    /// Whether the missing songs or their new paths changed since the last time it was asked.
+    changed: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+struct MissingSongs {
+    /// Sorted by path and start time.
+    songs: Vec<Song>,
+    /// The files of the songs, to tell quickly whether a song is missing.
+    paths: HashSet<PathBuf>,
+}
+
+impl MissingSongs {
+    /// Adds the songs that aren't missing yet. Returns whether there were any.
+    fn add(&mut self, mut songs: Vec<Song>) -> bool {
+        let known: HashSet<SongId> = self.songs.iter().map(SongId::of).collect();
+        songs.retain(|song| !known.contains(&SongId::of(song)));
+
+        if songs.is_empty() {
+            return false;
+        }
+
+        self.paths.extend(songs.iter().map(|song| song.path.clone()));
+        self.songs.extend(songs);
+        self.songs.sort_by(|a, b| a.path.cmp(&b.path).then(a.start_time.cmp(&b.start_time)));
+
+        true
+    }
+
+    /// Forgets the songs of the files. Returns whether there were any.
+    fn forget(&mut self, paths: &HashSet<PathBuf>) -> bool {
+        let count = self.songs.len();
+
+        self.songs.retain(|s| !paths.contains(&s.path));
+        self.paths.retain(|path| !paths.contains(path));
+
+        self.songs.len() != count
+    }
+}
+
+impl MissingFiles {
+    /// Checks whether the files of the songs exist, adding the ones that don't to the missing songs.
+    pub fn check(&self, songs: Vec<Song>) {
+        let missing = self.missing.clone();
+        let changed = self.changed.clone();
+
+        let spawned = thread::Builder::new().name("missing_files".to_string()).spawn(move || {
+            let mut exists: HashMap<PathBuf, bool> = HashMap::new();
+            let found: Vec<Song> = songs
+                .into_iter()
+                .filter(|song| !*exists.entry(song.path.clone()).or_insert_with(|| song.path.exists()))
+                .collect();
+
+            if found.is_empty() {
+                return;
+            }
+
+            log::info!("{} songs point to files that don't exist", found.len());
+
+            if missing.lock().unwrap().add(found) {
+                changed.store(true, Ordering::SeqCst);
+            }
+        });
+
+        if let Err(err) = spawned {
+            log::error!("Could not spawn the missing_files thread: {err}");
+        }
+    }
+
+    /// Adds songs whose files are already known to be gone, such as the ones the library scanner found gone.
+    pub fn add(&self, songs: Vec<Song>) {
+        if self.missing.lock().unwrap().add(songs) {
+            self.changed.store(true, Ordering::SeqCst);
+        }
+    }
+
+    pub fn songs(&self) -> Vec<Song> {
+        self.missing.lock().unwrap().songs.clone()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.missing.lock().unwrap().songs.is_empty()
+    }
+
+    pub fn is_missing(&self, path: &Path) -> bool {
+        self.missing.lock().unwrap().paths.contains(path)
+    }
+
+    pub fn new_path(&self, path: &Path) -> Option<PathBuf> {
+        self.new_paths.lock().unwrap().get(path).cloned()
+    }
+
+    pub fn new_paths(&self) -> BTreeMap<PathBuf, PathBuf> {
+        self.new_paths.lock().unwrap().clone()
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.searching.load(Ordering::SeqCst)
+    }
+
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::SeqCst)
+    }
+
+    /// Searches the folder and its subfolders for the files of the missing songs, by name, tags and length.
+    pub fn search(&self, root: PathBuf) {
+        if self.searching.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let missing = self.songs();
+        let new_paths = self.new_paths.clone();
+        let searching = self.searching.clone();
+        let changed = self.changed.clone();
+
+        let spawned = thread::Builder::new().name("relink".to_string()).spawn(move || {
+            log::info!("searching {:?} for {} missing songs", root, missing.len());
+
+            let mut files = vec![];
+            list_song_files(&root, &mut files);
+
+            let found = find_new_paths(&missing, &files, |path| Song::from_file(&path.to_path_buf()).ok());
+            log::info!("found {} of the missing files", found.len());
+
+            new_paths.lock().unwrap().extend(found);
+            searching.store(false, Ordering::SeqCst);
+            changed.store(true, Ordering::SeqCst);
+        });
+
+        if let Err(err) = spawned {
+            log::error!("Could not spawn the relink thread: {err}");
+            self.searching.store(false, Ordering::SeqCst);
+        }
+    }
+
+    /// Forgets the missing songs of the given files, once they've been pointed to their new paths.
+    pub fn forget(&self, paths: &HashSet<PathBuf>) {
+        if self.missing.lock().unwrap().forget(paths) {
+            self.new_paths.lock().unwrap().retain(|path, _| !paths.contains(path));
+            self.changed.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+fn list_song_files(folder: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(folder) else {
+        log::warn!("Could not read {:?}", folder);
+        return;
+    };
+
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(path_is_not_hidden) {
+        if path.is_dir() {
+            list_song_files(&path, files);
+        } else if path_has_song_extension(&path) {
+            files.push(path);
+        }
+    }
+}
+
+fn file_name_key(path: &Path) -> Option<String> {
+    path.file_name().map(|name| name.to_string_lossy().to_lowercase())
+}
+
+/// Whether the song read from a file has the artist and title of the missing song, and about its length.
+fn is_same_song(missing: &Song, candidate: &Song) -> bool {
+    let artist = |song: &Song| song.artist.as_deref().or(song.album_artist.as_deref()).map(normalize_tag);
+
+    !normalize_tag(&missing.title).is_empty()
+        && normalize_tag(&missing.title) == normalize_tag(&candidate.title)
+        && artist(missing) == artist(candidate)
+        && missing.length.abs_diff(candidate.length) <= MAX_LENGTH_DIFFERENCE
+}
+
+/// Finds the new path of each missing file among the given files. A file with the same name is taken, as long as
+/// it's the only one, or the only one with the same tags. Failing that, a file with the same artist, title and
+/// length is taken, reading the tags of every file if needed. Tracks of cue sheets are only looked for by name, since
+/// the tags of their file are the ones of the whole album. Each file is taken for one missing file at most.
+pub fn find_new_paths(
+    missing: &[Song],
+    files: &[PathBuf],
+    mut read: impl FnMut(&Path) -> Option<Song>,
+) -> BTreeMap<PathBuf, PathBuf> {
+    let mut by_name: HashMap<String, Vec<&PathBuf>> = HashMap::new();
+    for file in files {
+        if let Some(key) = file_name_key(file) {
+            by_name.entry(key).or_default().push(file);
+        }
+    }
+
+    let mut tags: HashMap<PathBuf, Option<Song>> = HashMap::new();
+    let mut read_tags = |file: &PathBuf| -> Option<Song> { tags.entry(file.clone()).or_insert_with(|| read(file)).clone() };
+
+    let cue_files: HashSet<&PathBuf> = missing.iter().filter(|s| !s.start_time.is_zero()).map(|s| &s.path).collect();
+
+    // The songs of a cue sheet share their file, so each missing file is looked for once
+    let mut missing_files: Vec<&Song> = vec![];
+    for song in missing {
+        if !missing_files.iter().any(|s| s.path == song.path) {
+            missing_files.push(song);
+        }
+    }
+
+    let mut new_paths = BTreeMap::new();
+    let mut taken: HashSet<PathBuf> = HashSet::new();
+
+    for song in &missing_files {
+        let same_name: Vec<&PathBuf> = file_name_key(&song.path)
+            .and_then(|key| by_name.get(&key))
+            .map(|files| files.iter().copied().filter(|f| !taken.contains(*f)).collect())
+            .unwrap_or_default();
+
+        let found = match same_name.as_slice() {
+            [file] => Some(*file),
+            _ if cue_files.contains(&song.path) => None,
+            files => {
+                let matching: Vec<&PathBuf> =
+                    files.iter().copied().filter(|f| read_tags(f).is_some_and(|c| is_same_song(song, &c))).collect();
+
+                match matching.as_slice() {
+                    [file] => Some(*file),
+                    _ => None,
+                }
+            }
+        };
+
+        if let Some(file) = found {
+            taken.insert(file.clone());
+            new_paths.insert(song.path.clone(), file.clone());
+        }
+    }
+
+    let not_found: Vec<&Song> =
+        missing_files.into_iter().filter(|s| !new_paths.contains_key(&s.path) && !cue_files.contains(&s.path)).collect();
+
+    for song in not_found {
+        let matching: Vec<&PathBuf> = files
+            .iter()
+            .filter(|f| !taken.contains(*f))
+            .filter(|f| read_tags(f).is_some_and(|c| is_same_song(song, &c)))
+            .collect();
+
+        if let [file] = matching.as_slice() {
+            taken.insert((*file).clone());
+            new_paths.insert(song.path.clone(), (*file).clone());
+        }
+    }
+
+    new_paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song(path: &str, artist: &str, title: &str, secs: u64) -> Song {
+        Song {
+            path: PathBuf::from(path),
+            title: title.to_string(),
+            artist: Some(artist.to_string()),
+            length: Duration::from_secs(secs),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn finds_files_by_name_and_then_by_tags() {
+        let missing = vec![
+            song("/old/Animals/03 Dogs.flac", "Pink Floyd", "Dogs", 1024),
+            song("/old/Animals/04 Pigs.flac", "Pink Floyd", "Pigs", 688),
+            song("/old/Wish/01 Shine On.flac", "Pink Floyd", "Shine On You Crazy Diamond", 811),
+            song("/old/Unknown/track.mp3", "Nobody", "Nothing", 100),
+        ];
+
+        let on_disk = [
+            song("/new/Pink Floyd/Animals/03 Dogs.flac", "Pink Floyd", "Dogs", 1024),
+            song("/new/Pink Floyd/Animals/04 Pigs.flac", "Pink Floyd", "Pigs (Three Different Ones)", 688),
+            song("/new/Other/04 Pigs.flac", "Someone Else", "Pigs", 200),
+            song("/new/Pink Floyd/Wish You Were Here/01 - Shine On You Crazy Diamond.flac", "Pink Floyd", "Shine On You Crazy Diamond", 812),
+        ];
+
+        let files: Vec<PathBuf> = on_disk.iter().map(|s| s.path.clone()).collect();
+        let new_paths = find_new_paths(&missing, &files, |path| on_disk.iter().find(|s| s.path == path).cloned());
+
+        assert_eq!(new_paths, BTreeMap::from([
+            (PathBuf::from("/old/Animals/03 Dogs.flac"), files[0].clone()),
+            (PathBuf::from("/old/Animals/04 Pigs.flac"), files[1].clone()),
+            (PathBuf::from("/old/Wish/01 Shine On.flac"), files[3].clone()),
+        ]));
+    }
+
+    #[test]
+    fn gone_files_are_listed_once_until_they_come_back() {
+        let missing_files = MissingFiles::default();
+        let dogs = song("/music/Animals/03 Dogs.flac", "Pink Floyd", "Dogs", 1024);
+
+        missing_files.add(vec![dogs.clone()]);
+        missing_files.add(vec![dogs.clone()]);
+
+        assert!(missing_files.take_changed());
+        assert!(missing_files.is_missing(&dogs.path));
+        assert_eq!(missing_files.songs(), vec![dogs.clone()]);
+
+        missing_files.forget(&HashSet::from([PathBuf::from("/music/Animals/04 Pigs.flac")]));
+        assert!(!missing_files.take_changed());
+
+        missing_files.forget(&HashSet::from([dogs.path.clone()]));
+        assert!(missing_files.take_changed());
+        assert!(!missing_files.is_missing(&dogs.path));
+    }
+}
@@ -15,7 +15,7 @@ use rodio::OutputStreamHandle;
 
 use crate::{
     cue::CueSheet,
-    structs::{Queue, Song},
+    structs::{play_threshold, Queue, Song},
     source::{Source, Controls},
     ui::KeyboardHandlerRef,
 };
@@ -33,6 +33,16 @@ pub struct Player {
     volume: Arc<Mutex<f32>>,
     pause: Arc<AtomicBool>,
     position: Arc<Mutex<Duration>>,
+    play_events: Arc<Mutex<Vec<PlayEvent>>>,
+}
+
+/// Something that happened to a song while it played, for the library to keep count of.
+#[derive(Debug)]
+pub enum PlayEvent {
+    /// The song was heard past its play threshold. See [`play_threshold`].
+    Played(Song),
+    /// The song was stopped, or moved past, before reaching its play threshold.
+    Skipped(Song),
 }
 
 #[derive(Debug)]
@@ -62,6 +72,7 @@ impl Player {
             volume: Arc::new(Mutex::new(1.0)),
             pause: Arc::new(AtomicBool::new(false)),
             position: Arc::new(Mutex::new(Duration::ZERO)),
+            play_events: Arc::new(Mutex::new(vec![])),
         }
     }
 
@@ -83,10 +94,21 @@ impl Player {
         pos.saturating_sub(Duration::from_secs(start_time))
     }
 
+    /// How much is left of the song currently playing.
+    pub fn time_remaining(&self) -> Duration {
+        let length = self.currently_playing.lock().unwrap().as_ref().map(|song| song.length).unwrap_or(Duration::ZERO);
+        length.saturating_sub(self.get_pos())
+    }
+
     pub fn currently_playing(&self) -> Arc<Mutex<Option<Song>>> {
         self.currently_playing.clone()
     }
 
+    /// The songs played and skipped since the last call.
+    pub fn take_play_events(&self) -> Vec<PlayEvent> {
+        std::mem::take(&mut *self.play_events.lock().unwrap())
+    }
+
     pub fn spawn(&self) {
         let output_stream = self.output_stream.clone();
         let command_receiver = self.command_receiver.lock().unwrap().take().unwrap();
@@ -97,6 +119,7 @@ impl Player {
         let position = self.position.clone();
         let volume = self.volume.clone();
         let pause = self.pause.clone();
+        let play_events = self.play_events.clone();
 
         let (song_ended_tx, song_ended_rx) = channel::<()>();
         let is_stopped = self.is_stopped.clone();
@@ -132,6 +155,9 @@ impl Player {
                 let path = song.path.clone();
                 let start_time = song.start_time.clone();
                 let length = song.length.clone();
+                let threshold = play_threshold(length);
+                let played_song = song.clone();
+                let mut is_counted = false;
 
                 is_stopped.store(false, Ordering::SeqCst);
 
@@ -187,7 +213,7 @@ impl Player {
                 };
 
 
-                let mut source = Source::from_file(path, periodic_access, position.clone(), {
+                let source = Source::from_file(path.clone(), periodic_access, position.clone(), {
                     let song_ended_tx = song_ended_tx.clone();
                     move || {
                         log::trace!("source.on_playback_ended");
@@ -195,6 +221,16 @@ impl Player {
                     }
                 });
 
+                // Songs whose files are gone, or can't be decoded, are skipped
+                let mut source = match source {
+                    Ok(source) => source,
+                    Err(err) => {
+                        log::error!("Could not play {:?}: {}", path, err);
+                        set_currently_playing(None);
+                        continue;
+                    }
+                };
+
                 if start_time > Duration::ZERO {
                     log::debug!("start_time > Duration::ZERO, {:?}", start_time);
                     if let Err(err) = source.seek(start_time) {
@@ -214,16 +250,32 @@ impl Player {
                 // When woken up, we check whether we need to immediately exit.
                 // If we don't, we recalculate the remaining time until the song ends,
                 // and then go back to bed.
+                // Until the song counts as played, we wake up at the play threshold too, to count it.
+                let mut is_waiting_for_threshold = false;
+
                 loop {
                     let sleepy_time = if pause.load(Ordering::SeqCst) {
                         Duration::MAX
                     } else {
                         let abs_pos = position.lock().unwrap().saturating_sub(start_time);
+
+                        if !is_counted && abs_pos >= threshold {
+                            is_counted = true;
+                            play_events.lock().unwrap().push(PlayEvent::Played(played_song.clone()));
+                        }
+
                         if abs_pos >= length {
                             log::debug!("inner loop: pos >= length, {:?} > {:?}", abs_pos, length);
                             break;
                         }
-                        length - abs_pos
+
+                        is_waiting_for_threshold = !is_counted;
+
+                        if is_counted {
+                            length - abs_pos
+                        } else {
+                            threshold - abs_pos
+                        }
                     };
 
                     // log::debug!("inner loop: sleepy_time! {:?}", sleepy_time);
@@ -243,6 +295,9 @@ impl Player {
                                     pause.store(true, Ordering::SeqCst);
                                 }
                                 Command::Stop => {
+                                    if !is_counted {
+                                        play_events.lock().unwrap().push(PlayEvent::Skipped(played_song.clone()));
+                                    }
                                     break;
                                 }
                                 Command::Seek(seek) => {
@@ -270,6 +325,9 @@ impl Player {
                                     // If we'd seek past song end, skip seeking and just move to next song instead.
                                     if target > length + start_time {
                                         log::debug!("Seeking past end");
+                                        if !is_counted {
+                                            play_events.lock().unwrap().push(PlayEvent::Skipped(played_song.clone()));
+                                        }
                                         break;
                                     }
 
@@ -280,6 +338,11 @@ impl Player {
                                 }
                             }
                         }
+                        Err(RecvTimeoutError::Timeout) if is_waiting_for_threshold => {
+                            // Playing song reached its play threshold.
+                            is_counted = true;
+                            play_events.lock().unwrap().push(PlayEvent::Played(played_song.clone()));
+                        }
                         Err(RecvTimeoutError::Timeout) => {
                             // Playing song reached its end. We want to move on to the next song.
                             log::trace!("Player Command Timeout");
@@ -314,10 +377,24 @@ impl Player {
         }
     }
 
+    /// Plays the songs right away, in order, ahead of the rest of the queue.
+    pub fn play_songs(&self, songs: Vec<Song>) {
+        log::debug!("player.play_songs({})", songs.len());
+        self.queue_items.prepend(songs);
+
+        if self.currently_playing.lock().unwrap().is_some() {
+            self.stop();
+        }
+    }
+
     pub fn enqueue_song(&self, song: Song) {
         self.queue_items.add_back(song);
     }
 
+    pub fn enqueue_songs(&self, songs: Vec<Song>) {
+        self.queue_items.append(&mut std::collections::VecDeque::from(songs));
+    }
+
     pub fn enqueue_cue(&self, cue_sheet: CueSheet) {
         let songs = Song::from_cue_sheet(cue_sheet);
         self.queue_items.append(&mut std::collections::VecDeque::from(songs));
@@ -402,6 +479,8 @@ impl KeyboardHandlerRef<'_> for Player {
             KeyCode::Down | KeyCode::Char('j') => self.queue().select_next(),
             KeyCode::Up | KeyCode::Char('k') => self.queue().select_previous(),
             KeyCode::Delete => self.queue().remove_selected(),
+            KeyCode::Char('s') => self.queue().shuffle(),
+            KeyCode::Char('S') => self.queue().shuffle_albums(),
             _ => {}
         };
         true
@@ -0,0 +1,8 @@
+mod m3u;
+mod playlist_file;
+mod pls;
+mod resolver;
+mod xspf;
+
+pub use playlist_file::*;
+pub use resolver::*;
@@ -0,0 +1,113 @@
+use std::path::{Path, PathBuf};
+
+use crate::structs::Song;
+
+use super::playlist_file::{parse_seconds, resolve_path, song_name, split_song_name, PlaylistFileEntry};
+
+/// Reads the entries of an `.m3u` or `.m3u8` playlist: one path per line, relative to the playlist's folder or absolute,
+/// each optionally preceded by an `#EXTINF:<seconds>,<artist> - <title>` line. Other comments and streams are skipped.
+pub fn parse(text: &str, folder: &Path) -> Vec<PlaylistFileEntry> {
+    let mut entries = vec![];
+    let mut info: Option<PlaylistFileEntry> = None;
+
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            info = Some(parse_extinf(extinf));
+        } else if line.starts_with('#') {
+            continue;
+        } else if line.contains("://") && !line.starts_with("file://") {
+            log::debug!("skipping stream {line} in playlist");
+            info = None;
+        } else {
+            entries.push(PlaylistFileEntry {
+                path: resolve_path(folder, line),
+                ..info.take().unwrap_or_default()
+            });
+        }
+    }
+
+    entries
+}
+
+/// Such as `253 tvg-logo="…",Pink Floyd - Dogs`. A length of -1 stands for an unknown one.
+fn parse_extinf(extinf: &str) -> PlaylistFileEntry {
+    let (length, name) = extinf.split_once(',').unwrap_or((extinf, ""));
+    let (artist, title) = split_song_name(name);
+
+    PlaylistFileEntry {
+        artist,
+        title,
+        length: length.split_whitespace().next().and_then(parse_seconds),
+        ..Default::default()
+    }
+}
+
+/// Writes the songs as an extended M3U playlist, with the given path for each one.
+pub fn write(name: &str, songs: &[&Song], song_path: impl Fn(&Song) -> PathBuf) -> String {
+    let mut text = format!("#EXTM3U\n#PLAYLIST:{name}\n");
+
+    for song in songs {
+        let length = match song.length.as_secs() {
+            0 => -1,
+            secs => secs as i64,
+        };
+
+        text.push_str(&format!("#EXTINF:{length},{}\n{}\n", song_name(song), song_path(song).display()));
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn reads_extended_and_plain_playlists() {
+        let text = "\
+#EXTM3U
+#EXTINF:1024,Pink Floyd - Dogs
+../Pink Floyd/Animals/03 Dogs.flac
+
+# a comment
+#EXTINF:-1 tvg-name=\"Radio\",Some Radio
+http://radio.example.com/stream
+/music/Sigur Rós/Takk/02 Glósóli.flac
+";
+        let entries = parse(text, Path::new("/music/Playlists"));
+
+        assert_eq!(entries, vec![
+            PlaylistFileEntry {
+                path: PathBuf::from("/music/Playlists/../Pink Floyd/Animals/03 Dogs.flac"),
+                artist: Some("Pink Floyd".to_string()),
+                title: Some("Dogs".to_string()),
+                album: None,
+                length: Some(Duration::from_secs(1024)),
+            },
+            PlaylistFileEntry {
+                path: PathBuf::from("/music/Sigur Rós/Takk/02 Glósóli.flac"),
+                ..Default::default()
+            },
+        ]);
+    }
+
+    #[test]
+    fn writes_extended_playlists() {
+        let song = |path: &str, title: &str, secs: u64| Song {
+            path: PathBuf::from(path),
+            artist: Some("Tim Buckley".to_string()),
+            title: title.to_string(),
+            length: Duration::from_secs(secs),
+            ..Default::default()
+        };
+
+        let songs = [song("/music/Happy Sad.flac", "Strange Feelin'", 0), song("/music/Song.mp3", "Song", 200)];
+
+        assert_eq!(
+            write("Mix", &songs.iter().collect::<Vec<_>>(), |s| s.path.clone()),
+            "#EXTM3U\n#PLAYLIST:Mix\n#EXTINF:-1,Tim Buckley - Strange Feelin'\n/music/Happy Sad.flac\n#EXTINF:200,Tim Buckley - Song\n/music/Song.mp3\n",
+        );
+    }
+}
@@ -0,0 +1,496 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io,
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::path_has_cue_extension,
+    cue::CueSheet,
+    structs::{normalize_tag, Song},
+    toml::write_file_atomically,
+};
+
+use super::{m3u, pls, xspf};
+
+/// A song found in the library by its tags is expected to be about as long as the one listed.
+const MAX_LENGTH_DIFFERENCE: Duration = Duration::from_secs(2);
+
+/// The formats playlists are read from and written to.
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaylistFormat {
+    /// Extended M3U, in UTF-8. Plain `.m3u` files are read as M3U too.
+    #[default]
+    M3u8,
+    Xspf,
+    Pls,
+}
+
+impl PlaylistFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+
+        match extension.as_str() {
+            "m3u" | "m3u8" => Some(PlaylistFormat::M3u8),
+            "xspf" => Some(PlaylistFormat::Xspf),
+            "pls" => Some(PlaylistFormat::Pls),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PlaylistFormat::M3u8 => "m3u8",
+            PlaylistFormat::Xspf => "xspf",
+            PlaylistFormat::Pls => "pls",
+        }
+    }
+
+    fn parse(&self, text: &str, folder: &Path) -> Vec<PlaylistFileEntry> {
+        match self {
+            PlaylistFormat::M3u8 => m3u::parse(text, folder),
+            PlaylistFormat::Xspf => xspf::parse(text, folder),
+            PlaylistFormat::Pls => pls::parse(text, folder),
+        }
+    }
+
+    fn write(&self, name: &str, songs: &[&Song], song_path: impl Fn(&Song) -> PathBuf) -> String {
+        match self {
+            PlaylistFormat::M3u8 => m3u::write(name, songs, song_path),
+            PlaylistFormat::Xspf => xspf::write(name, songs, song_path),
+            PlaylistFormat::Pls => pls::write(name, songs, song_path),
+        }
+    }
+}
+
+/// A song as listed in a playlist file: where its file is, and its artist, title, album and length, for formats that
+/// have them.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PlaylistFileEntry {
+    pub path: PathBuf,
+    pub artist: Option<String>,
+    pub title: Option<String>,
+    pub album: Option<String>,
+    pub length: Option<Duration>,
+}
+
+impl PlaylistFileEntry {
+    /// The songs of the entry: the song of its file, read from its tags, or the tracks of a cue sheet.
+    fn read_songs(&self) -> Option<Vec<Song>> {
+        if !self.path.is_file() {
+            return None;
+        }
+
+        if path_has_cue_extension(&self.path) {
+            if let Ok(cue_sheet) = CueSheet::from_file(&self.path) {
+                return Some(Song::from_cue_sheet(cue_sheet));
+            }
+        }
+
+        Song::from_file(&self.path).ok().map(|song| vec![song])
+    }
+
+    /// Whether the song has the artist, title and album of the entry, as far as the entry has them, and about its length.
+    fn is_song(&self, song: &Song) -> bool {
+        let same = |listed: &Option<String>, tagged: Option<&String>| -> bool {
+            listed.as_deref().is_none_or(|listed| tagged.is_some_and(|tagged| normalize_tag(listed) == normalize_tag(tagged)))
+        };
+
+        self.title.is_some()
+            && same(&self.title, Some(&song.title))
+            && same(&self.artist, song.artist.as_ref().or(song.album_artist.as_ref()))
+            && same(&self.album, song.album.as_ref())
+            && self.length.is_none_or(|length| length.abs_diff(song.length) <= MAX_LENGTH_DIFFERENCE)
+    }
+
+    /// The song as listed, for files that can't be read, such as missing ones, so they can be relinked later on.
+    fn listed_song(&self) -> Song {
+        let file_name = self.path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+
+        Song {
+            path: self.path.clone(),
+            artist: self.artist.clone(),
+            title: self.title.clone().unwrap_or(file_name),
+            album: self.album.clone(),
+            length: self.length.unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+}
+
+/// The songs of the library, by their normalized title, and the files that hold the tracks of cue sheets.
+struct LibraryIndex {
+    by_title: HashMap<String, Vec<Song>>,
+    cue_files: HashSet<PathBuf>,
+}
+
+impl LibraryIndex {
+    fn new(songs: Vec<Song>) -> Self {
+        let mut index = Self {
+            by_title: HashMap::new(),
+            cue_files: HashSet::new(),
+        };
+
+        for song in songs {
+            // Only the first track of a cue sheet starts at the start of its file
+            if !song.start_time.is_zero() {
+                index.cue_files.insert(song.path.clone());
+            }
+
+            index.by_title.entry(normalize_tag(&song.title)).or_default().push(song);
+        }
+
+        index
+    }
+
+    /// The song of the library the entry lists, among the ones `f` accepts.
+    fn find(&self, entry: &PlaylistFileEntry, f: impl Fn(&Song) -> bool) -> Option<&Song> {
+        entry
+            .title
+            .as_ref()
+            .and_then(|title| self.by_title.get(&normalize_tag(title)))
+            .and_then(|songs| songs.iter().find(|s| f(s) && entry.is_song(s)))
+    }
+}
+
+/// The songs of a playlist file, along with the entries whose files couldn't be read nor found in the library.
+/// Those are still among the songs, as listed.
+#[derive(Debug, Default, Clone)]
+pub struct ResolvedPlaylist {
+    pub name: String,
+    pub songs: Vec<Song>,
+    pub unresolved: Vec<PlaylistFileEntry>,
+}
+
+/// A playlist file, such as an `.m3u`, found on disk.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PlaylistFile {
+    path: PathBuf,
+    entries: Vec<PlaylistFileEntry>,
+}
+
+impl PlaylistFile {
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let format = PlaylistFormat::from_path(path).ok_or(io::Error::new(io::ErrorKind::InvalidInput, "not a playlist file"))?;
+        let text = read_text(path)?;
+        let folder = path.parent().unwrap_or(Path::new(""));
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries: format.parse(&text, folder),
+        })
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    /// The name of the file, without its extension.
+    pub fn name(&self) -> String {
+        self.path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default()
+    }
+
+    /// The songs of the playlist, reading the tags of their files. Entries whose files can't be read, such as ones
+    /// written by players on other computers, are looked for by their tags in the songs of the library, which are only
+    /// asked for once they're needed.
+    ///
+    /// Tracks of cue sheets are listed by the file they share, which reads as one song spanning all of them, so entries
+    /// of such files are looked for by their title and length among the tracks of the file in the library first.
+    pub fn resolve(&self, mut library_songs: impl FnMut() -> Vec<Song>) -> ResolvedPlaylist {
+        let mut resolved = ResolvedPlaylist {
+            name: self.name(),
+            ..Default::default()
+        };
+
+        let mut library: Option<LibraryIndex> = None;
+
+        for entry in &self.entries {
+            if let Some(songs) = entry.read_songs() {
+                let track = if entry.title.is_some() && !path_has_cue_extension(&entry.path) {
+                    let library = library.get_or_insert_with(|| LibraryIndex::new(library_songs()));
+
+                    library
+                        .cue_files
+                        .contains(&entry.path)
+                        .then(|| library.find(entry, |s| s.path == entry.path))
+                        .flatten()
+                } else {
+                    None
+                };
+
+                match track {
+                    Some(track) => resolved.songs.push(track.clone()),
+                    None => resolved.songs.extend(songs),
+                }
+
+                continue;
+            }
+
+            let library = library.get_or_insert_with(|| LibraryIndex::new(library_songs()));
+
+            if let Some(song) = library.find(entry, |_| true) {
+                resolved.songs.push(song.clone());
+            } else {
+                log::warn!("{:?}, listed in {:?}, was neither found on disk nor in the library", entry.path, self.path);
+                resolved.songs.push(entry.listed_song());
+                resolved.unresolved.push(entry.clone());
+            }
+        }
+
+        resolved
+    }
+}
+
+pub fn path_has_playlist_extension(path: &Path) -> bool {
+    PlaylistFormat::from_path(path).is_some()
+}
+
+/// The name of the playlist, with the characters that aren't allowed in file names on some systems replaced. Empty
+/// names become "Untitled", and names starting with a dot get a `_` before it, so the file isn't hidden.
+pub fn playlist_file_name(name: &str) -> String {
+    let file_name: String = name
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control() { '_' } else { c })
+        .collect();
+
+    if file_name.trim().is_empty() {
+        "Untitled".to_string()
+    } else if file_name.starts_with('.') {
+        format!("_{file_name}")
+    } else {
+        file_name
+    }
+}
+
+/// The path in the folder for a file named after the playlist, such as "Mix.m3u8", or "Mix (2).m3u8" and so on if
+/// that one's taken.
+pub fn free_playlist_path(folder: &Path, name: &str, extension: &str, is_taken: impl Fn(&Path) -> bool) -> PathBuf {
+    let file_name = playlist_file_name(name);
+
+    (1..)
+        .map(|n| match n {
+            1 => folder.join(format!("{file_name}.{extension}")),
+            n => folder.join(format!("{file_name} ({n}).{extension}")),
+        })
+        .find(|path| !is_taken(path))
+        .unwrap()
+}
+
+/// Writes the songs to a playlist file at the given path, in the format of its extension. With `relative`, the paths of
+/// the songs are written relative to the folder of the file, when they share more than the root with it, so the music
+/// folder and its playlists can be moved together. Tracks of cue sheets are listed by the file they share, each with
+/// its own title and length, which tell them apart when the playlist is read again.
+pub fn write_playlist_file(path: &Path, name: &str, songs: &[Song], relative: bool) -> io::Result<()> {
+    let format = PlaylistFormat::from_path(path).unwrap_or_default();
+    let folder = path.parent().unwrap_or(Path::new(""));
+    let songs: Vec<&Song> = songs.iter().collect();
+
+    let song_path = |song: &Song| -> PathBuf {
+        if relative {
+            relative_path(folder, &song.path).unwrap_or(song.path.clone())
+        } else {
+            song.path.clone()
+        }
+    };
+
+    log::info!("writing {} songs to {:?}", songs.len(), path);
+    write_file_atomically(path, format.write(name, &songs, song_path).as_bytes())
+}
+
+/// Such as "Pink Floyd - Dogs", as playlists list songs.
+pub(super) fn song_name(song: &Song) -> String {
+    match &song.artist {
+        Some(artist) => format!("{artist} - {}", song.title),
+        None => song.title.clone(),
+    }
+}
+
+/// The artist and title out of a name such as "Pink Floyd - Dogs".
+pub(super) fn split_song_name(name: &str) -> (Option<String>, Option<String>) {
+    let name = name.trim();
+
+    match name.split_once(" - ") {
+        Some((artist, title)) => (Some(artist.trim().to_string()), Some(title.trim().to_string())),
+        None if name.is_empty() => (None, None),
+        None => (None, Some(name.to_string())),
+    }
+}
+
+/// A length in seconds, where -1 or 0 stand for an unknown one.
+pub(super) fn parse_seconds(text: &str) -> Option<Duration> {
+    text.trim().parse::<f64>().ok().filter(|l| *l > 0.).map(Duration::from_secs_f64)
+}
+
+/// Playlist files are supposed to be UTF-8, but older `.m3u` files are often Latin-1.
+fn read_text(path: &Path) -> io::Result<String> {
+    let bytes = fs::read(path)?;
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&bytes);
+
+    Ok(match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|b| *b as char).collect(),
+    })
+}
+
+/// Resolves a path as written in a playlist file, relative to the playlist's folder unless it's absolute.
+pub(super) fn resolve_path(folder: &Path, path: &str) -> PathBuf {
+    let path = match path.strip_prefix("file://") {
+        Some(url) => percent_decode(url),
+        None => path.to_string(),
+    };
+
+    // Playlists written on Windows use backslashes, which elsewhere would be taken as part of the file name
+    let path = if cfg!(windows) { path } else { path.replace('\\', "/") };
+    let path = PathBuf::from(path);
+
+    if path.is_absolute() {
+        path
+    } else {
+        folder.join(path)
+    }
+}
+
+pub(super) fn percent_decode(text: &str) -> String {
+    let mut bytes = vec![];
+    let mut rest = text.as_bytes();
+
+    while let Some((&b, tail)) = rest.split_first() {
+        let hex = tail.get(..2).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok());
+
+        match hex {
+            Some(decoded) if b == b'%' => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+/// Escapes the characters of a path that can't be part of a URI, keeping the slashes.
+pub(super) fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+
+    for b in text.bytes() {
+        if b.is_ascii_alphanumeric() || b"/-_.~".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{b:02X}"));
+        }
+    }
+
+    encoded
+}
+
+/// The path to get from the folder to the file, or none if they have nothing but the root in common.
+fn relative_path(folder: &Path, file: &Path) -> Option<PathBuf> {
+    let folder: Vec<Component> = folder.components().collect();
+    let file: Vec<Component> = file.components().collect();
+
+    let common = folder.iter().zip(file.iter()).take_while(|(a, b)| a == b).count();
+    let shares_only_root = folder.iter().take(common).all(|c| matches!(c, Component::RootDir | Component::Prefix(_)));
+
+    if common == 0 || shares_only_root {
+        return None;
+    }
+
+    let mut path = PathBuf::new();
+    for _ in common..folder.len() {
+        path.push("..");
+    }
+    for component in &file[common..] {
+        path.push(component);
+    }
+
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_and_makes_paths_relative() {
+        let folder = Path::new("/music/Playlists");
+
+        assert_eq!(resolve_path(folder, "../Pink Floyd/Animals/03 Dogs.flac"), PathBuf::from("/music/Playlists/../Pink Floyd/Animals/03 Dogs.flac"));
+        assert_eq!(resolve_path(folder, "/other/song.mp3"), PathBuf::from("/other/song.mp3"));
+        assert_eq!(resolve_path(folder, "file:///other/Sigur%20R%C3%B3s.mp3"), PathBuf::from("/other/Sigur Rós.mp3"));
+        assert_eq!(resolve_path(folder, "Mix\\01 Intro.mp3"), PathBuf::from("/music/Playlists/Mix/01 Intro.mp3"));
+
+        assert_eq!(relative_path(folder, Path::new("/music/Pink Floyd/Animals/03 Dogs.flac")), Some(PathBuf::from("../Pink Floyd/Animals/03 Dogs.flac")));
+        assert_eq!(relative_path(folder, Path::new("/music/Playlists/song.mp3")), Some(PathBuf::from("song.mp3")));
+        assert_eq!(relative_path(folder, Path::new("/other/song.mp3")), None);
+
+        assert_eq!(percent_decode(&percent_encode("/music/Sigur Rós/100%.flac")), "/music/Sigur Rós/100%.flac");
+    }
+
+    #[test]
+    fn reads_back_the_cue_tracks_it_writes() {
+        let folder = std::env::temp_dir().join(format!("jolteon-playlist-file-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+
+        let album = folder.join("Album.flac");
+        fs::write(&album, b"").unwrap();
+
+        let track = |title: &str, start: u64| Song {
+            path: album.clone(),
+            artist: Some("Pink Floyd".to_string()),
+            title: title.to_string(),
+            start_time: Duration::from_secs(start),
+            length: Duration::from_secs(300),
+            ..Default::default()
+        };
+        let library = vec![track("Pigs on the Wing 1", 0), track("Dogs", 300), track("Pigs", 600)];
+        let songs = [library[1].clone(), library[0].clone(), library[1].clone(), library[2].clone()];
+
+        let path = folder.join("Mix.m3u8");
+        write_playlist_file(&path, "Mix", &songs, true).unwrap();
+
+        let resolved = PlaylistFile::from_file(&path).unwrap().resolve(|| library.clone());
+
+        assert_eq!(resolved.songs, songs);
+        assert!(resolved.unresolved.is_empty());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn finds_unreadable_entries_in_the_library_by_tags() {
+        let entry = |path: &str, artist: &str, title: &str, secs: u64| PlaylistFileEntry {
+            path: PathBuf::from(path),
+            artist: Some(artist.to_string()),
+            title: Some(title.to_string()),
+            album: None,
+            length: Some(Duration::from_secs(secs)),
+        };
+        let playlist = PlaylistFile {
+            path: PathBuf::from("/elsewhere/Mix.xspf"),
+            entries: vec![entry("/nowhere/dogs.flac", "PINK FLOYD", "Dogs", 1023), entry("/nowhere/pigs.flac", "Pink Floyd", "Pigs", 688)],
+        };
+        let library = [Song {
+            path: PathBuf::from("/music/Pink Floyd/Animals/03 Dogs.flac"),
+            artist: Some("Pink Floyd".to_string()),
+            title: "Dogs".to_string(),
+            length: Duration::from_secs(1024),
+            ..Default::default()
+        }];
+
+        let resolved = playlist.resolve(|| library.to_vec());
+
+        assert_eq!(resolved.name, "Mix");
+        assert_eq!(resolved.songs.iter().map(|s| s.path.clone()).collect::<Vec<_>>(), vec![library[0].path.clone(), PathBuf::from("/nowhere/pigs.flac")]);
+        assert_eq!(resolved.unresolved, vec![playlist.entries[1].clone()]);
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::structs::Song;
+
+use super::playlist_file::{parse_seconds, resolve_path, song_name, split_song_name, PlaylistFileEntry};
+
+/// Reads the entries of a PLS playlist: numbered `FileN`, `TitleN` and `LengthN` keys, in the order of their numbers.
+/// Entries of streams are skipped.
+pub fn parse(text: &str, folder: &Path) -> Vec<PlaylistFileEntry> {
+    let mut entries: BTreeMap<usize, PlaylistFileEntry> = BTreeMap::new();
+
+    for (key, value) in text.lines().filter_map(|l| l.split_once('=')) {
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+
+        let Some((name, number)) = key.find(|c: char| c.is_ascii_digit()).map(|i| key.split_at(i)) else {
+            continue;
+        };
+        let Ok(number) = number.parse::<usize>() else {
+            continue;
+        };
+
+        let entry = entries.entry(number).or_default();
+
+        match name {
+            "file" => entry.path = PathBuf::from(value),
+            "title" => (entry.artist, entry.title) = split_song_name(value),
+            "length" => entry.length = parse_seconds(value),
+            _ => {}
+        }
+    }
+
+    entries
+        .into_values()
+        .filter_map(|mut entry| {
+            let path = entry.path.to_string_lossy().to_string();
+
+            if path.is_empty() || (path.contains("://") && !path.starts_with("file://")) {
+                log::debug!("skipping entry {path:?} in playlist");
+                return None;
+            }
+
+            entry.path = resolve_path(folder, &path);
+            Some(entry)
+        })
+        .collect()
+}
+
+/// Writes the songs as a PLS playlist, with the given path for each one.
+pub fn write(_name: &str, songs: &[&Song], song_path: impl Fn(&Song) -> PathBuf) -> String {
+    let mut text = String::from("[playlist]\n");
+
+    for (i, song) in songs.iter().enumerate() {
+        let n = i + 1;
+        let length = match song.length.as_secs() {
+            0 => -1,
+            secs => secs as i64,
+        };
+
+        text.push_str(&format!("File{n}={}\nTitle{n}={}\nLength{n}={length}\n", song_path(song).display(), song_name(song)));
+    }
+
+    text.push_str(&format!("NumberOfEntries={}\nVersion=2\n", songs.len()));
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn reads_entries_in_order_of_their_numbers() {
+        let text = "\
+[playlist]
+File2=/music/Sigur Rós/Takk/02 Glósóli.flac
+File1=../Pink Floyd/Animals/03 Dogs.flac
+Title1=Pink Floyd - Dogs
+Length1=1024
+File3=http://radio.example.com/stream
+Title3=Some Radio
+Length3=-1
+NumberOfEntries=3
+Version=2
+";
+
+        assert_eq!(parse(text, Path::new("/music/Playlists")), vec![
+            PlaylistFileEntry {
+                path: PathBuf::from("/music/Playlists/../Pink Floyd/Animals/03 Dogs.flac"),
+                artist: Some("Pink Floyd".to_string()),
+                title: Some("Dogs".to_string()),
+                album: None,
+                length: Some(Duration::from_secs(1024)),
+            },
+            PlaylistFileEntry {
+                path: PathBuf::from("/music/Sigur Rós/Takk/02 Glósóli.flac"),
+                ..Default::default()
+            },
+        ]);
+
+        let song = Song {
+            path: PathBuf::from("/music/Song.mp3"),
+            title: "Song".to_string(),
+            length: Duration::from_secs(200),
+            ..Default::default()
+        };
+        assert_eq!(write("Mix", &[&song], |s| s.path.clone()), "[playlist]\nFile1=/music/Song.mp3\nTitle1=Song\nLength1=200\nNumberOfEntries=1\nVersion=2\n");
+    }
+}
@@ -0,0 +1,51 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use crate::structs::Song;
+
+use super::{PlaylistFile, ResolvedPlaylist};
+
+/// What to do with the songs of a playlist file, once they're found.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PlaylistFileAction {
+    Enqueue,
+    AddToLibrary,
+    /// Import the file as a playlist of its own.
+    Import,
+}
+
+/// Finds the songs of playlist files in the background, since reading the tags of hundreds of files can take a while.
+#[derive(Default)]
+pub struct PlaylistFileResolver {
+    resolved: Arc<Mutex<Vec<(PlaylistFileAction, ResolvedPlaylist)>>>,
+}
+
+impl PlaylistFileResolver {
+    /// Finds the songs of the playlist file, looking for the ones that can't be read among the songs of the library.
+    /// Songs whose files don't exist are left out, unless the file is imported.
+    pub fn resolve(&self, playlist_file: PlaylistFile, action: PlaylistFileAction, library_songs: Vec<Song>) {
+        let resolved = self.resolved.clone();
+
+        let spawned = thread::Builder::new().name("playlist_file".to_string()).spawn(move || {
+            let mut library_songs = Some(library_songs);
+            let mut playlist = playlist_file.resolve(|| library_songs.take().unwrap_or_default());
+
+            if action != PlaylistFileAction::Import {
+                playlist.songs.retain(|s| s.path.exists());
+            }
+
+            resolved.lock().unwrap().push((action, playlist));
+        });
+
+        if let Err(err) = spawned {
+            log::error!("Could not spawn the playlist_file thread: {err}");
+        }
+    }
+
+    /// The playlist files resolved since the last time it was asked.
+    pub fn take_resolved(&self) -> Vec<(PlaylistFileAction, ResolvedPlaylist)> {
+        std::mem::take(&mut *self.resolved.lock().unwrap())
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::structs::Song;
+
+use super::playlist_file::{percent_decode, percent_encode, resolve_path, PlaylistFileEntry};
+
+/// Reads the tracks of an XSPF playlist. Locations are URIs: `file://` ones, or ones relative to the playlist's folder.
+/// Tracks without a location, or with the location of a stream, are skipped.
+pub fn parse(text: &str, folder: &Path) -> Vec<PlaylistFileEntry> {
+    let mut entries = vec![];
+    let mut rest = text;
+
+    while let Some((track, after)) = element(rest, "track") {
+        rest = after;
+
+        let Some(location) = element(track, "location").map(|(l, _)| unescape(l)) else {
+            continue;
+        };
+        let location = location.trim();
+
+        if location.contains("://") && !location.starts_with("file://") {
+            log::debug!("skipping stream {location} in playlist");
+            continue;
+        }
+
+        let location = if location.starts_with("file://") { location.to_string() } else { percent_decode(location) };
+        let text_of = |name: &str| element(track, name).map(|(t, _)| unescape(t).trim().to_string()).filter(|t| !t.is_empty());
+
+        entries.push(PlaylistFileEntry {
+            path: resolve_path(folder, &location),
+            artist: text_of("creator"),
+            title: text_of("title"),
+            album: text_of("album"),
+            length: text_of("duration").and_then(|d| d.parse::<u64>().ok()).filter(|d| *d > 0).map(Duration::from_millis),
+        });
+    }
+
+    entries
+}
+
+/// The content of the first element with the given name, and what follows it.
+fn element<'t>(text: &'t str, name: &str) -> Option<(&'t str, &'t str)> {
+    let open = format!("<{name}");
+    let close = format!("</{name}>");
+    let mut rest = text;
+
+    loop {
+        let start = rest.find(&open)?;
+        let after_name = &rest[start + open.len()..];
+
+        // Elements whose name starts with the one looked for, such as <trackList> for <track>, aren't it
+        if !after_name.starts_with(['>', ' ', '\t', '\r', '\n', '/']) {
+            rest = after_name;
+            continue;
+        }
+
+        let tag_end = after_name.find('>')?;
+        if after_name[..tag_end].ends_with('/') {
+            return Some(("", &after_name[tag_end + 1..]));
+        }
+
+        let content = &after_name[tag_end + 1..];
+        let end = content.find(&close)?;
+
+        return Some((&content[..end], &content[end + close.len()..]));
+    }
+}
+
+fn unescape(text: &str) -> String {
+    if let Some(cdata) = text.trim().strip_prefix("<![CDATA[").and_then(|t| t.strip_suffix("]]>")) {
+        return cdata.to_string();
+    }
+
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+
+        let entity = &rest[1..end];
+        let char = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse::<u32>()))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        };
+
+        match char {
+            Some(char) => {
+                unescaped.push(char);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    unescaped.push_str(rest);
+    unescaped
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Writes the songs as an XSPF playlist, with the given path for each one, as a `file://` URI when it's absolute.
+pub fn write(name: &str, songs: &[&Song], song_path: impl Fn(&Song) -> PathBuf) -> String {
+    let mut text = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n");
+    text.push_str(&format!("  <title>{}</title>\n  <trackList>\n", escape(name)));
+
+    for song in songs {
+        let path = song_path(song);
+        let location = percent_encode(&path.to_string_lossy());
+        let location = if path.is_absolute() { format!("file://{location}") } else { location };
+
+        text.push_str("    <track>\n");
+        text.push_str(&format!("      <location>{}</location>\n", escape(&location)));
+        text.push_str(&format!("      <title>{}</title>\n", escape(&song.title)));
+
+        if let Some(artist) = &song.artist {
+            text.push_str(&format!("      <creator>{}</creator>\n", escape(artist)));
+        }
+        if let Some(album) = &song.album {
+            text.push_str(&format!("      <album>{}</album>\n", escape(album)));
+        }
+        if !song.length.is_zero() {
+            text.push_str(&format!("      <duration>{}</duration>\n", song.length.as_millis()));
+        }
+
+        text.push_str("    </track>\n");
+    }
+
+    text.push_str("  </trackList>\n</playlist>\n");
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_what_it_writes() {
+        let songs = [
+            Song {
+                path: PathBuf::from("/music/Simon & Garfunkel/Bookends/07 America.flac"),
+                artist: Some("Simon & Garfunkel".to_string()),
+                title: "America".to_string(),
+                album: Some("Bookends".to_string()),
+                length: Duration::from_millis(215_300),
+                ..Default::default()
+            },
+            Song {
+                path: PathBuf::from("/music/Sigur Rós/Takk/02 Glósóli.flac"),
+                title: "Glósóli".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let text = write("Mix <1>", &songs.iter().collect::<Vec<_>>(), |s| s.path.clone());
+        assert!(text.contains("<location>file:///music/Simon%20%26%20Garfunkel/Bookends/07%20America.flac</location>"));
+        assert!(text.contains("<title>Mix &lt;1&gt;</title>"));
+
+        let entries = parse(&text, Path::new("/music/Playlists"));
+        assert_eq!(entries, vec![
+            PlaylistFileEntry {
+                path: songs[0].path.clone(),
+                artist: Some("Simon & Garfunkel".to_string()),
+                title: Some("America".to_string()),
+                album: Some("Bookends".to_string()),
+                length: Some(Duration::from_millis(215_300)),
+            },
+            PlaylistFileEntry {
+                path: songs[1].path.clone(),
+                title: Some("Glósóli".to_string()),
+                ..Default::default()
+            },
+        ]);
+    }
+
+    #[test]
+    fn reads_relative_locations_and_skips_streams() {
+        let text = r#"<playlist version="1" xmlns="http://xspf.org/ns/0/"><trackList>
+            <track><location>../Pink%20Floyd/Animals/03%20Dogs.flac</location><creator>Pink Floyd</creator><title><![CDATA[Dogs]]></title></track>
+            <track><location>http://radio.example.com/stream</location></track>
+            <track><title>No location</title></track>
+        </trackList></playlist>"#;
+
+        assert_eq!(parse(text, Path::new("/music/Playlists")), vec![PlaylistFileEntry {
+            path: PathBuf::from("/music/Playlists/../Pink Floyd/Animals/03 Dogs.flac"),
+            artist: Some("Pink Floyd".to_string()),
+            title: Some("Dogs".to_string()),
+            ..Default::default()
+        }]);
+    }
+}
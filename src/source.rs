@@ -1,3 +1,4 @@
+use std::fmt::Formatter;
 use std::fs::File;
 use std::io::BufReader;
 use std::path::PathBuf;
@@ -7,6 +8,7 @@ use std::time::Duration;
 use rodio::{
     Decoder,
     Source as RodioSource,
+    decoder::DecoderError,
     source::{Amplify, Pausable, PeriodicAccess, SamplesConverter, Skippable, Speed, Stoppable, TrackPosition, SeekError},
 };
 
@@ -62,6 +64,33 @@ impl Controls<'_> {
     }
 }
 
+#[derive(Debug)]
+#[allow(dead_code)]
+pub enum SourceError {
+    IoError(std::io::Error),
+    DecoderError(DecoderError),
+}
+
+impl std::fmt::Display for SourceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#?}", self)
+    }
+}
+
+impl std::error::Error for SourceError {}
+
+impl From<std::io::Error> for SourceError {
+    fn from(value: std::io::Error) -> Self {
+        SourceError::IoError(value)
+    }
+}
+
+impl From<DecoderError> for SourceError {
+    fn from(value: DecoderError) -> Self {
+        SourceError::DecoderError(value)
+    }
+}
+
 pub struct Source<F> {
     input: PeriodicRodioSource<F>,
     on_playback_end: Option<Box<dyn FnOnce() + Send + 'static>>,
@@ -73,7 +102,7 @@ impl Source<()> {
         mut periodic_access: impl FnMut(&mut Controls) + Send,
         shared_pos: Arc<Mutex<Duration>>,
         on_playback_end: impl FnOnce() + Send + 'static,
-    ) -> Source<Box<impl FnMut(&mut FullRodioSource) + Send>>
+    ) -> Result<Source<Box<impl FnMut(&mut FullRodioSource) + Send>>, SourceError>
     {
         let periodic_access_inner = {
             Box::new(move |src: &mut FullRodioSource| {
@@ -83,8 +112,8 @@ impl Source<()> {
             })
         };
 
-        let file = BufReader::new(File::open(path.clone()).unwrap());
-        let source = Decoder::new(file).unwrap();
+        let file = BufReader::new(File::open(path)?);
+        let source = Decoder::new(file)?;
         let input = source
             .speed(1.0)
             .track_position()
@@ -95,10 +124,10 @@ impl Source<()> {
             .periodic_access(Duration::from_millis(5), periodic_access_inner)
             .convert_samples();
 
-        Source {
+        Ok(Source {
             input,
             on_playback_end: Some(Box::new(on_playback_end)),
-        }
+        })
     }
 }
 
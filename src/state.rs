@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    structs::{Song, Playlist},
+    structs::{Song, Playlist, SongSort, SongSortKey},
     toml::{read_toml_file_or_default, write_toml_file, TomlFileError},
 };
 
@@ -10,8 +10,34 @@ pub struct State {
     pub last_visited_path: Option<String>,
     #[serde(default)]
     pub queue_items: Vec<Song>,
-    #[serde(default)]
+    /// Where the playlists were kept before they got a file each in the playlists folder. Read to move them there,
+    /// and written only if that fails.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub playlists: Vec<Playlist>,
+    /// The names of the playlists, in the order they're listed in.
+    #[serde(default)]
+    pub playlist_order: Vec<String>,
+    #[serde(default)]
+    pub sorting: Sorting,
+}
+
+/// How the songs of each view are sorted.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct Sorting {
+    pub library: SongSort,
+    pub playlists: SongSort,
+    pub queue: SongSort,
+}
+
+impl Default for Sorting {
+    fn default() -> Self {
+        Self {
+            library: SongSort::by(SongSortKey::Album),
+            playlists: SongSort::default(),
+            queue: SongSort::default(),
+        }
+    }
 }
 
 impl Default for State {
@@ -20,6 +46,8 @@ impl Default for State {
             last_visited_path: None,
             queue_items: vec![],
             playlists: vec![],
+            playlist_order: vec![],
+            sorting: Sorting::default(),
         }
     }
 }
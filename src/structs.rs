@@ -1,7 +1,34 @@
 mod queue;
 mod song;
 mod playlist;
+mod shuffle;
+mod virtual_folder;
+mod metadata_override;
+mod path_guess;
+mod tag_issue;
+mod file_stamp;
+mod song_stats;
+mod smart_playlist;
+mod song_id;
+mod duplicates;
+mod lyrics;
+mod song_sort;
+mod artist_name;
+mod removed_song;
 
 pub use queue::Queue;
 pub use song::Song;
 pub use playlist::Playlist;
+pub use virtual_folder::{VirtualFolder, VirtualFolderAlbum};
+pub use metadata_override::{JoltFiles, MetadataOverride, MetadataOverrides, JOLT_FILE_NAME};
+pub use path_guess::guess_from_path;
+pub use tag_issue::tag_issues;
+pub use file_stamp::FileStamp;
+pub use song_stats::{play_threshold, time_ago, unix_now, SongStats};
+pub use smart_playlist::{SmartCandidate, SmartPlaylist};
+pub use song_id::SongId;
+pub use duplicates::{find_duplicates, normalize_tag};
+pub use lyrics::Lyrics;
+pub use song_sort::{SongSort, SongSortKey};
+pub use artist_name::{compare_artists, ArtistNames};
+pub use removed_song::RemovedSong;
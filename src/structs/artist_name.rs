@@ -0,0 +1,176 @@
+use std::collections::{HashMap, HashSet};
+
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::structs::Song;
+
+/// Tells spellings of the same artist apart from different artists: lowercased, without accents or punctuation, and
+/// without a leading or trailing "The", so "The Beatles", "Beatles, The" and "BEATLES" are the same artist, and so are
+/// "Motörhead" and "Motorhead". Names made only of punctuation or symbols, like "!!!", are only lowercased, so they
+/// don't all fold into the same empty key.
+pub fn artist_key(name: &str) -> String {
+    let mut key = String::with_capacity(name.len());
+
+    for c in name.nfd().filter(|c| !is_combining_mark(*c)) {
+        if c.is_alphanumeric() {
+            key.extend(c.to_lowercase());
+        } else if !key.is_empty() && !key.ends_with(' ') {
+            key.push(' ');
+        }
+    }
+
+    let key = key.trim_end();
+    let key = key.strip_prefix("the ").or_else(|| key.strip_suffix(" the")).unwrap_or(key);
+
+    if key.is_empty() {
+        return name.trim().to_lowercase();
+    }
+
+    key.to_string()
+}
+
+/// Sorts artists as people would: ignoring case, accents and a leading "The", so "The Beatles" sorts under B and
+/// "Édith Piaf" among the other E's. Artists with the same key are sorted by name, so the order is stable.
+pub fn compare_artists(a: &str, b: &str) -> std::cmp::Ordering {
+    artist_key(a).cmp(&artist_key(b)).then_with(|| a.cmp(b))
+}
+
+/// Out of the spellings of an artist, and how many songs have each, the one most songs have. Ties go to the current
+/// name, so the name shown doesn't flip back and forth as songs come and go, and then to the one sorting first.
+fn majority_name(counts: &HashMap<String, usize>, current: Option<&str>) -> Option<String> {
+    counts
+        .iter()
+        .max_by(|(name_a, count_a), (name_b, count_b)| {
+            count_a
+                .cmp(count_b)
+                .then_with(|| (Some(name_a.as_str()) == current).cmp(&(Some(name_b.as_str()) == current)))
+                .then_with(|| name_b.cmp(name_a))
+        })
+        .map(|(name, _)| name.clone())
+}
+
+/// The name each artist is shown with, by its [`artist_key`]: the spelling most of its songs are tagged with, unless
+/// an override set another one. Spellings are counted from the tags, as songs come and go, so the names shown never
+/// vote for themselves, and the tags are left as they are.
+#[derive(Debug, Default)]
+pub struct ArtistNames {
+    spellings: HashMap<String, HashMap<String, usize>>,
+    names: HashMap<String, String>,
+    /// Artists named by an override, rather than by their songs.
+    pinned: HashSet<String>,
+}
+
+impl ArtistNames {
+    /// The name an artist is shown with, or the given one if it isn't known yet.
+    pub fn name(&self, name: &str) -> String {
+        self.names.get(&artist_key(name)).cloned().unwrap_or(name.to_string())
+    }
+
+    /// Counts the spellings of the artists and album artists of the songs taken out of and put into the library,
+    /// as tagged. Returns the artists whose name changed, from and to.
+    pub fn update(&mut self, removed: &[Song], added: &[Song]) -> Vec<(String, String)> {
+        let mut keys = HashSet::new();
+
+        for (songs, added) in [(removed, false), (added, true)] {
+            for name in songs.iter().flat_map(|song| [&song.artist, &song.album_artist]).flatten() {
+                let key = artist_key(name);
+                let spellings = self.spellings.entry(key.clone()).or_default();
+
+                if added {
+                    *spellings.entry(name.clone()).or_default() += 1;
+                } else if let Some(count) = spellings.get_mut(name) {
+                    *count -= 1;
+                    if *count == 0 {
+                        spellings.remove(name);
+                    }
+                }
+
+                keys.insert(key);
+            }
+        }
+
+        keys.into_iter().filter_map(|key| self.vote(key)).collect()
+    }
+
+    /// Shows an artist with the name an override gave it, whatever its songs say. Returns the name it replaced.
+    pub fn pin(&mut self, name: &str) -> Option<String> {
+        let key = artist_key(name);
+        self.pinned.insert(key.clone());
+        self.names.insert(key, name.to_string()).filter(|current| current != name)
+    }
+
+    /// Goes back to naming artists after their songs, such as before the overrides are applied again. Returns the
+    /// artists whose name changed, from and to.
+    pub fn unpin_all(&mut self) -> Vec<(String, String)> {
+        std::mem::take(&mut self.pinned).into_iter().filter_map(|key| self.vote(key)).collect()
+    }
+
+    /// Names the artist after the spelling most of its songs have, unless an override named it.
+    fn vote(&mut self, key: String) -> Option<(String, String)> {
+        if self.pinned.contains(&key) {
+            return None;
+        }
+
+        let current = self.names.get(&key).cloned();
+
+        let Some(name) = self.spellings.get(&key).and_then(|s| majority_name(s, current.as_deref())) else {
+            self.spellings.remove(&key);
+            return None;
+        };
+
+        self.names.insert(key, name.clone());
+        current.filter(|current| *current != name).map(|current| (current, name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folds_case_accents_and_articles() {
+        assert_eq!(artist_key("Pink Floyd"), artist_key("PINK FLOYD"));
+        assert_eq!(artist_key("The Beatles"), "beatles");
+        assert_eq!(artist_key("Beatles, The"), "beatles");
+        assert_eq!(artist_key("Motörhead"), "motorhead");
+        assert_eq!(artist_key("Sigur Rós"), "sigur ros");
+        assert_eq!(artist_key("The The"), "the");
+        assert_eq!(artist_key("Theatre of Tragedy"), "theatre of tragedy");
+        assert_eq!(artist_key("!!!"), "!!!");
+        assert_ne!(artist_key("!!!"), artist_key("†††"));
+
+        let mut artists = vec!["The Rolling Stones", "Édith Piaf", "ABBA", "the Beatles", "Eagles"];
+        artists.sort_by(|a, b| compare_artists(a, b));
+        assert_eq!(artists, vec!["ABBA", "the Beatles", "Eagles", "Édith Piaf", "The Rolling Stones"]);
+    }
+
+    #[test]
+    fn picks_the_most_common_spelling() {
+        let counts = HashMap::from([("Pink Floyd".to_string(), 10), ("PINK FLOYD".to_string(), 2)]);
+        assert_eq!(majority_name(&counts, Some("PINK FLOYD")).as_deref(), Some("Pink Floyd"));
+
+        let counts = HashMap::from([("Beatles, The".to_string(), 3), ("The Beatles".to_string(), 3)]);
+        assert_eq!(majority_name(&counts, Some("The Beatles")).as_deref(), Some("The Beatles"));
+        assert_eq!(majority_name(&counts, None).as_deref(), Some("Beatles, The"));
+    }
+
+    #[test]
+    fn names_artists_after_their_tags() {
+        let song = |artist: &str| Song { artist: Some(artist.to_string()), ..Default::default() };
+        let mut names = ArtistNames::default();
+
+        assert_eq!(names.update(&[], &[song("PINK FLOYD")]), vec![]);
+        assert_eq!(names.name("pink floyd"), "PINK FLOYD");
+
+        // Ties go to the current name, and the names shown don't count
+        assert_eq!(names.update(&[], &[song("Pink Floyd")]), vec![]);
+        assert_eq!(names.update(&[], &[song("Pink Floyd")]), vec![("PINK FLOYD".to_string(), "Pink Floyd".to_string())]);
+        assert_eq!(names.update(&[song("Pink Floyd"), song("Pink Floyd")], &[]), vec![("Pink Floyd".to_string(), "PINK FLOYD".to_string())]);
+
+        assert_eq!(names.pin("Pink Floyd"), Some("PINK FLOYD".to_string()));
+        assert_eq!(names.update(&[], &[song("PINK FLOYD")]), vec![]);
+        assert_eq!(names.name("PINK FLOYD"), "Pink Floyd");
+
+        assert_eq!(names.unpin_all(), vec![("Pink Floyd".to_string(), "PINK FLOYD".to_string())]);
+    }
+}
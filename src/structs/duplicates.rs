@@ -0,0 +1,123 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::structs::Song;
+
+/// Copies of the same song are expected to be this close in length. Different encodings of the same recording
+/// differ by a few frames of padding; live versions and remixes usually differ by more.
+const MAX_LENGTH_DIFFERENCE: Duration = Duration::from_secs(2);
+
+/// Lowercases the text and drops everything but letters and digits, along with anything in parentheses or brackets,
+/// so "Dogs (2011 Remaster)" and "dogs" are told to be the same title.
+pub fn normalize_tag(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut depth = 0usize;
+
+    for c in text.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            _ if depth > 0 => {}
+            c if c.is_alphanumeric() => normalized.extend(c.to_lowercase()),
+            _ if !normalized.is_empty() && !normalized.ends_with(' ') => normalized.push(' '),
+            _ => {}
+        }
+    }
+
+    normalized.trim_end().to_string()
+}
+
+/// Groups the songs that look like copies of each other: the same artist and title, once normalized, and about
+/// the same length. Each group has songs of at least two different files, since cue sheet tracks sharing a title
+/// aren't copies of each other. Groups are sorted by artist and title, and the songs in them by path.
+pub fn find_duplicates(songs: &[Song]) -> Vec<Vec<Song>> {
+    let mut candidates: HashMap<(String, String), Vec<&Song>> = HashMap::new();
+
+    for song in songs {
+        let artist = song.artist.as_deref().or(song.album_artist.as_deref()).map(normalize_tag).unwrap_or_default();
+        let title = normalize_tag(&song.title);
+
+        if title.is_empty() {
+            continue;
+        }
+
+        candidates.entry((artist, title)).or_default().push(song);
+    }
+
+    let mut groups: Vec<((String, String), Vec<Song>)> = vec![];
+
+    for (key, mut candidates) in candidates.into_iter().filter(|(_, c)| c.len() > 1) {
+        candidates.sort_by_key(|s| s.length);
+
+        let mut group: Vec<&Song> = vec![];
+
+        for song in candidates {
+            if group.last().is_some_and(|last| song.length - last.length > MAX_LENGTH_DIFFERENCE) {
+                push_group(&mut groups, &key, std::mem::take(&mut group));
+            }
+
+            group.push(song);
+        }
+
+        push_group(&mut groups, &key, group);
+    }
+
+    groups.sort_by(|(key_a, a), (key_b, b)| key_a.cmp(key_b).then(a[0].length.cmp(&b[0].length)));
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
+fn push_group(groups: &mut Vec<((String, String), Vec<Song>)>, key: &(String, String), group: Vec<&Song>) {
+    if !group.iter().any(|s| s.path != group[0].path) {
+        return;
+    }
+
+    let mut group: Vec<Song> = group.into_iter().cloned().collect();
+    group.sort_by(|a, b| a.path.cmp(&b.path).then(a.start_time.cmp(&b.start_time)));
+    groups.push((key.clone(), group));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn song(path: &str, artist: &str, title: &str, secs: u64) -> Song {
+        Song {
+            path: PathBuf::from(path),
+            title: title.to_string(),
+            artist: Some(artist.to_string()),
+            length: Duration::from_secs(secs),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn normalizes_case_punctuation_and_version_notes() {
+        assert_eq!(normalize_tag("Dogs (2011 Remaster)"), "dogs");
+        assert_eq!(normalize_tag("  Don't   Stop  Me Now! "), "don t stop me now");
+        assert_eq!(normalize_tag("Sheep [Live]"), "sheep");
+    }
+
+    #[test]
+    fn groups_copies_of_the_same_song() {
+        let songs = vec![
+            song("/music/Animals/03 Dogs.flac", "Pink Floyd", "Dogs", 1024),
+            song("/music/Animals (copy)/03 Dogs.mp3", "PINK FLOYD", "Dogs (2011 Remaster)", 1025),
+            song("/music/Live/Dogs.flac", "Pink Floyd", "Dogs", 1100),
+            song("/music/Animals/04 Pigs.flac", "Pink Floyd", "Pigs (Three Different Ones)", 688),
+            song("/music/Animals.flac", "Pink Floyd", "Untitled", 100),
+        ];
+
+        let mut untitled = song("/music/Animals.flac", "Pink Floyd", "Untitled", 101);
+        untitled.start_time = Duration::from_secs(100);
+
+        let songs: Vec<Song> = songs.into_iter().chain([untitled]).collect();
+        let groups = find_duplicates(&songs);
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(
+            groups[0].iter().map(|s| s.path.to_str().unwrap()).collect::<Vec<_>>(),
+            vec!["/music/Animals/03 Dogs.flac", "/music/Animals (copy)/03 Dogs.mp3"],
+        );
+    }
+}
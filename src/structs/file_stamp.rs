@@ -0,0 +1,24 @@
+use std::{fs, path::Path, time::UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// The modification time and size of a file, as of the last time it was read into the library.
+/// Files whose stamp hasn't changed aren't read again when rescanning the library folders.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub struct FileStamp {
+    /// Seconds since the unix epoch.
+    pub modified: u64,
+    pub size: u64,
+}
+
+impl FileStamp {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs();
+
+        Some(Self {
+            modified,
+            size: metadata.len(),
+        })
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    fs,
+    path::Path,
+    time::Duration,
+};
+
+use lofty::{
+    id3::v2::{SynchronizedText, TimestampFormat},
+    ItemKey,
+    Probe,
+    TaggedFileExt,
+};
+
+/// The lyrics of a song: synced, with the time each line is sung at, or plain text.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Lyrics {
+    /// Sorted by time.
+    Synced(Vec<(Duration, String)>),
+    Unsynced(Vec<String>),
+}
+
+impl Lyrics {
+    /// Parses lyrics in the LRC format, such as `[01:23.45]Some line`. Text without timestamps is taken as unsynced lyrics.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut synced = vec![];
+        let mut unsynced = vec![];
+        let mut offset_ms = 0i64;
+
+        for line in text.lines().map(str::trim) {
+            let mut rest = line;
+            let mut times = vec![];
+
+            while let Some((tag, after)) = rest.strip_prefix('[').and_then(|r| r.split_once(']')) {
+                if let Some(time) = parse_timestamp(tag) {
+                    times.push(time);
+                } else if let Some(offset) = tag.strip_prefix("offset:") {
+                    offset_ms = offset.trim().parse().unwrap_or(0);
+                } else if !tag.contains(':') {
+                    // Not a tag, but text that happens to start with brackets
+                    break;
+                }
+
+                rest = after;
+            }
+
+            let text = strip_word_timestamps(rest);
+
+            if !times.is_empty() {
+                synced.extend(times.into_iter().map(|time| (time, text.clone())));
+            } else if rest.len() == line.len() || !text.is_empty() {
+                unsynced.push(text);
+            }
+        }
+
+        if !synced.is_empty() {
+            // A positive offset shows lines sooner
+            for (time, _) in &mut synced {
+                *time = if offset_ms >= 0 {
+                    time.saturating_sub(Duration::from_millis(offset_ms as u64))
+                } else {
+                    *time + Duration::from_millis(offset_ms.unsigned_abs())
+                };
+            }
+
+            synced.sort_by_key(|(time, _)| *time);
+            return Some(Lyrics::Synced(synced));
+        }
+
+        while unsynced.last().is_some_and(|l| l.is_empty()) {
+            unsynced.pop();
+        }
+
+        while unsynced.first().is_some_and(|l| l.is_empty()) {
+            unsynced.remove(0);
+        }
+
+        if unsynced.is_empty() {
+            None
+        } else {
+            Some(Lyrics::Unsynced(unsynced))
+        }
+    }
+
+    /// The lyrics of the audio file: from a `.lrc` file next to it, with the same name, or from its tags.
+    /// For a file split by a cue sheet, they're the lyrics of the whole file, timed from its start.
+    pub fn from_audio_file(path: &Path) -> Option<Self> {
+        if let Some(lyrics) = lrc_file(path).and_then(|text| Self::parse(&text)) {
+            return Some(lyrics);
+        }
+
+        let tagged_file = Probe::open(path).ok()?.read().ok()?;
+        let tags = tagged_file.tags();
+
+        // ID3v2 synced lyrics (SYLT frames) are kept as they are in the file, rather than converted
+        let sylt = ItemKey::Unknown("SYLT".to_string());
+        let synced = tags
+            .iter()
+            .filter_map(|tag| tag.get_binary(&sylt, false))
+            .filter_map(|data| SynchronizedText::parse(data).ok())
+            .find(|text| text.timestamp_format == TimestampFormat::MS && !text.content.is_empty());
+
+        if let Some(synced) = synced {
+            let lines = synced
+                .content
+                .into_iter()
+                .map(|(ms, text)| (Duration::from_millis(ms as u64), text.trim().to_string()))
+                .collect();
+            return Some(Lyrics::Synced(lines));
+        }
+
+        // Lyrics tags (USLT frames, LYRICS comments) are often LRC too
+        tags.iter().find_map(|tag| tag.get_string(&ItemKey::Lyrics)).and_then(Self::parse)
+    }
+
+    /// The index of the line being sung at the given position, if the lyrics are synced and it's started.
+    pub fn current_line(&self, position: Duration) -> Option<usize> {
+        match self {
+            Lyrics::Synced(lines) => lines.partition_point(|(time, _)| *time <= position).checked_sub(1),
+            Lyrics::Unsynced(_) => None,
+        }
+    }
+
+    pub fn lines(&self) -> Vec<&str> {
+        match self {
+            Lyrics::Synced(lines) => lines.iter().map(|(_, text)| text.as_str()).collect(),
+            Lyrics::Unsynced(lines) => lines.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+fn lrc_file(path: &Path) -> Option<String> {
+    ["lrc", "LRC"].iter().find_map(|extension| fs::read_to_string(path.with_extension(extension)).ok())
+}
+
+/// Parses `mm:ss`, `mm:ss.xx`, `mm:ss.xxx` and `mm:ss:xx`.
+fn parse_timestamp(tag: &str) -> Option<Duration> {
+    let (minutes, rest) = tag.split_once(':')?;
+    let minutes: u64 = minutes.trim().parse().ok()?;
+
+    let (seconds, fraction) = match rest.split_once(['.', ':']) {
+        Some((seconds, fraction)) => (seconds, fraction),
+        None => (rest, "0"),
+    };
+
+    let seconds: u64 = seconds.parse().ok()?;
+
+    if fraction.is_empty() || fraction.len() > 3 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let millis = fraction.parse::<u64>().ok()? * 10u64.pow(3 - fraction.len() as u32);
+
+    Some(Duration::from_millis((minutes * 60 + seconds) * 1000 + millis))
+}
+
+/// Enhanced LRC times each word too, as in `<00:12.34>word`. Only lines are highlighted here.
+fn strip_word_timestamps(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('<') {
+        match rest[start..].find('>') {
+            Some(end) if parse_timestamp(&rest[start + 1..start + end]).is_some() => {
+                stripped.push_str(&rest[..start]);
+                rest = &rest[start + end + 1..];
+            }
+            _ => {
+                stripped.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+
+    stripped.push_str(rest);
+    stripped.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(s: f64) -> Duration {
+        Duration::from_secs_f64(s)
+    }
+
+    #[test]
+    fn parses_lrc() {
+        let lrc = "[ar:Pink Floyd]\n[ti:Time]\n[offset:+500]\n\n[00:10.00]Ticking away\n[00:12.50][01:12.5]the moments\n[00:15.123]<00:15.12>that <00:16.00>make up";
+
+        let lyrics = Lyrics::parse(lrc).unwrap();
+
+        assert_eq!(lyrics, Lyrics::Synced(vec![
+            (secs(9.5), "Ticking away".to_string()),
+            (secs(12.0), "the moments".to_string()),
+            (secs(14.623), "that make up".to_string()),
+            (secs(72.0), "the moments".to_string()),
+        ]));
+
+        assert_eq!(lyrics.current_line(secs(5.)), None);
+        assert_eq!(lyrics.current_line(secs(9.5)), Some(0));
+        assert_eq!(lyrics.current_line(secs(13.)), Some(1));
+        assert_eq!(lyrics.current_line(secs(300.)), Some(3));
+    }
+
+    #[test]
+    fn text_without_timestamps_is_unsynced() {
+        let lyrics = Lyrics::parse("\n[Verse 1]\nTicking away\n\nthe moments\n\n").unwrap();
+
+        assert_eq!(lyrics.lines(), vec!["[Verse 1]", "Ticking away", "", "the moments"]);
+        assert_eq!(lyrics.current_line(secs(10.)), None);
+        assert_eq!(Lyrics::parse("\n[ar:Nobody]\n"), None);
+    }
+}
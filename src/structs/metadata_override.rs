@@ -0,0 +1,364 @@
+use std::{
+    collections::HashMap,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::structs::Song;
+
+/// Name of the sidecar file, stored in the same folder as the music files it overrides.
+pub const JOLT_FILE_NAME: &str = ".jolt";
+
+/// Metadata values that replace the ones read from the tags or cue sheet of a song. Empty values are left untouched.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct MetadataOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album_artist: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub year: Option<u32>,
+}
+
+impl MetadataOverride {
+    pub fn apply(&self, song: &mut Song) {
+        if let Some(artist) = &self.artist {
+            song.artist = Some(artist.clone());
+        }
+        if let Some(album_artist) = &self.album_artist {
+            song.album_artist = Some(album_artist.clone());
+        }
+        if let Some(album) = &self.album {
+            song.album = Some(album.clone());
+        }
+        if let Some(title) = &self.title {
+            song.title = title.clone();
+        }
+        if self.track.is_some() {
+            song.track = self.track;
+        }
+        if self.year.is_some() {
+            song.year = self.year;
+        }
+    }
+
+    /// Copies over the values set in `other`, keeping the rest.
+    pub fn merge(&mut self, other: MetadataOverride) {
+        self.artist = other.artist.or(self.artist.take());
+        self.album_artist = other.album_artist.or(self.album_artist.take());
+        self.album = other.album.or(self.album.take());
+        self.title = other.title.or(self.title.take());
+        self.track = other.track.or(self.track);
+        self.year = other.year.or(self.year);
+    }
+}
+
+/// A `.jolt` sidecar file:
+///
+/// ```toml
+/// # Applies to every song in the folder — usually, an album.
+/// [folder]
+/// artist = "Johann Sebastian Bach"
+///
+/// # Applies to a single file, by name.
+/// [files."01 - Prelude.flac"]
+/// title = "Prelude in C major"
+/// track = 1
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct JoltFile {
+    #[serde(default)]
+    pub folder: MetadataOverride,
+    #[serde(default)]
+    pub files: HashMap<String, MetadataOverride>,
+}
+
+impl JoltFile {
+    /// Reads the `.jolt` file in the given folder, if there's one.
+    pub fn from_dir(path: &Path) -> Option<Self> {
+        let jolt_path = path.join(JOLT_FILE_NAME);
+
+        if !jolt_path.exists() {
+            return None;
+        }
+
+        let jolt_file = read_to_string(&jolt_path)
+            .map_err(|err| err.to_string())
+            .and_then(|string| toml::from_str(&string).map_err(|err| err.to_string()));
+
+        match jolt_file {
+            Ok(jolt_file) => Some(jolt_file),
+            Err(err) => {
+                log::warn!("Could not read {:?}: {err}", jolt_path);
+                None
+            }
+        }
+    }
+
+    pub fn apply(&self, song: &mut Song) {
+        self.folder.apply(song);
+
+        let file_name = song.path.file_name().and_then(|f| f.to_str());
+
+        if let Some(file_override) = file_name.and_then(|f| self.files.get(f)) {
+            file_override.apply(song);
+        }
+    }
+}
+
+/// The `.jolt` files of the folders songs are in, each read the first time a song of its folder needs it, and kept
+/// until it changes.
+#[derive(Debug, Default)]
+pub struct JoltFiles {
+    folders: HashMap<PathBuf, Option<JoltFile>>,
+}
+
+impl JoltFiles {
+    /// Applies the overrides in the `.jolt` file next to the song, if there's one.
+    pub fn apply(&mut self, song: &mut Song) {
+        let Some(dir) = song.path.parent() else {
+            return;
+        };
+
+        if !self.folders.contains_key(dir) {
+            self.folders.insert(dir.to_path_buf(), JoltFile::from_dir(dir));
+        }
+
+        if let Some(jolt_file) = &self.folders[dir] {
+            jolt_file.apply(song);
+        }
+    }
+
+    /// Forgets the `.jolt` file of the folder, after it changed, so it's read again.
+    pub fn forget(&mut self, dir: &Path) {
+        self.folders.remove(dir);
+    }
+}
+
+/// Overrides a single song. Songs of a cue sheet share their path, so they're told apart by their start time.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct SongOverride {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub start_time: Duration,
+    #[serde(flatten)]
+    pub metadata: MetadataOverride,
+}
+
+/// Overrides every song of an album, identified by its library artist (usually, the album artist) and name.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct AlbumOverride {
+    pub artist: String,
+    pub album: String,
+    #[serde(flatten)]
+    pub metadata: MetadataOverride,
+}
+
+/// Replaces an artist name everywhere in the library. Useful to merge different spellings of the same artist,
+/// or to file interpreters under the composer.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ArtistAlias {
+    pub from: String,
+    pub to: String,
+}
+
+/// Metadata overrides edited from within the app, stored along with the library.
+///
+/// Overrides are applied in order: artist aliases first, then album overrides, then song overrides.
+/// Within each kind, rules are applied in the order they were created, so chained edits
+/// (renaming an album, and then renaming it again) keep working for songs added later.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct MetadataOverrides {
+    #[serde(default)]
+    pub artist_aliases: Vec<ArtistAlias>,
+    #[serde(default)]
+    pub albums: Vec<AlbumOverride>,
+    #[serde(default)]
+    pub songs: Vec<SongOverride>,
+}
+
+impl MetadataOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.artist_aliases.is_empty() && self.albums.is_empty() && self.songs.is_empty()
+    }
+
+    pub fn apply(&self, song: &mut Song) {
+        for alias in &self.artist_aliases {
+            if song.artist.as_ref() == Some(&alias.from) {
+                song.artist = Some(alias.to.clone());
+            }
+            if song.album_artist.as_ref() == Some(&alias.from) {
+                song.album_artist = Some(alias.to.clone());
+            }
+        }
+
+        for album_override in &self.albums {
+            if song.library_artist().as_ref() == Some(&album_override.artist) && song.album.as_ref() == Some(&album_override.album) {
+                album_override.metadata.apply(song);
+            }
+        }
+
+        for song_override in &self.songs {
+            if song.path == song_override.path && song.start_time == song_override.start_time {
+                song_override.metadata.apply(song);
+            }
+        }
+    }
+
+    pub fn add_artist_alias(&mut self, from: String, to: String) {
+        if from == to {
+            return;
+        }
+
+        self.artist_aliases.push(ArtistAlias { from, to });
+    }
+
+    pub fn add_album_override(&mut self, artist: String, album: String, metadata: MetadataOverride) {
+        match self.albums.iter_mut().find(|a| a.artist == artist && a.album == album) {
+            Some(album_override) => album_override.metadata.merge(metadata),
+            None => self.albums.push(AlbumOverride { artist, album, metadata }),
+        }
+    }
+
+    pub fn add_song_override(&mut self, song: &Song, metadata: MetadataOverride) {
+        match self.songs.iter_mut().find(|s| s.path == song.path && s.start_time == song.start_time) {
+            Some(song_override) => song_override.metadata.merge(metadata),
+            None => self.songs.push(SongOverride {
+                path: song.path.clone(),
+                start_time: song.start_time,
+                metadata,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song(artist: &str, album: &str, title: &str) -> Song {
+        Song {
+            path: PathBuf::from(format!("/music/{artist}/{album}/{title}.flac")),
+            start_time: Duration::ZERO,
+            length: Duration::from_secs(180),
+            title: title.to_string(),
+            artist: Some(artist.to_string()),
+            album: Some(album.to_string()),
+            track: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn jolt_file_applies_folder_then_file_overrides() {
+        let jolt_file: JoltFile = toml::from_str(r#"
+            [folder]
+            artist = "Johann Sebastian Bach"
+            year = 1722
+
+            [files."Prelude.flac"]
+            title = "Prelude in C major"
+            track = 1
+        "#).unwrap();
+
+        let mut prelude = song("Glenn Gould", "The Well-Tempered Clavier", "Prelude");
+        let mut fugue = song("Glenn Gould", "The Well-Tempered Clavier", "Fugue");
+
+        jolt_file.apply(&mut prelude);
+        jolt_file.apply(&mut fugue);
+
+        assert_eq!(prelude.artist.as_deref(), Some("Johann Sebastian Bach"));
+        assert_eq!(prelude.title, "Prelude in C major");
+        assert_eq!(prelude.track, Some(1));
+        assert_eq!(prelude.year, Some(1722));
+
+        assert_eq!(fugue.artist.as_deref(), Some("Johann Sebastian Bach"));
+        assert_eq!(fugue.title, "Fugue");
+        assert_eq!(fugue.track, None);
+    }
+
+    #[test]
+    fn jolt_files_are_read_once_until_forgotten() {
+        let dir = std::env::temp_dir().join(format!("jolteon-jolt-files-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(JOLT_FILE_NAME), "[folder]\nartist = \"Bach\"\n").unwrap();
+
+        let tagged = Song { path: dir.join("Prelude.flac"), ..song("Glenn Gould", "WTC", "Prelude") };
+        let mut jolt_files = JoltFiles::default();
+
+        let mut prelude = tagged.clone();
+        jolt_files.apply(&mut prelude);
+        assert_eq!(prelude.artist.as_deref(), Some("Bach"));
+
+        std::fs::remove_file(dir.join(JOLT_FILE_NAME)).unwrap();
+
+        let mut prelude = tagged.clone();
+        jolt_files.apply(&mut prelude);
+        assert_eq!(prelude.artist.as_deref(), Some("Bach"));
+
+        jolt_files.forget(&dir);
+
+        let mut prelude = tagged.clone();
+        jolt_files.apply(&mut prelude);
+        assert_eq!(prelude.artist.as_deref(), Some("Glenn Gould"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn overrides_apply_aliases_then_albums_then_songs_in_order() {
+        let mut overrides = MetadataOverrides::default();
+
+        overrides.add_artist_alias("PINK FLOYD".to_string(), "Pink Floyd".to_string());
+        overrides.add_album_override("Pink Floyd".to_string(), "Piper".to_string(), MetadataOverride {
+            album: Some("The Piper at the Gates of Dawn".to_string()),
+            ..Default::default()
+        });
+        overrides.add_album_override("Pink Floyd".to_string(), "The Piper at the Gates of Dawn".to_string(), MetadataOverride {
+            year: Some(1967),
+            ..Default::default()
+        });
+
+        let mut astronomy = song("PINK FLOYD", "Piper", "Astronomy Domine");
+        overrides.add_song_override(&astronomy, MetadataOverride {
+            track: Some(1),
+            ..Default::default()
+        });
+
+        overrides.apply(&mut astronomy);
+
+        assert_eq!(astronomy.artist.as_deref(), Some("Pink Floyd"));
+        assert_eq!(astronomy.album.as_deref(), Some("The Piper at the Gates of Dawn"));
+        assert_eq!(astronomy.year, Some(1967));
+        assert_eq!(astronomy.track, Some(1));
+
+        // Applying overrides again to an overridden song changes nothing.
+        let overridden = astronomy.clone();
+        overrides.apply(&mut astronomy);
+        assert_eq!(astronomy, overridden);
+    }
+
+    #[test]
+    fn overrides_round_trip_through_toml() {
+        let mut overrides = MetadataOverrides::default();
+        overrides.add_artist_alias("PINK FLOYD".to_string(), "Pink Floyd".to_string());
+        overrides.add_song_override(&song("Pink Floyd", "Animals", "Dogs"), MetadataOverride {
+            title: Some("Dogs (Remastered)".to_string()),
+            ..Default::default()
+        });
+
+        let string = toml::to_string(&overrides).unwrap();
+        let parsed: MetadataOverrides = toml::from_str(&string).unwrap();
+
+        assert_eq!(parsed, overrides);
+    }
+}
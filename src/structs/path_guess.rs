@@ -0,0 +1,120 @@
+use std::path::Path;
+
+/// Folder names that say nothing about the artist of the music in them.
+const GENERIC_FOLDER_NAMES: [&str; 6] = ["music", "musica", "música", "downloads", "audio", "mp3"];
+
+/// Artist and album guessed from the path of a song, for songs with missing tags.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PathGuess {
+    pub artist: Option<String>,
+    pub album: Option<String>,
+}
+
+/// Removes a leading year from a folder name, such as the "1973 - " in "1973 - The Dark Side of the Moon",
+/// or the "(1973) " in "(1973) The Dark Side of the Moon".
+fn strip_year(name: &str) -> &str {
+    let trimmed = name.trim_start_matches(['(', '[']);
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+
+    if digits != 4 {
+        return name;
+    }
+
+    let rest = trimmed[digits..].trim_start_matches([')', ']']).trim_start();
+    let rest = rest.strip_prefix('-').map(str::trim_start).unwrap_or(rest);
+
+    if rest.is_empty() {
+        name
+    } else {
+        rest
+    }
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim();
+
+    if s.is_empty() {
+        None
+    } else {
+        Some(s.to_string())
+    }
+}
+
+/// Guesses the artist and album of a song from the usual ways music is organized in folders:
+/// `Artist/Album/01 - Title.flac`, `Artist - Album/01 - Title.flac` or `Artist - Title.mp3`.
+pub fn guess_from_path(path: &Path) -> PathGuess {
+    let folder_name = |p: Option<&Path>| -> Option<String> {
+        p.and_then(|p| p.file_name())
+            .and_then(|name| name.to_str())
+            .filter(|name| !GENERIC_FOLDER_NAMES.contains(&name.to_lowercase().as_str()))
+            .and_then(non_empty)
+    };
+
+    let parent = path.parent();
+    let album_folder = folder_name(parent);
+    let artist_folder = folder_name(parent.and_then(Path::parent));
+
+    let mut guess = PathGuess::default();
+
+    if let Some(album_folder) = album_folder {
+        match album_folder.split_once(" - ") {
+            Some((artist, album)) if !artist.trim().chars().all(|c| c.is_ascii_digit()) => {
+                guess.artist = non_empty(artist);
+                guess.album = non_empty(strip_year(album));
+            }
+            _ => {
+                guess.artist = artist_folder;
+                guess.album = non_empty(strip_year(&album_folder));
+            }
+        }
+    }
+
+    if guess.artist.is_none() {
+        let file_stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+
+        // "01 - Title" has a track number, not an artist
+        if let Some((artist, _title)) = file_stem.split_once(" - ") {
+            if !artist.trim().chars().all(|c| c.is_ascii_digit()) {
+                guess.artist = non_empty(artist);
+            }
+        }
+    }
+
+    guess
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn guess(path: &str) -> PathGuess {
+        guess_from_path(&PathBuf::from(path))
+    }
+
+    #[test]
+    fn guesses_artist_and_album_from_folders() {
+        let g = guess("/home/me/Music/Pink Floyd/1977 - Animals/01 - Pigs on the Wing.flac");
+        assert_eq!(g.artist.as_deref(), Some("Pink Floyd"));
+        assert_eq!(g.album.as_deref(), Some("Animals"));
+
+        let g = guess("/home/me/Music/The Doors - L.A. Woman/03 - Love Her Madly.mp3");
+        assert_eq!(g.artist.as_deref(), Some("The Doors"));
+        assert_eq!(g.album.as_deref(), Some("L.A. Woman"));
+
+        let g = guess("/home/me/Music/(1969) Happy Sad/01.flac");
+        assert_eq!(g.artist.as_deref(), None);
+        assert_eq!(g.album.as_deref(), Some("Happy Sad"));
+    }
+
+    #[test]
+    fn guesses_artist_from_file_name_in_generic_folders() {
+        let g = guess("/home/me/Downloads/Tim Buckley - Strange Feelin'.mp3");
+        assert_eq!(g.artist.as_deref(), Some("Tim Buckley"));
+        assert_eq!(g.album, None);
+
+        let g = guess("/home/me/music/01 - Track.mp3");
+        assert_eq!(g, PathGuess::default());
+    }
+}
@@ -3,14 +3,25 @@ use std::sync::{
     atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
 };
 use std::time::Duration;
-use std::collections::VecDeque;
-
-use crate::structs::Song;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::path::PathBuf;
+
+use crate::structs::{
+    Song,
+    SongSort,
+    SongStats,
+    shuffle::{album_shuffle, balanced_shuffle},
+};
 
 pub struct Queue {
     songs: Arc<Mutex<VecDeque<Song>>>,
     selected_item_index: AtomicUsize,
 
+    /// Songs added by the auto-DJ, rather than by the user.
+    auto_dj_songs: Mutex<HashSet<Song>>,
+    /// How the queue was last sorted, so sorting it again picks up from there.
+    sort: Mutex<SongSort>,
+
     pop_condvar: Condvar,
     must_exit_pop_loop: AtomicBool,
 
@@ -32,6 +43,9 @@ impl Queue {
             songs: Arc::new(Mutex::new(songs)),
             selected_item_index: AtomicUsize::new(0),
 
+            auto_dj_songs: Mutex::new(HashSet::new()),
+            sort: Mutex::new(SongSort::default()),
+
             pop_condvar: Condvar::new(),
             must_exit_pop_loop: AtomicBool::new(false),
 
@@ -57,6 +71,7 @@ impl Queue {
                 log::trace!(target: target, "Got song {:?}", song.title);
                 self.queue_length.fetch_sub(1, Ordering::SeqCst);
                 self.set_total_time(song_list_to_duration(&items).as_secs());
+                self.auto_dj_songs.lock().unwrap().remove(&song);
                 return Ok(song);
             }
 
@@ -135,18 +150,75 @@ impl Queue {
         });
     }
 
+    pub fn prepend(&self, songs: Vec<Song>) {
+        self.mut_queue(|queue_songs| {
+            for song in songs.into_iter().rev() {
+                queue_songs.push_front(song);
+            }
+        });
+    }
+
     pub fn add_back(&self, song: Song) {
         self.mut_queue(|queue_songs| {
             queue_songs.push_back(song);
         });
     }
 
+    pub fn add_auto_dj(&self, song: Song) {
+        self.auto_dj_songs.lock().unwrap().insert(song.clone());
+        self.add_back(song);
+    }
+
+    pub fn is_auto_dj(&self, song: &Song) -> bool {
+        self.auto_dj_songs.lock().unwrap().contains(song)
+    }
+
     pub fn append(&self, songs: &mut VecDeque<Song>) {
         self.mut_queue(|queue_songs| {
             queue_songs.append(songs);
         });
     }
 
+    pub fn sort(&self) -> SongSort {
+        *self.sort.lock().unwrap()
+    }
+
+    /// Remembers how the queue was sorted, without sorting it.
+    pub fn set_sort(&self, sort: SongSort) {
+        *self.sort.lock().unwrap() = sort;
+    }
+
+    /// Sorts the queue. Takes the stats of the songs in it, for the keys that need them.
+    pub fn sort_by(&self, sort: SongSort, stats: impl FnOnce(&[Song]) -> Vec<SongStats>) {
+        self.mut_queue(|queue_songs| {
+            let songs = Vec::from(std::mem::take(queue_songs));
+            let stats = stats(&songs);
+            *queue_songs = VecDeque::from(sort.sort(&songs, &stats));
+        });
+        self.set_sort(sort);
+        self.selected_item_index.store(0, Ordering::SeqCst);
+    }
+
+    /// Shuffles the queue, spreading artists and albums as evenly as possible.
+    pub fn shuffle(&self) {
+        self.mut_queue(|queue_songs| {
+            let songs = Vec::from(std::mem::take(queue_songs));
+            *queue_songs = VecDeque::from(balanced_shuffle(songs, &mut rand::thread_rng()));
+        });
+        self.set_sort(SongSort::default());
+        self.selected_item_index.store(0, Ordering::SeqCst);
+    }
+
+    /// Shuffles the order of the albums in the queue, keeping each album's tracks together and in order.
+    pub fn shuffle_albums(&self) {
+        self.mut_queue(|queue_songs| {
+            let songs = Vec::from(std::mem::take(queue_songs));
+            *queue_songs = VecDeque::from(album_shuffle(songs, &mut rand::thread_rng()));
+        });
+        self.set_sort(SongSort::default());
+        self.selected_item_index.store(0, Ordering::SeqCst);
+    }
+
     pub fn remove_selected(&self) {
         if self.length() == 0 {
             return;
@@ -155,11 +227,33 @@ impl Queue {
         let selected_index = self.selected_song_index();
 
         self.mut_queue(|queue_songs| {
-            queue_songs.remove(selected_index);
+            if let Some(song) = queue_songs.remove(selected_index) {
+                self.auto_dj_songs.lock().unwrap().remove(&song);
+            }
         });
 
         self.select_previous();
     }
+
+    /// Points the songs whose files moved to their new paths.
+    pub fn relink(&self, new_paths: &BTreeMap<PathBuf, PathBuf>) {
+        self.mut_queue(|queue_songs| {
+            let mut auto_dj_songs = self.auto_dj_songs.lock().unwrap();
+
+            for song in queue_songs.iter_mut() {
+                let Some(new_path) = new_paths.get(&song.path) else {
+                    continue;
+                };
+
+                let is_auto_dj = auto_dj_songs.remove(song);
+                song.path = new_path.clone();
+
+                if is_auto_dj {
+                    auto_dj_songs.insert(song.clone());
+                }
+            }
+        });
+    }
 }
 
 impl Drop for Queue {
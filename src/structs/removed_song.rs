@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+use crate::structs::Song;
+
+/// How long removed songs are kept around to be restored, in seconds.
+pub const KEEP_REMOVED_FOR: u64 = 30 * 24 * 60 * 60;
+
+/// A song removed from the library, kept for a while so it can be restored. Its file is left untouched.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct RemovedSong {
+    pub song: Song,
+    /// Seconds since the unix epoch.
+    pub removed_at: u64,
+}
+
+impl RemovedSong {
+    pub fn is_expired(&self, now: u64) -> bool {
+        now.saturating_sub(self.removed_at) > KEEP_REMOVED_FOR
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+};
+
+use rand::{seq::SliceRandom, Rng};
+
+use crate::structs::Song;
+
+/// Groups songs by the given key, keeping groups in the order they're first seen,
+/// and songs within each group in their original order.
+fn group_by<K: Eq + Hash>(songs: Vec<Song>, key: impl Fn(&Song) -> K) -> Vec<Vec<Song>> {
+    let mut groups: Vec<Vec<Song>> = vec![];
+    let mut group_indices: HashMap<K, usize> = HashMap::new();
+
+    for song in songs {
+        let k = key(&song);
+
+        if let Some(&i) = group_indices.get(&k) {
+            groups[i].push(song);
+        } else {
+            group_indices.insert(k, groups.len());
+            groups.push(vec![song]);
+        }
+    }
+
+    groups
+}
+
+/// Merges the groups so songs of each group end up evenly spread across the whole list.
+///
+/// Each song gets a position in [0, 1), at `offset + i / n`, where `n` is the size of its group and `offset` is a
+/// random number in [0, 1 / n). Sorting by that position interleaves the groups.
+fn spread(groups: Vec<Vec<Song>>, rng: &mut impl Rng) -> Vec<Song> {
+    let mut positioned: Vec<(f64, Song)> = Vec::with_capacity(groups.iter().map(Vec::len).sum());
+
+    for group in groups {
+        let n = group.len() as f64;
+        let offset = rng.gen::<f64>() / n;
+
+        for (i, song) in group.into_iter().enumerate() {
+            positioned.push((offset + i as f64 / n, song));
+        }
+    }
+
+    positioned.sort_by(|a, b| a.0.total_cmp(&b.0));
+    positioned.into_iter().map(|(_, song)| song).collect()
+}
+
+/// Shuffles the songs, spreading artists evenly across the list, and, within each artist, spreading their albums
+/// evenly too, so the same artist or album rarely plays twice in a row.
+pub fn balanced_shuffle(songs: Vec<Song>, rng: &mut impl Rng) -> Vec<Song> {
+    let artists = group_by(songs, |s| s.artist.clone())
+        .into_iter()
+        .map(|artist_songs| {
+            let mut albums = group_by(artist_songs, |s| s.album.clone());
+            albums.shuffle(rng);
+
+            for album in albums.iter_mut() {
+                album.shuffle(rng);
+            }
+
+            spread(albums, rng)
+        })
+        .collect();
+
+    spread(artists, rng)
+}
+
+/// Shuffles the order of the albums, keeping the tracks of each album together and in order.
+pub fn album_shuffle(songs: Vec<Song>, rng: &mut impl Rng) -> Vec<Song> {
+    let mut albums = group_by(songs, |s| (s.artist.clone(), s.album.clone()));
+
+    albums.shuffle(rng);
+
+    for album in albums.iter_mut() {
+        album.sort_by_key(|s| (s.disc.unwrap_or(1), s.track.is_none(), s.track, s.start_time));
+    }
+
+    albums.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn song(artist: &str, album: &str, track: u32) -> Song {
+        Song {
+            path: PathBuf::from(format!("/music/{artist}/{album}/{track}.flac")),
+            start_time: Duration::ZERO,
+            length: Duration::from_secs(180),
+            title: format!("{artist} {album} {track}"),
+            artist: Some(artist.to_string()),
+            album: Some(album.to_string()),
+            track: Some(track),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn balanced_shuffle_interleaves_artists() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let songs: Vec<Song> = (1..=5)
+            .map(|t| song("Pink Floyd", "Animals", t))
+            .chain((1..=5).map(|t| song("The Doors", "L.A. Woman", t)))
+            .collect();
+
+        let shuffled = balanced_shuffle(songs.clone(), &mut rng);
+
+        assert_eq!(shuffled.len(), songs.len());
+        assert!(songs.iter().all(|s| shuffled.contains(s)));
+
+        for pair in shuffled.windows(2) {
+            assert_ne!(pair[0].artist, pair[1].artist, "{:#?}", shuffled);
+        }
+    }
+
+    #[test]
+    fn album_shuffle_keeps_albums_together_and_in_order() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let songs: Vec<Song> = [3, 1, 2]
+            .into_iter()
+            .map(|t| song("Pink Floyd", "Animals", t))
+            .chain([2, 1].into_iter().map(|t| song("Pink Floyd", "Meddle", t)))
+            .chain([1, 2, 3].into_iter().map(|t| song("The Doors", "L.A. Woman", t)))
+            .collect();
+
+        let shuffled = album_shuffle(songs.clone(), &mut rng);
+
+        assert_eq!(shuffled.len(), songs.len());
+
+        let albums: Vec<Vec<Song>> = group_by(shuffled.clone(), |s| s.album.clone());
+        assert_eq!(albums.len(), 3, "albums must not be split: {:#?}", shuffled);
+
+        for album in albums {
+            let tracks: Vec<u32> = album.iter().filter_map(|s| s.track).collect();
+            let mut sorted_tracks = tracks.clone();
+            sorted_tracks.sort();
+            assert_eq!(tracks, sorted_tracks);
+        }
+    }
+}
@@ -0,0 +1,401 @@
+use std::cmp::Reverse;
+
+use serde::{Deserialize, Serialize};
+
+use crate::structs::{Song, SongStats};
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// An inclusive range of numbers. Either end may be left open.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Range {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<u64>,
+}
+
+impl Range {
+    pub fn contains(&self, n: u64) -> bool {
+        self.min.is_none_or(|min| n >= min) && self.max.is_none_or(|max| n <= max)
+    }
+
+    /// Parses `5`, `1-3`, `>5` or `<5`, with an optional unit suffix, such as the `d` in `<30d`.
+    fn parse(s: &str, unit: &str) -> Option<Self> {
+        let number = |s: &str| s.strip_suffix(unit).unwrap_or(s).parse::<u64>().ok();
+
+        if let Some(n) = s.strip_prefix('>') {
+            Some(Self { min: Some(number(n)?.checked_add(1)?), max: None })
+        } else if let Some(n) = s.strip_prefix('<') {
+            Some(Self { min: None, max: Some(number(n)?.checked_sub(1)?) })
+        } else if let Some((min, max)) = s.split_once('-') {
+            Some(Self { min: Some(number(min)?), max: Some(number(max)?) })
+        } else {
+            let n = number(s)?;
+            Some(Self { min: Some(n), max: Some(n) })
+        }
+    }
+
+    fn format(self, unit: &str) -> String {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) if min == max => format!("{min}{unit}"),
+            (Some(min), Some(max)) => format!("{min}{unit}-{max}{unit}"),
+            (Some(min), None) => format!(">{}{unit}", min.saturating_sub(1)),
+            (None, Some(max)) => match max.checked_add(1) {
+                Some(end) => format!("<{end}{unit}"),
+                None => format!("0{unit}-{max}{unit}"),
+            },
+            (None, None) => format!(">0{unit}"),
+        }
+    }
+}
+
+/// A condition songs of a smart playlist must meet.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartRule {
+    /// The artist or album artist contains the text, ignoring case. So do the album, genre and title rules.
+    Artist(String),
+    Album(String),
+    Genre(String),
+    Title(String),
+    Year(Range),
+    PlayCount(Range),
+    /// Days since the song was last played. Songs never played match any range without a maximum.
+    LastPlayed(Range),
+    /// Days since the song was added to the library.
+    Added(Range),
+    /// Length of the song, in seconds.
+    Length(Range),
+    Favorite(bool),
+}
+
+impl SmartRule {
+    fn matches(&self, song: &Song, stats: Option<&SongStats>, added: Option<u64>, now: u64) -> bool {
+        let contains = |field: Option<&str>, text: &str| field.is_some_and(|f| f.to_lowercase().contains(&text.to_lowercase()));
+        let days_ago = |time: Option<u64>| time.map(|t| now.saturating_sub(t) / DAY);
+
+        match self {
+            SmartRule::Artist(text) => {
+                contains(song.artist.as_deref(), text) || contains(song.album_artist.as_deref(), text)
+            }
+            SmartRule::Album(text) => contains(song.album.as_deref(), text),
+            SmartRule::Genre(text) => contains(song.genre.as_deref(), text),
+            SmartRule::Title(text) => contains(Some(&song.title), text),
+            SmartRule::Year(range) => song.year.is_some_and(|y| range.contains(y as u64)),
+            SmartRule::PlayCount(range) => range.contains(stats.map_or(0, |s| s.play_count) as u64),
+            SmartRule::LastPlayed(range) => match days_ago(stats.and_then(|s| s.last_played)) {
+                Some(days) => range.contains(days),
+                None => range.max.is_none(),
+            },
+            SmartRule::Added(range) => days_ago(added).is_some_and(|days| range.contains(days)),
+            SmartRule::Length(range) => range.contains(song.length.as_secs()),
+            SmartRule::Favorite(favorite) => stats.is_some_and(|s| s.favorite) == *favorite,
+        }
+    }
+
+    fn to_query(&self) -> String {
+        let text = |s: &str| if s.contains(' ') { format!("\"{s}\"") } else { s.to_string() };
+
+        match self {
+            SmartRule::Artist(s) => format!("artist:{}", text(s)),
+            SmartRule::Album(s) => format!("album:{}", text(s)),
+            SmartRule::Genre(s) => format!("genre:{}", text(s)),
+            SmartRule::Title(s) => format!("title:{}", text(s)),
+            SmartRule::Year(range) => format!("year:{}", range.format("")),
+            SmartRule::PlayCount(range) => format!("plays:{}", range.format("")),
+            SmartRule::LastPlayed(range) => format!("played:{}", range.format("d")),
+            SmartRule::Added(range) => format!("added:{}", range.format("d")),
+            SmartRule::Length(range) => format!("length:{}", range.format("s")),
+            SmartRule::Favorite(true) => "favorite".to_string(),
+            SmartRule::Favorite(false) => "favorite:no".to_string(),
+        }
+    }
+}
+
+/// The order of the songs of a smart playlist. Songs that tie stay in library order: by artist, album and track.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartSort {
+    #[default]
+    Library,
+    Title,
+    Year,
+    MostPlayed,
+    LeastPlayed,
+    RecentlyPlayed,
+    RecentlyAdded,
+    Longest,
+    Shortest,
+}
+
+impl SmartSort {
+    const ALL: [SmartSort; 9] = [
+        SmartSort::Library,
+        SmartSort::Title,
+        SmartSort::Year,
+        SmartSort::MostPlayed,
+        SmartSort::LeastPlayed,
+        SmartSort::RecentlyPlayed,
+        SmartSort::RecentlyAdded,
+        SmartSort::Longest,
+        SmartSort::Shortest,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SmartSort::Library => "library",
+            SmartSort::Title => "title",
+            SmartSort::Year => "year",
+            SmartSort::MostPlayed => "most_played",
+            SmartSort::LeastPlayed => "least_played",
+            SmartSort::RecentlyPlayed => "recently_played",
+            SmartSort::RecentlyAdded => "recently_added",
+            SmartSort::Longest => "longest",
+            SmartSort::Shortest => "shortest",
+        }
+    }
+}
+
+/// A song of the library, along with what smart playlist rules need to know about it.
+pub struct SmartCandidate<'s> {
+    pub song: &'s Song,
+    pub stats: Option<&'s SongStats>,
+    /// When the song was added to the library, in seconds since the unix epoch, if known.
+    pub added: Option<u64>,
+}
+
+/// A playlist of the songs of the library that meet its rules, rather than of songs added to it by hand.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct SmartPlaylist {
+    pub name: String,
+    /// Whether songs need to meet any of the rules, rather than all of them.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub match_any: bool,
+    #[serde(default)]
+    pub sort: SmartSort,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub rules: Vec<SmartRule>,
+}
+
+impl SmartPlaylist {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            match_any: false,
+            sort: SmartSort::default(),
+            limit: None,
+            rules: vec![],
+        }
+    }
+
+    /// The songs that meet the rules of the playlist, sorted and limited as it says.
+    /// Candidates are expected in library order, which songs that tie keep.
+    pub fn songs(&self, candidates: Vec<SmartCandidate>, now: u64) -> Vec<Song> {
+        let matches = |c: &SmartCandidate| {
+            let mut rules = self.rules.iter();
+            let meets = |rule: &SmartRule| rule.matches(c.song, c.stats, c.added, now);
+
+            if self.match_any {
+                self.rules.is_empty() || rules.any(meets)
+            } else {
+                rules.all(meets)
+            }
+        };
+
+        let mut songs: Vec<SmartCandidate> = candidates.into_iter().filter(matches).collect();
+
+        let play_count = |c: &SmartCandidate| c.stats.map_or(0, |s| s.play_count);
+
+        match self.sort {
+            SmartSort::Library => {}
+            SmartSort::Title => songs.sort_by_key(|c| c.song.title.to_lowercase()),
+            SmartSort::Year => songs.sort_by_key(|c| c.song.year),
+            SmartSort::MostPlayed => songs.sort_by_key(|c| Reverse(play_count(c))),
+            SmartSort::LeastPlayed => songs.sort_by_key(play_count),
+            SmartSort::RecentlyPlayed => songs.sort_by_key(|c| Reverse(c.stats.and_then(|s| s.last_played))),
+            SmartSort::RecentlyAdded => songs.sort_by_key(|c| Reverse(c.added)),
+            SmartSort::Longest => songs.sort_by_key(|c| Reverse(c.song.length)),
+            SmartSort::Shortest => songs.sort_by_key(|c| c.song.length),
+        }
+
+        songs.into_iter().take(self.limit.unwrap_or(usize::MAX)).map(|c| c.song.clone()).collect()
+    }
+
+    /// The rules, sort and limit of the playlist, as edited in the playlists tab.
+    /// For example, `artist:"pink floyd" year:1970-1979 plays:>5 sort:most_played limit:20`.
+    pub fn to_query(&self) -> String {
+        let mut terms: Vec<String> = self.rules.iter().map(SmartRule::to_query).collect();
+
+        if self.match_any {
+            terms.push("any".to_string());
+        }
+
+        if self.sort != SmartSort::Library {
+            terms.push(format!("sort:{}", self.sort.name()));
+        }
+
+        if let Some(limit) = self.limit {
+            terms.push(format!("limit:{limit}"));
+        }
+
+        terms.join(" ")
+    }
+
+    /// Replaces the rules, sort and limit of the playlist with the ones of the query. See [`SmartPlaylist::to_query`].
+    pub fn set_query(&mut self, query: &str) -> Result<(), String> {
+        let mut rules = vec![];
+        let mut match_any = false;
+        let mut sort = SmartSort::default();
+        let mut limit = None;
+
+        for term in split_query(query) {
+            let (key, value) = term.split_once(':').unwrap_or((term.as_str(), ""));
+            let range = |unit: &str| Range::parse(value, unit).ok_or(format!("invalid range in '{term}'"));
+
+            match key {
+                "artist" => rules.push(SmartRule::Artist(value.to_string())),
+                "album" => rules.push(SmartRule::Album(value.to_string())),
+                "genre" => rules.push(SmartRule::Genre(value.to_string())),
+                "title" => rules.push(SmartRule::Title(value.to_string())),
+                "year" => rules.push(SmartRule::Year(range("")?)),
+                "plays" => rules.push(SmartRule::PlayCount(range("")?)),
+                "played" if value == "never" => rules.push(SmartRule::PlayCount(Range { min: None, max: Some(0) })),
+                "played" => rules.push(SmartRule::LastPlayed(range("d")?)),
+                "added" => rules.push(SmartRule::Added(range("d")?)),
+                "length" => rules.push(SmartRule::Length(range("s")?)),
+                "favorite" => rules.push(SmartRule::Favorite(value != "no")),
+                "any" => match_any = true,
+                "sort" => {
+                    sort = *SmartSort::ALL
+                        .iter()
+                        .find(|s| s.name() == value)
+                        .ok_or(format!("unknown sort '{value}'"))?;
+                }
+                "limit" => limit = Some(value.parse().map_err(|_| format!("invalid limit '{value}'"))?),
+                _ => return Err(format!("unknown rule '{term}'")),
+            }
+        }
+
+        self.rules = rules;
+        self.match_any = match_any;
+        self.sort = sort;
+        self.limit = limit;
+
+        Ok(())
+    }
+}
+
+/// Splits the query by whitespace, except inside double quotes, which are removed.
+fn split_query(query: &str) -> Vec<String> {
+    let mut terms = vec![];
+    let mut term = String::new();
+    let mut in_quotes = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !term.is_empty() {
+                    terms.push(std::mem::take(&mut term));
+                }
+            }
+            c => term.push(c),
+        }
+    }
+
+    if !term.is_empty() {
+        terms.push(term);
+    }
+
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use super::*;
+
+    fn song(title: &str, artist: &str, year: u32) -> Song {
+        Song {
+            path: PathBuf::from(format!("/music/{title}.flac")),
+            title: title.to_string(),
+            artist: Some(artist.to_string()),
+            year: Some(year),
+            length: Duration::from_secs(200),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn queries_round_trip() {
+        let mut playlist = SmartPlaylist::new("70s Floyd".to_string());
+        let query = "artist:\"pink floyd\" year:1970-1979 plays:>5 played:<30d favorite any sort:most_played limit:20";
+
+        playlist.set_query(query).unwrap();
+
+        assert_eq!(playlist.rules[0], SmartRule::Artist("pink floyd".to_string()));
+        assert_eq!(playlist.rules[2], SmartRule::PlayCount(Range { min: Some(6), max: None }));
+        assert_eq!(playlist.rules[3], SmartRule::LastPlayed(Range { min: None, max: Some(29) }));
+        assert_eq!(playlist.limit, Some(20));
+        assert_eq!(playlist.to_query(), query);
+
+        assert!(playlist.set_query("loudness:11").is_err());
+        assert!(playlist.set_query("year:recent").is_err());
+        assert!(playlist.set_query(&format!("plays:>{}", u64::MAX)).is_err());
+
+        // Such as set in the smart playlists file
+        let rule = SmartRule::PlayCount(Range { min: None, max: Some(u64::MAX) });
+        assert_eq!(rule.to_query(), format!("plays:0-{}", u64::MAX));
+    }
+
+    #[test]
+    fn smart_playlists_round_trip_through_toml() {
+        let mut playlist = SmartPlaylist::new("Forgotten favorites".to_string());
+        playlist.set_query("favorite played:>90d sort:least_played limit:50").unwrap();
+
+        let string = toml::to_string(&playlist).unwrap();
+        let parsed: SmartPlaylist = toml::from_str(&string).unwrap();
+
+        assert_eq!(parsed, playlist);
+    }
+
+    #[test]
+    fn picks_sorts_and_limits_songs() {
+        let now = 1_000_000_000;
+        let songs = [song("Dogs", "Pink Floyd", 1977), song("Time", "Pink Floyd", 1973), song("Roxanne", "The Police", 1978)];
+        let stats = [
+            SongStats { play_count: 2, ..SongStats::new(&songs[0]) },
+            SongStats { play_count: 9, last_played: Some(now - DAY), ..SongStats::new(&songs[1]) },
+        ];
+
+        let candidates = || {
+            songs
+                .iter()
+                .map(|song| SmartCandidate {
+                    song,
+                    stats: stats.iter().find(|s| s.path == song.path),
+                    added: None,
+                })
+                .collect()
+        };
+
+        let mut playlist = SmartPlaylist::new("Smart".to_string());
+        let titles = |p: &SmartPlaylist| p.songs(candidates(), now).into_iter().map(|s| s.title).collect::<Vec<_>>();
+
+        playlist.set_query("artist:floyd sort:most_played").unwrap();
+        assert_eq!(titles(&playlist), vec!["Time", "Dogs"]);
+
+        playlist.set_query("year:>1975 sort:title limit:1").unwrap();
+        assert_eq!(titles(&playlist), vec!["Dogs"]);
+
+        playlist.set_query("played:>7d").unwrap();
+        assert_eq!(titles(&playlist), vec!["Dogs", "Roxanne"]);
+
+        playlist.set_query("played:never artist:police any").unwrap();
+        assert_eq!(titles(&playlist), vec!["Roxanne"]);
+    }
+}
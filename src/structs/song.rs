@@ -3,15 +3,15 @@ use std::{
     time::Duration,
 };
 
-use lofty::{Accessor, AudioFile, LoftyError, Probe, TaggedFileExt};
+use lofty::{Accessor, AudioFile, ItemKey, LoftyError, Probe, TaggedFileExt};
 use serde::{Deserialize, Serialize};
 
-use crate::{
-    cue::CueSheet,
-    components::{FileBrowserSelection, directory_to_songs_and_folders},
-};
+use crate::cue::CueSheet;
+
+/// The library artist of compilations without an album artist.
+pub const VARIOUS_ARTISTS: &str = "Various Artists";
 
-#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct Song {
     pub path: PathBuf,
     pub start_time: Duration,
@@ -20,52 +20,137 @@ pub struct Song {
     pub artist: Option<String>,
     pub album: Option<String>,
     pub track: Option<u32>,
+    #[serde(default)]
+    pub year: Option<u32>,
+    #[serde(default)]
+    pub album_artist: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub compilation: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track_total: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disc: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disc_total: Option<u32>,
+    /// As tagged. May hold several genres, separated by semicolons. See [`Song::genres`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub genre: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub composer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+/// The year of a date such as "1969", "1969-05-01" or "1969/05/01".
+pub fn year_from_date(date: &str) -> Option<u32> {
+    let date = date.trim();
+    let digits = date.chars().take_while(char::is_ascii_digit).count();
+
+    if digits == 4 {
+        date[..4].parse().ok()
+    } else {
+        None
+    }
 }
 
 impl Song {
     pub fn from_file(path: &PathBuf) -> Result<Self, LoftyError> {
         let tagged_file = Probe::open(path)?.read()?;
 
-        let (artist, album, title, track) = match tagged_file.primary_tag() {
-            Some(primary_tag) => (
-                primary_tag.artist().map(String::from),
-                primary_tag.album().map(String::from),
-                primary_tag.title().map(String::from),
-                primary_tag.track(),
-            ),
-            _ => (None, None, None, None),
-        };
-
-        Ok(Song {
+        let mut song = Song {
             path: PathBuf::from(path),
             start_time: Duration::ZERO,
             length: tagged_file.properties().duration(),
-            title: title.unwrap_or(path.file_name().unwrap().to_str().unwrap().to_string()),
-            artist,
-            album,
-            track,
-        })
-    }
+            title: path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default(),
+            ..Default::default()
+        };
 
-    pub fn from_dir(path: &PathBuf) -> Vec<Self> {
-        let songs = directory_to_songs_and_folders(path);
-        songs.iter().filter_map(|s| {
-            if let FileBrowserSelection::Song(song) = s {
-                Some(song.clone())
-            } else {
-                None
+        if let Some(tag) = tagged_file.primary_tag() {
+            let get_string = |key: ItemKey| tag.get_string(&key).map(String::from);
+
+            if let Some(title) = tag.title() {
+                song.title = title.to_string();
             }
-        }).collect()
+
+            song.artist = tag.artist().map(String::from);
+            song.album = tag.album().map(String::from);
+            song.track = tag.track();
+            song.track_total = tag.track_total();
+            song.disc = tag.disk();
+            song.disc_total = tag.disk_total();
+            song.year = tag.year().or_else(|| {
+                tag.get_string(&ItemKey::RecordingDate).and_then(year_from_date)
+            });
+            song.album_artist = get_string(ItemKey::AlbumArtist);
+            song.compilation = tag
+                .get_string(&ItemKey::FlagCompilation)
+                .is_some_and(|flag| flag == "1" || flag.eq_ignore_ascii_case("true"));
+            song.genre = tag.genre().map(String::from);
+            song.composer = get_string(ItemKey::Composer);
+            song.label = get_string(ItemKey::Label);
+            song.comment = tag.comment().map(String::from);
+        }
+
+        Ok(song)
+    }
+
+    /// The artist the song is filed under in the library: its album artist, if any, or its own artist otherwise.
+    /// Compilations without an album artist are filed under "Various Artists".
+    pub fn library_artist(&self) -> Option<String> {
+        self.library_artist_name().map(String::from)
+    }
+
+    /// [`Song::library_artist`], without copying it.
+    pub fn library_artist_name(&self) -> Option<&str> {
+        if self.is_compilation() {
+            Some(self.album_artist.as_deref().unwrap_or(VARIOUS_ARTISTS))
+        } else {
+            self.album_artist.as_deref().or(self.artist.as_deref())
+        }
+    }
+
+    /// Whether the song is part of a compilation, either because its tags say so,
+    /// or because its album artist is "Various Artists".
+    pub fn is_compilation(&self) -> bool {
+        self.compilation || self.album_artist.as_ref().is_some_and(|a| a.eq_ignore_ascii_case(VARIOUS_ARTISTS) || a == "VA")
+    }
+
+    /// The genres of the song, for songs tagged with several, semicolon-separated, genres.
+    pub fn genres(&self) -> Vec<String> {
+        self.genre
+            .iter()
+            .flat_map(|genre| genre.split(';'))
+            .map(str::trim)
+            .filter(|genre| !genre.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    /// The track number, prefixed with the disc number for albums with several discs, as in "2.03".
+    pub fn track_label(&self) -> String {
+        let track = self.track.unwrap_or(0);
+
+        match self.disc {
+            Some(disc) if disc > 1 || self.disc_total.is_some_and(|total| total > 1) => format!("{disc}.{track:02}"),
+            _ => track.to_string(),
+        }
     }
 
     pub fn from_cue_sheet(cue_sheet: CueSheet) -> Vec<Self> {
-        let cue_file = cue_sheet.file().unwrap();
+        let cue_path = cue_sheet.cue_sheet_file_path();
+
+        let Some(cue_file) = cue_sheet.file() else {
+            log::warn!(target: "::song.from_cue_sheet", "Cue sheet without a FILE line: {:?}", cue_path);
+            return Vec::new();
+        };
+
         let performer = cue_sheet.performer();
         let file_name = cue_file.name();
         let tracks = cue_file.tracks();
 
-        let cue_path = cue_sheet.cue_sheet_file_path();
-        let song_path = cue_path.parent().unwrap().join(file_name);
+        let song_path = cue_path.with_file_name(file_name);
 
         let song = match Song::from_file(&song_path) {
             Ok(s) => s,
@@ -80,16 +165,25 @@ impl Song {
 
         // log::debug!(target: "::Song.from_cue_sheet()", "{:#?}", tracks);
 
+        let track_total = tracks.len() as u32;
+        let genre = cue_sheet.remark("GENRE").or(song.genre.clone());
+        let year = cue_sheet.remark("DATE").as_deref().and_then(year_from_date).or(song.year);
+
         let mut songs: Vec<Song> = tracks
             .iter()
             .map(|t| Song {
                 path: song_path.clone(),
                 length: Duration::ZERO,
-                artist: performer.clone().or(t.performer()),
+                artist: t.performer().or(performer.clone()),
+                album_artist: performer.clone(),
                 title: t.title(),
                 start_time: t.start_time(),
                 album: cue_sheet.title(),
                 track: t.index().split_whitespace().nth(0).map(|i| i.parse().ok()).flatten(),
+                track_total: Some(track_total),
+                year,
+                genre: genre.clone(),
+                ..song.clone()
             })
             .collect();
 
@@ -106,3 +200,56 @@ impl Song {
         songs
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn library_artist_prefers_album_artist_and_files_compilations_under_various_artists() {
+        let song = Song {
+            artist: Some("David Gilmour".to_string()),
+            album_artist: Some("Pink Floyd".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(song.library_artist().as_deref(), Some("Pink Floyd"));
+        assert!(!song.is_compilation());
+
+        let song = Song {
+            artist: Some("Yoko Kanno".to_string()),
+            compilation: true,
+            ..Default::default()
+        };
+        assert_eq!(song.library_artist().as_deref(), Some(VARIOUS_ARTISTS));
+        assert!(song.is_compilation());
+
+        let song = Song {
+            artist: Some("The Doors".to_string()),
+            album_artist: Some("various artists".to_string()),
+            ..Default::default()
+        };
+        assert!(song.is_compilation());
+
+        let song = Song {
+            artist: Some("The Doors".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(song.library_artist().as_deref(), Some("The Doors"));
+    }
+
+    #[test]
+    fn reads_years_from_dates_and_splits_genres() {
+        assert_eq!(year_from_date("1969"), Some(1969));
+        assert_eq!(year_from_date("1969-05-01"), Some(1969));
+        assert_eq!(year_from_date("May 1969"), None);
+
+        let song = Song {
+            genre: Some("Folk; Blues;".to_string()),
+            disc: Some(2),
+            track: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(song.genres(), vec!["Folk".to_string(), "Blues".to_string()]);
+        assert_eq!(song.track_label(), "2.03");
+    }
+}
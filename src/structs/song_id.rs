@@ -0,0 +1,22 @@
+use std::{path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::structs::Song;
+
+/// Tells a song apart from the rest of the library: by the file it's in and, for cue sheet tracks, where it starts.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct SongId {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub start_time: Duration,
+}
+
+impl SongId {
+    pub fn of(song: &Song) -> Self {
+        Self {
+            path: song.path.clone(),
+            start_time: song.start_time,
+        }
+    }
+}
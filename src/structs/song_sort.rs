@@ -0,0 +1,212 @@
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::structs::{compare_artists, Song, SongStats};
+
+/// What songs are sorted by.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SongSortKey {
+    /// The order of the playlist or queue itself.
+    #[default]
+    Custom,
+    /// By artist, album, disc and track. The library groups songs by album when sorted by it.
+    Album,
+    Title,
+    Track,
+    Year,
+    Length,
+    Added,
+    Plays,
+    LastPlayed,
+    Skips,
+    Favorite,
+}
+
+impl SongSortKey {
+    const ALL: [SongSortKey; 11] = [
+        SongSortKey::Custom,
+        SongSortKey::Album,
+        SongSortKey::Title,
+        SongSortKey::Track,
+        SongSortKey::Year,
+        SongSortKey::Length,
+        SongSortKey::Added,
+        SongSortKey::Plays,
+        SongSortKey::LastPlayed,
+        SongSortKey::Skips,
+        SongSortKey::Favorite,
+    ];
+
+    /// The next key, skipping [`SongSortKey::Custom`] for views without an order of their own, such as the library.
+    pub fn next(&self, with_custom: bool) -> Self {
+        let i = Self::ALL.iter().position(|k| k == self).unwrap_or(0);
+
+        Self::ALL
+            .iter()
+            .cycle()
+            .skip(i + 1)
+            .find(|k| with_custom || **k != SongSortKey::Custom)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SongSortKey::Custom => "custom order",
+            SongSortKey::Album => "album",
+            SongSortKey::Title => "title",
+            SongSortKey::Track => "track number",
+            SongSortKey::Year => "year",
+            SongSortKey::Length => "length",
+            SongSortKey::Added => "date added",
+            SongSortKey::Plays => "play count",
+            SongSortKey::LastPlayed => "last played",
+            SongSortKey::Skips => "skips",
+            SongSortKey::Favorite => "favorites",
+        }
+    }
+
+    /// Counts and dates are usually wanted highest or latest first.
+    fn is_descending_by_default(&self) -> bool {
+        matches!(
+            self,
+            SongSortKey::Added | SongSortKey::Plays | SongSortKey::LastPlayed | SongSortKey::Skips | SongSortKey::Favorite
+        )
+    }
+
+    fn compare(&self, a: &Song, stats_a: &SongStats, b: &Song, stats_b: &SongStats) -> Ordering {
+        let track = |s: &Song| (s.disc.unwrap_or(1), s.track);
+
+        // Artists are sorted as the library sorts them, songs without one first
+        let artist = || match (a.library_artist_name(), b.library_artist_name()) {
+            (Some(artist_a), Some(artist_b)) => compare_artists(artist_a, artist_b),
+            (artist_a, artist_b) => artist_a.cmp(&artist_b),
+        };
+
+        match self {
+            SongSortKey::Custom => Ordering::Equal,
+            SongSortKey::Album => artist().then_with(|| (&a.album, track(a)).cmp(&(&b.album, track(b)))),
+            SongSortKey::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+            SongSortKey::Track => track(a).cmp(&track(b)),
+            SongSortKey::Year => a.year.cmp(&b.year),
+            SongSortKey::Length => a.length.cmp(&b.length),
+            SongSortKey::Added => stats_a.added.cmp(&stats_b.added),
+            SongSortKey::Plays => stats_a.play_count.cmp(&stats_b.play_count),
+            SongSortKey::LastPlayed => stats_a.last_played.cmp(&stats_b.last_played),
+            SongSortKey::Skips => stats_a.skip_count.cmp(&stats_b.skip_count),
+            SongSortKey::Favorite => stats_a.favorite.cmp(&stats_b.favorite),
+        }
+    }
+}
+
+/// How a view sorts its songs: by what, and which way.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SongSort {
+    pub key: SongSortKey,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+impl SongSort {
+    pub fn by(key: SongSortKey) -> Self {
+        Self {
+            key,
+            descending: key.is_descending_by_default(),
+        }
+    }
+
+    /// Sorts by the next key, in its usual direction.
+    pub fn next(&self, with_custom: bool) -> Self {
+        Self::by(self.key.next(with_custom))
+    }
+
+    pub fn reversed(&self) -> Self {
+        Self {
+            descending: !self.descending,
+            ..*self
+        }
+    }
+
+    /// Such as "play count ↓".
+    pub fn name(&self) -> String {
+        format!("{} {}", self.key.name(), if self.descending { '↓' } else { '↑' })
+    }
+
+    /// The indexes of the songs, in sorted order. Takes the stats of each song, for the keys that need them.
+    /// The sort is stable, so songs that compare equal keep their order.
+    pub fn sorted_indexes(&self, songs: &[Song], stats: &[SongStats]) -> Vec<usize> {
+        let mut indexes: Vec<usize> = (0..songs.len()).collect();
+        let no_stats = SongStats::default();
+        let stats = |i: usize| stats.get(i).unwrap_or(&no_stats);
+
+        indexes.sort_by(|&a, &b| {
+            let ordering = self.key.compare(&songs[a], stats(a), &songs[b], stats(b));
+            if self.descending { ordering.reverse() } else { ordering }
+        });
+
+        if self.key == SongSortKey::Custom && self.descending {
+            indexes.reverse();
+        }
+
+        indexes
+    }
+
+    pub fn sort(&self, songs: &[Song], stats: &[SongStats]) -> Vec<Song> {
+        self.sorted_indexes(songs, stats).into_iter().map(|i| songs[i].clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use super::*;
+
+    fn song(title: &str, secs: u64) -> Song {
+        Song {
+            path: PathBuf::from(format!("/music/{title}.flac")),
+            title: title.to_string(),
+            length: Duration::from_secs(secs),
+            ..Default::default()
+        }
+    }
+
+    fn plays(play_count: u32) -> SongStats {
+        SongStats { play_count, ..Default::default() }
+    }
+
+    #[test]
+    fn sorts_either_way_keeping_ties_in_order() {
+        let songs = vec![song("b", 300), song("A", 100), song("c", 200), song("d", 100)];
+        let stats = vec![plays(1), plays(5), plays(1), plays(0)];
+
+        assert_eq!(SongSort::by(SongSortKey::Title).sorted_indexes(&songs, &stats), vec![1, 0, 2, 3]);
+        assert_eq!(SongSort::by(SongSortKey::Length).sorted_indexes(&songs, &stats), vec![1, 3, 2, 0]);
+        assert_eq!(SongSort::by(SongSortKey::Length).reversed().sorted_indexes(&songs, &stats), vec![0, 2, 1, 3]);
+        assert_eq!(SongSort::by(SongSortKey::Plays).sorted_indexes(&songs, &stats), vec![1, 0, 2, 3]);
+        assert_eq!(SongSort::default().sorted_indexes(&songs, &stats), vec![0, 1, 2, 3]);
+        assert_eq!(SongSort::default().reversed().sorted_indexes(&songs, &stats), vec![3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn sorts_albums_by_artist_as_the_library_does() {
+        let by = |artist: &str, album: &str| Song {
+            artist: Some(artist.to_string()),
+            album: Some(album.to_string()),
+            ..Default::default()
+        };
+        let songs = vec![by("Pink Floyd", "Meddle"), by("The Beatles", "Abbey Road"), by("PINK FLOYD", "Animals"), by("Talking Heads", "Remain in Light")];
+
+        assert_eq!(SongSort::by(SongSortKey::Album).sorted_indexes(&songs, &[]), vec![1, 2, 0, 3]);
+    }
+
+    #[test]
+    fn cycles_through_keys() {
+        assert_eq!(SongSortKey::Favorite.next(true), SongSortKey::Custom);
+        assert_eq!(SongSortKey::Favorite.next(false), SongSortKey::Album);
+        assert_eq!(SongSort::by(SongSortKey::Year).next(false), SongSort { key: SongSortKey::Length, descending: false });
+        assert_eq!(SongSort::by(SongSortKey::Length).next(false), SongSort { key: SongSortKey::Added, descending: true });
+    }
+}
@@ -0,0 +1,125 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::structs::Song;
+
+/// A song counts as played once this much of it was heard, or half of it, for songs shorter than twice this.
+const PLAY_THRESHOLD: Duration = Duration::from_secs(4 * 60);
+
+/// How much of a song has to be heard for it to count as played, rather than skipped.
+pub fn play_threshold(length: Duration) -> Duration {
+    (length / 2).min(PLAY_THRESHOLD)
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+/// How many times a song was played and skipped, when it was last played, and whether it's a favorite.
+/// Kept in the library, apart from the songs themselves, so rescanning a file doesn't reset them.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct SongStats {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub start_time: Duration,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub play_count: u32,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub skip_count: u32,
+    /// Seconds since the unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_played: Option<u64>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub favorite: bool,
+    /// When the song was added to the library, in seconds since the unix epoch.
+    /// Unknown for songs added before this was kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added: Option<u64>,
+}
+
+impl SongStats {
+    pub fn new(song: &Song) -> Self {
+        Self {
+            path: song.path.clone(),
+            start_time: song.start_time,
+            ..Default::default()
+        }
+    }
+
+    /// The stats as shown next to the song, such as "♥ · 12 plays · 1 skip · 3 days ago". Empty for songs never played.
+    pub fn summary(&self, now: u64) -> String {
+        let mut parts = vec![];
+
+        if self.favorite {
+            parts.push("♥".to_string());
+        }
+
+        match self.play_count {
+            0 => {}
+            1 => parts.push("1 play".to_string()),
+            n => parts.push(format!("{n} plays")),
+        }
+
+        match self.skip_count {
+            0 => {}
+            1 => parts.push("1 skip".to_string()),
+            n => parts.push(format!("{n} skips")),
+        }
+
+        if let Some(last_played) = self.last_played {
+            parts.push(time_ago(now.saturating_sub(last_played)));
+        }
+
+        parts.join(" · ")
+    }
+}
+
+/// Such as "3 days ago", for something that happened the given number of seconds ago.
+pub fn time_ago(secs: u64) -> String {
+    let days = secs / (24 * 60 * 60);
+
+    match days {
+        0 => "today".to_string(),
+        1 => "yesterday".to_string(),
+        2..=59 => format!("{days} days ago"),
+        60..=729 => format!("{} months ago", days / 30),
+        _ => format!("{} years ago", days / 365),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn songs_count_as_played_after_half_their_length_or_four_minutes() {
+        assert_eq!(play_threshold(Duration::from_secs(180)), Duration::from_secs(90));
+        assert_eq!(play_threshold(Duration::from_secs(20 * 60)), Duration::from_secs(4 * 60));
+    }
+
+    #[test]
+    fn summarizes_only_what_happened() {
+        let now = 1_000_000_000;
+        let mut stats = SongStats::default();
+
+        assert_eq!(stats.summary(now), "");
+
+        stats.play_count = 12;
+        stats.skip_count = 1;
+        stats.last_played = Some(now - 3 * 24 * 60 * 60);
+        assert_eq!(stats.summary(now), "12 plays · 1 skip · 3 days ago");
+
+        stats.favorite = true;
+        stats.skip_count = 0;
+        stats.last_played = Some(now - 60);
+        assert_eq!(stats.summary(now), "♥ · 12 plays · today");
+    }
+}
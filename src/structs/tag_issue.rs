@@ -0,0 +1,132 @@
+use std::time::Duration;
+
+use crate::structs::Song;
+
+/// Something missing or suspicious in the metadata of a song, listed in the library health view.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TagIssue {
+    MissingArtist,
+    MissingAlbum,
+    MissingTitle,
+    MissingTrackNumber,
+    /// Text with the unicode replacement character or control characters, usually from tags in a non-utf encoding.
+    InvalidEncoding,
+    ExtraWhitespace,
+    ZeroLength,
+}
+
+impl TagIssue {
+    pub fn description(&self) -> &'static str {
+        match self {
+            TagIssue::MissingArtist => "no artist",
+            TagIssue::MissingAlbum => "no album",
+            TagIssue::MissingTitle => "no title",
+            TagIssue::MissingTrackNumber => "no track number",
+            TagIssue::InvalidEncoding => "invalid characters",
+            TagIssue::ExtraWhitespace => "extra whitespace",
+            TagIssue::ZeroLength => "zero length",
+        }
+    }
+}
+
+fn has_invalid_characters(s: &str) -> bool {
+    s.chars().any(|c| c == char::REPLACEMENT_CHARACTER || c.is_control())
+}
+
+fn has_extra_whitespace(s: &str) -> bool {
+    s.trim() != s || s.contains("  ")
+}
+
+pub fn tag_issues(song: &Song) -> Vec<TagIssue> {
+    let mut issues = vec![];
+
+    if song.artist.is_none() && song.album_artist.is_none() {
+        issues.push(TagIssue::MissingArtist);
+    }
+
+    if song.album.is_none() {
+        issues.push(TagIssue::MissingAlbum);
+    }
+
+    // Songs without a title tag get their file name as title
+    let file_name = song.path.file_name().and_then(|f| f.to_str());
+    if song.title.trim().is_empty() || file_name == Some(song.title.as_str()) {
+        issues.push(TagIssue::MissingTitle);
+    }
+
+    if song.track.is_none() {
+        issues.push(TagIssue::MissingTrackNumber);
+    }
+
+    let texts = [
+        song.artist.as_deref(),
+        song.album_artist.as_deref(),
+        song.album.as_deref(),
+        Some(song.title.as_str()),
+    ];
+
+    if texts.iter().flatten().any(|s| has_invalid_characters(s)) {
+        issues.push(TagIssue::InvalidEncoding);
+    }
+
+    if texts.iter().flatten().any(|s| has_extra_whitespace(s)) {
+        issues.push(TagIssue::ExtraWhitespace);
+    }
+
+    if song.length == Duration::ZERO {
+        issues.push(TagIssue::ZeroLength);
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn well_tagged_songs_have_no_issues() {
+        let song = Song {
+            path: PathBuf::from("/music/Pink Floyd/Animals/01.flac"),
+            length: Duration::from_secs(85),
+            title: "Pigs on the Wing 1".to_string(),
+            artist: Some("Pink Floyd".to_string()),
+            album: Some("Animals".to_string()),
+            track: Some(1),
+            ..Default::default()
+        };
+
+        assert_eq!(tag_issues(&song), vec![]);
+    }
+
+    #[test]
+    fn untagged_songs_have_issues() {
+        let song = Song {
+            path: PathBuf::from("/music/track01.flac"),
+            title: "track01.flac".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(tag_issues(&song), vec![
+            TagIssue::MissingArtist,
+            TagIssue::MissingAlbum,
+            TagIssue::MissingTitle,
+            TagIssue::MissingTrackNumber,
+            TagIssue::ZeroLength,
+        ]);
+
+        let song = Song {
+            path: PathBuf::from("/music/01.flac"),
+            length: Duration::from_secs(85),
+            title: "Caf\u{FFFD} ".to_string(),
+            artist: Some("Somebody".to_string()),
+            album: Some("Something".to_string()),
+            track: Some(1),
+            ..Default::default()
+        };
+
+        assert_eq!(tag_issues(&song), vec![TagIssue::InvalidEncoding, TagIssue::ExtraWhitespace]);
+    }
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/// A user-defined folder in the media library, grouping artists and albums regardless of their tags.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct VirtualFolder {
+    pub name: String,
+    #[serde(default)]
+    pub artists: Vec<String>,
+    #[serde(default)]
+    pub albums: Vec<VirtualFolderAlbum>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Ord, PartialOrd)]
+pub struct VirtualFolderAlbum {
+    pub artist: String,
+    pub album: String,
+}
+
+impl VirtualFolder {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            artists: vec![],
+            albums: vec![],
+        }
+    }
+
+    pub fn contains_artist(&self, artist: &str) -> bool {
+        self.artists.iter().any(|a| a == artist)
+    }
+
+    pub fn contains_album(&self, artist: &str, album: &str) -> bool {
+        self.albums.iter().any(|a| a.artist == artist && a.album == album)
+    }
+}
@@ -1,6 +1,7 @@
 use std::fmt::Formatter;
-use std::fs::{create_dir_all, read_to_string, write};
-use std::path::PathBuf;
+use std::fs::{self, create_dir_all, read_to_string};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
 use log::error;
 use serde::de::DeserializeOwned;
@@ -40,7 +41,7 @@ impl From<toml::ser::Error> for TomlFileError {
     }
 }
 
-fn get_config_dir_path() -> Result<PathBuf, TomlFileError> {
+pub fn get_config_dir_path() -> Result<PathBuf, TomlFileError> {
     // TODO: XDG_CONFIG_HOME
     home::home_dir()
         .map(|path| path.as_path().join(".config/jolteon"))
@@ -86,6 +87,19 @@ where
     create_dir()?;
     let path = get_config_file_path(file_name)?;
     let serialized = toml::to_string(file_contents)?;
-    write(path, serialized)?;
+    write_file_atomically(&path, serialized.as_bytes())?;
     Ok(())
 }
+
+/// Writes the file through a temporary one next to it, renamed over it once written, so a crash or a full disk halfway
+/// through leaves the previous contents in place, rather than a truncated file.
+pub fn write_file_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let temp_path = path.with_file_name(format!(".{file_name}.tmp"));
+
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+
+    fs::rename(&temp_path, path)
+}
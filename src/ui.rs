@@ -3,9 +3,11 @@ mod currently_playing;
 mod playlist;
 mod keyboard_handler;
 mod top_bar;
+mod lyrics;
 
 pub use help_tab::*;
 pub use currently_playing::*;
 pub use playlist::*;
 pub use keyboard_handler::{KeyboardHandler, KeyboardHandlerMut, KeyboardHandlerRef};
 pub use top_bar::TopBar;
+pub use lyrics::LyricsPanel;
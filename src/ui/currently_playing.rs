@@ -1,5 +1,6 @@
 use std::time::Duration;
 
+use chrono::{Local, TimeDelta};
 use log::error;
 use ratatui::{
     layout::{Constraint, Layout},
@@ -13,7 +14,7 @@ use crate::{
     structs::Song,
 };
 
-fn duration_to_string(total_time: Duration) -> String {
+pub fn duration_to_string(total_time: Duration) -> String {
     let hours = total_time.as_secs() / SECONDS_PER_HOUR;
     let minutes = (total_time.as_secs() % SECONDS_PER_HOUR) / SECONDS_PER_MINUTE;
     let seconds = total_time.as_secs() % SECONDS_PER_MINUTE;
@@ -31,6 +32,12 @@ fn duration_to_string(total_time: Duration) -> String {
     strings.join(":")
 }
 
+/// The time of day it'll be after the given duration, such as "2:32pm".
+pub fn wall_clock_after(duration: Duration) -> String {
+    let time = Local::now() + TimeDelta::from_std(duration).unwrap_or(TimeDelta::zero());
+    time.format("%-l:%M%P").to_string()
+}
+
 pub fn song_to_string(song: &Song) -> String {
     let title = song.title.clone();
 
@@ -47,6 +54,7 @@ pub struct CurrentlyPlaying {
     current_song_position: Duration,
     queue_total_time: Duration,
     queue_song_count: usize,
+    show_remaining_time: bool,
 }
 
 impl CurrentlyPlaying {
@@ -56,6 +64,7 @@ impl CurrentlyPlaying {
         current_song_position: Duration,
         queue_total_time: Duration,
         queue_song_count: usize,
+        show_remaining_time: bool,
     ) -> Self {
         Self {
             theme,
@@ -63,13 +72,14 @@ impl CurrentlyPlaying {
             current_song_position,
             queue_total_time,
             queue_song_count,
+            show_remaining_time,
         }
     }
 }
 
 impl Widget for CurrentlyPlaying {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let [area_top, area_bottom] = Layout::vertical([Constraint::Length(2), Constraint::Length(1)]).areas(area);
+        let [area_top, area_bottom] = Layout::vertical([Constraint::Min(2), Constraint::Length(1)]).areas(area);
 
         if let Some(ref current_song) = self.current_song {
             let playing_file = Block::default()
@@ -81,21 +91,36 @@ impl Widget for CurrentlyPlaying {
             playing_file.render(area_top, buf);
         }
 
+        let current_song_remaining = self
+            .current_song
+            .as_ref()
+            .map(|song| song.length.saturating_sub(self.current_song_position))
+            .unwrap_or(Duration::ZERO);
+
         let playing_song_label = self.current_song.as_ref().map(|song| {
-            format!(
-                "{time_played} / {current_song_length}",
-                time_played = duration_to_string(self.current_song_position),
-                current_song_length = duration_to_string(song.length),
-            )
+            if self.show_remaining_time {
+                format!(
+                    "-{time_remaining} / {current_song_length}",
+                    time_remaining = duration_to_string(current_song_remaining),
+                    current_song_length = duration_to_string(song.length),
+                )
+            } else {
+                format!(
+                    "{time_played} / {current_song_length}",
+                    time_played = duration_to_string(self.current_song_position),
+                    current_song_length = duration_to_string(song.length),
+                )
+            }
         });
 
         let songs = if self.queue_song_count == 1 { "song" } else { "songs" };
 
         let queue_label = if self.queue_song_count > 0 {
             Some(format!(
-                "{queue_items} {songs} / {total_time} in queue",
+                "{queue_items} {songs} / {total_time} in queue, ends at {end_time}",
                 total_time = duration_to_string(self.queue_total_time),
                 queue_items = self.queue_song_count,
+                end_time = wall_clock_after(self.queue_total_time + current_song_remaining),
             ))
         } else {
             None
@@ -22,13 +22,44 @@ impl<'a> HelpTab<'a> {
             config,
             header: vec!["Keys", "Commands"],
             items: vec![
-                vec!["Q", "Quit"],
-                vec!["P", "Play / Pause"],
-                vec!["G", "Skip Song"],
+                vec!["Ctrl+Q", "Quit"],
+                vec!["Ctrl+P", "Play / Pause"],
+                vec!["Ctrl+G", "Skip Song"],
+                vec!["Ctrl+D", "Toggle Auto-DJ"],
+                vec!["Alt+D", "Change Auto-DJ Strategy"],
+                vec!["Ctrl+T", "Toggle Elapsed / Remaining Time"],
+                vec!["Ctrl+L", "Show / Hide Lyrics"],
+                vec!["Ctrl+X", "Cancel Library Scan"],
                 vec!["A", "Add To Queue"],
-                vec!["R", "Remove From Queue"],
+                vec!["R", "Remove From Queue, Or Search Folder For Missing Files"],
+                vec!["S", "Shuffle Queue"],
+                vec!["Shift+S", "Shuffle Queue By Album"],
                 vec!["Enter", "Enter Directory"],
                 vec!["Backspace", "Previous Directory"],
+                vec!["V", "Browse Library By Artist / Genre / Year"],
+                vec!["Ctrl+N", "New Library Folder"],
+                vec!["Ctrl+R", "Rename Library Folder"],
+                vec!["M", "Move To Next Library Folder"],
+                vec!["Space", "Collapse / Expand Album"],
+                vec!["Alt+Up / Alt+Down", "Previous / Next Album"],
+                vec!["G", "Accept Artist / Album Guessed From Folders, Or Go To Search Result"],
+                vec!["E", "Edit Metadata, Or Smart Playlist Rules"],
+                vec!["Shift+E", "Edit Album Metadata"],
+                vec!["F", "Toggle Favorite"],
+                vec!["O", "Sort Songs By Album / Title / Track / Year / Length / Added / Plays / Last Played / Skips / Favorites"],
+                vec!["Shift+O", "Reverse Sort Order"],
+                vec!["H", "Hide Songs / Album / Artist From Library, Or Hide / Unhide Duplicate"],
+                vec!["Delete", "Remove Songs / Album / Artist / Duplicate From Library, Delete Folder, Or Forget Recently Removed Songs"],
+                vec!["U", "Restore Recently Removed Songs"],
+                vec!["C", "Compare Duplicates By Audio"],
+                vec!["K", "Keep Duplicate, Hiding The Other Copies"],
+                vec!["Shift+R", "Relink Missing Files Found"],
+                vec!["Y", "Import Playlist File"],
+                vec!["Ctrl+E", "Export Playlist / Queue, With Relative Paths"],
+                vec!["Alt+E", "Export Playlist / Queue, With Absolute Paths"],
+                vec!["Alt+N", "New Smart Playlist"],
+                vec!["4", "Search Library And Playlists"],
+                vec!["/", "Edit Search"],
                 vec!["Down", "Next Item"],
                 vec!["Up", "Previous Item"],
                 vec!["Right / Left", "Enter Queue / Browser"],
@@ -216,3 +247,20 @@ impl Drop for HelpTab<'_> {
         log::trace!("HelpTab.drop()");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn lists_every_key_once() {
+        let help_tab = HelpTab::new(Config::default());
+        let mut keys = HashSet::new();
+
+        for item in &help_tab.items {
+            assert!(keys.insert(item[0]), "{} is listed more than once", item[0]);
+        }
+    }
+}
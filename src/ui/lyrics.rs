@@ -0,0 +1,154 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        mpsc::{channel, Sender},
+        Arc,
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders},
+};
+
+use crate::{
+    config::Theme,
+    structs::{Lyrics, Song},
+};
+
+/// The lyrics of a file, as far as they've been read.
+enum LoadedLyrics {
+    Loading,
+    Loaded(Option<Lyrics>),
+}
+
+/// The lyrics of the song playing, next to the active tab. Synced lyrics follow the song, highlighting the line
+/// being sung, and plain lyrics scroll along with it.
+pub struct LyricsPanel {
+    theme: Theme,
+    /// The lyrics of the last file shown, so they're read once per song, rather than once per frame.
+    /// Tracks of a cue sheet share them.
+    loaded: Arc<Mutex<Option<(PathBuf, LoadedLyrics)>>>,
+    /// Reading lyrics means reading every tag of the file, embedded art included, so it's done in the background.
+    loader: Sender<PathBuf>,
+}
+
+impl LyricsPanel {
+    pub fn new(theme: Theme) -> Self {
+        let loaded: Arc<Mutex<Option<(PathBuf, LoadedLyrics)>>> = Arc::new(Mutex::new(None));
+        let (loader, requests) = channel::<PathBuf>();
+
+        let spawned = thread::Builder::new().name("lyrics".to_string()).spawn({
+            let loaded = loaded.clone();
+            move || {
+                while let Ok(path) = requests.recv() {
+                    // Songs skipped past while their lyrics were read aren't shown anymore
+                    let path = requests.try_iter().last().unwrap_or(path);
+                    let lyrics = Lyrics::from_audio_file(&path);
+
+                    if let Some((shown, state)) = loaded.lock().unwrap().as_mut() {
+                        if *shown == path {
+                            *state = LoadedLyrics::Loaded(lyrics);
+                        }
+                    }
+                }
+            }
+        });
+
+        if let Err(err) = spawned {
+            log::error!("Could not spawn the lyrics thread: {err}");
+        }
+
+        Self {
+            theme,
+            loaded,
+            loader,
+        }
+    }
+
+    /// Renders the lyrics of the song, at the position the player is at in it.
+    pub fn render(&self, song: Option<&Song>, position: Duration, area: Rect, buf: &mut Buffer) {
+        let block = Block::default()
+            .borders(Borders::TOP)
+            .title(" Lyrics ")
+            .title_alignment(Alignment::Center)
+            .style(Style::default().fg(self.theme.foreground).bg(self.theme.background));
+        let area_lines = block.inner(area);
+        block.render(area, buf);
+
+        let Some(song) = song else {
+            self.render_message("Nothing playing", area_lines, buf);
+            return;
+        };
+
+        let mut loaded = self.loaded.lock().unwrap();
+
+        if loaded.as_ref().is_none_or(|(path, _)| *path != song.path) {
+            *loaded = Some((song.path.clone(), LoadedLyrics::Loading));
+
+            if self.loader.send(song.path.clone()).is_err() {
+                log::error!("The lyrics thread is gone");
+            }
+        }
+
+        let lyrics = match loaded.as_ref() {
+            Some((_, LoadedLyrics::Loaded(Some(lyrics)))) => lyrics,
+            Some((_, LoadedLyrics::Loading)) => {
+                self.render_message("Loading…", area_lines, buf);
+                return;
+            }
+            _ => {
+                self.render_message("No lyrics found", area_lines, buf);
+                return;
+            }
+        };
+
+        // Lyrics of a file split by a cue sheet are timed from the start of the file, rather than the track
+        let position = song.start_time + position;
+        let lines = lyrics.lines();
+        let height = area_lines.height as usize;
+        let max_offset = lines.len().saturating_sub(height);
+        let current_line = lyrics.current_line(position);
+
+        // The line being sung stays a third of the way down, and plain lyrics scroll as the song goes
+        let offset = match (lyrics, current_line) {
+            (Lyrics::Synced(_), Some(i)) => i.saturating_sub(height / 3),
+            (Lyrics::Synced(_), None) => 0,
+            (Lyrics::Unsynced(_), _) => {
+                let end = song.start_time + song.length;
+                let progress = match end.as_secs_f64() {
+                    0.0 => 0.0,
+                    end => (position.as_secs_f64() / end).clamp(0.0, 1.0),
+                };
+                (max_offset as f64 * progress) as usize
+            }
+        }
+        .min(max_offset);
+
+        for (i, line) in lines.iter().enumerate().skip(offset).take(height) {
+            let style = if Some(i) == current_line {
+                Style::default().fg(self.theme.foreground).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(self.theme.foreground_secondary)
+            };
+
+            let area = Rect {
+                y: area_lines.y + (i - offset) as u16,
+                height: 1,
+                ..area_lines
+            };
+
+            Line::from(*line).style(style).alignment(Alignment::Center).render(area, buf);
+        }
+    }
+
+    fn render_message(&self, message: &str, area: Rect, buf: &mut Buffer) {
+        Line::from(message)
+            .style(Style::default().fg(self.theme.foreground_secondary))
+            .alignment(Alignment::Center)
+            .render(Rect { height: 1.min(area.height), ..area }, buf);
+    }
+}
@@ -1,7 +1,10 @@
 use std::{
+    collections::BTreeMap,
+    path::PathBuf,
     time::{SystemTime, UNIX_EPOCH},
     sync::{
         atomic::{AtomicUsize, AtomicBool, Ordering},
+        Arc,
         Mutex,
     },
 };
@@ -17,8 +20,9 @@ use ratatui::{
 };
 
 use crate::{
-    structs::{Song, Playlist},
+    structs::{Song, Playlist, SmartPlaylist, SongSort},
     config::Theme,
+    components::Library,
     cue::CueSheet,
     ui::{song_to_string, KeyboardHandlerRef},
 };
@@ -29,18 +33,62 @@ enum PlaylistScreenElement {
     SongList,
 }
 
+/// The rules of a smart playlist, as they're being edited.
+struct RulesEdit {
+    query: String,
+    error: Option<String>,
+}
+
+/// How many songs of the selected playlist are missing, as of the given versions of the playlists and the library.
+struct MissingCount {
+    playlist_index: usize,
+    version: usize,
+    revision: usize,
+    count: usize,
+}
+
+/// The songs of the selected playlist in the given order, as of the given versions of the playlists and the library.
+struct SortedSongs {
+    playlist_index: usize,
+    version: usize,
+    revision: usize,
+    sort: SongSort,
+    songs: Vec<Song>,
+}
+
+/// The songs of a smart playlist, as of the given revision of the library.
+struct SmartSongs {
+    playlist: SmartPlaylist,
+    revision: usize,
+    songs: Vec<Song>,
+}
+
 pub struct Playlists<'a> {
     playlists: Mutex<Vec<Playlist>>,
+    /// Listed after the regular playlists, and selected by the same index.
+    smart_playlists: Mutex<Vec<SmartPlaylist>>,
+    smart_songs: Mutex<Option<SmartSongs>>,
+    missing_count: Mutex<Option<MissingCount>>,
+    sorted_songs: Mutex<Option<SortedSongs>>,
+    /// How the songs of every playlist are shown. Playlists keep their own order, which is shown when sorted by
+    /// [`crate::structs::SongSortKey::Custom`].
+    sort: Mutex<SongSort>,
+    rules_edit: Mutex<Option<RulesEdit>>,
+    library: Arc<Library<'a>>,
     theme: Theme,
     focused_element: Mutex<PlaylistScreenElement>,
     selected_playlist_index: AtomicUsize,
     selected_song_index: AtomicUsize,
     renaming: AtomicBool,
+    /// Whether the regular playlists changed since they were last saved.
+    changed: AtomicBool,
+    /// Bumped whenever the playlists change, so views derived from them know when to refresh.
+    version: AtomicUsize,
     on_select_fn: Mutex<Box<dyn FnMut((Song, KeyEvent)) + 'a>>,
 }
 
 impl<'a> Playlists<'a> {
-    pub fn new(theme: Theme, playlists: Vec<Playlist>) -> Self {
+    pub fn new(theme: Theme, playlists: Vec<Playlist>, smart_playlists: Vec<SmartPlaylist>, library: Arc<Library<'a>>) -> Self {
         Self {
             // playlists: Mutex::new(vec![
             //     Playlist::new("My first Jolteon playlist".to_string()),
@@ -48,11 +96,20 @@ impl<'a> Playlists<'a> {
             //     Playlist::new("Alt+N to rename".to_string()),
             // ]),
             playlists: Mutex::new(playlists),
+            smart_playlists: Mutex::new(smart_playlists),
+            smart_songs: Mutex::new(None),
+            missing_count: Mutex::new(None),
+            sorted_songs: Mutex::new(None),
+            sort: Mutex::new(SongSort::default()),
+            rules_edit: Mutex::new(None),
+            library,
             selected_playlist_index: AtomicUsize::new(0),
             selected_song_index: AtomicUsize::new(0),
             theme,
             focused_element: Mutex::new(PlaylistScreenElement::PlaylistList),
             renaming: AtomicBool::new(false),
+            changed: AtomicBool::new(false),
+            version: AtomicUsize::new(0),
             on_select_fn: Mutex::new(Box::new(|_| {}) as _),
         }
     }
@@ -61,37 +118,215 @@ impl<'a> Playlists<'a> {
         *self.on_select_fn.lock().unwrap() = Box::new(cb);
     }
 
+    pub fn playlist_names(&self) -> Vec<String> {
+        self.playlists.lock().unwrap().iter().map(|pl| pl.name.clone()).collect()
+    }
+
     pub fn playlists(&self) -> Vec<Playlist> {
         let playlists = self.playlists.lock().unwrap();
         playlists.clone()
     }
 
+    /// Replaces the regular playlists, such as with the ones edited by other programs, without counting it as a change.
+    pub fn set_playlists(&self, playlists: Vec<Playlist>) {
+        let len = playlists.len() + self.smart_playlists.lock().unwrap().len();
+        *self.playlists.lock().unwrap() = playlists;
+        self.version.fetch_add(1, Ordering::Relaxed);
+
+        let _ = self.selected_playlist_index.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |i| Some(i.min(len.saturating_sub(1))));
+        self.selected_song_index.store(0, Ordering::Relaxed);
+    }
+
+    /// Whether the regular playlists changed since the last time it was asked, so they can be saved.
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::Relaxed)
+    }
+
+    /// Counts a change to the regular playlists, so they're saved, and the views of their songs refreshed.
+    fn mark_changed(&self) {
+        self.changed.store(true, Ordering::Relaxed);
+        self.version.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn smart_playlists(&self) -> Vec<SmartPlaylist> {
+        self.smart_playlists.lock().unwrap().clone()
+    }
+
+    pub fn sort(&self) -> SongSort {
+        *self.sort.lock().unwrap()
+    }
+
+    pub fn set_sort(&self, sort: SongSort) {
+        *self.sort.lock().unwrap() = sort;
+        self.selected_song_index.store(0, Ordering::Relaxed);
+    }
+
+    /// Whether a playlist is being renamed, or the rules of a smart playlist edited, so keys are meant as text.
+    pub fn is_editing(&self) -> bool {
+        self.renaming.load(Ordering::Relaxed) || self.rules_edit.lock().unwrap().is_some()
+    }
+
+    pub fn create_smart_playlist(&self) {
+        let mut smart_playlists = self.smart_playlists.lock().unwrap();
+        smart_playlists.push(SmartPlaylist::new("New smart playlist".to_string()));
+        self.version.fetch_add(1, Ordering::Relaxed);
+
+        let index = self.playlists.lock().unwrap().len() + smart_playlists.len() - 1;
+        drop(smart_playlists);
+
+        self.selected_playlist_index.store(index, Ordering::Relaxed);
+        self.selected_song_index.store(0, Ordering::Relaxed);
+        self.start_rules_edit();
+    }
+
+    /// The index of the selected smart playlist in the list of smart playlists, if a smart playlist is selected.
+    fn selected_smart_playlist_index(&self) -> Option<usize> {
+        let selected_playlist_index = self.selected_playlist_index.load(Ordering::Relaxed);
+        let playlist_count = self.playlists.lock().unwrap().len();
+
+        selected_playlist_index
+            .checked_sub(playlist_count)
+            .filter(|i| *i < self.smart_playlists.lock().unwrap().len())
+    }
+
+    fn start_rules_edit(&self) {
+        let Some(i) = self.selected_smart_playlist_index() else {
+            return;
+        };
+
+        let query = self.smart_playlists.lock().unwrap()[i].to_query();
+        *self.rules_edit.lock().unwrap() = Some(RulesEdit { query, error: None });
+    }
+
+    /// Replaces the rules of the selected smart playlist with the edited ones, unless they're invalid.
+    fn save_rules_edit(&self) {
+        let mut rules_edit = self.rules_edit.lock().unwrap();
+        let (Some(edit), Some(i)) = (rules_edit.as_mut(), self.selected_smart_playlist_index()) else {
+            return;
+        };
+
+        match self.smart_playlists.lock().unwrap()[i].set_query(&edit.query) {
+            Ok(()) => {
+                *rules_edit = None;
+                self.version.fetch_add(1, Ordering::Relaxed);
+                self.selected_song_index.store(0, Ordering::Relaxed);
+            }
+            Err(err) => edit.error = Some(err),
+        }
+    }
+
+    /// Calls `f` with the songs of the selected playlist, sorted as the playlists are. They're sorted again only when
+    /// the playlists, the library or the sort changed.
+    fn with_selected_songs<T>(&self, f: impl FnOnce(&[Song]) -> T) -> T {
+        let sort = self.sort();
+
+        if sort == SongSort::default() {
+            return self.with_selected_playlist_songs(f);
+        }
+
+        let playlist_index = self.selected_playlist_index.load(Ordering::Relaxed);
+        let version = self.version.load(Ordering::Relaxed);
+        let revision = self.library.revision();
+        let mut sorted_songs = self.sorted_songs.lock().unwrap();
+
+        let is_stale = sorted_songs.as_ref().is_none_or(|s| {
+            s.playlist_index != playlist_index || s.version != version || s.revision != revision || s.sort != sort
+        });
+
+        if is_stale {
+            let songs = self.with_selected_playlist_songs(|songs| sort.sort(songs, &self.library.song_stats(songs)));
+            *sorted_songs = Some(SortedSongs { playlist_index, version, revision, sort, songs });
+        }
+
+        f(sorted_songs.as_ref().map(|s| s.songs.as_slice()).unwrap_or_default())
+    }
+
+    /// Calls `f` with the songs of the selected playlist, in its own order. The songs of smart playlists are picked
+    /// from the library again whenever it or the playlist changed.
+    fn with_selected_playlist_songs<T>(&self, f: impl FnOnce(&[Song]) -> T) -> T {
+        let Some(i) = self.selected_smart_playlist_index() else {
+            let selected_playlist_index = self.selected_playlist_index.load(Ordering::Relaxed);
+            let playlists = self.playlists.lock().unwrap();
+            return f(playlists.get(selected_playlist_index).map(|pl| pl.songs.as_slice()).unwrap_or_default());
+        };
+
+        let playlist = self.smart_playlists.lock().unwrap()[i].clone();
+        let revision = self.library.revision();
+        let mut smart_songs = self.smart_songs.lock().unwrap();
+
+        let is_stale = smart_songs.as_ref().is_none_or(|s| s.playlist != playlist || s.revision != revision);
+
+        if is_stale {
+            let songs = self.library.smart_playlist_songs(&playlist);
+            *smart_songs = Some(SmartSongs { playlist, revision, songs });
+        }
+
+        f(smart_songs.as_ref().map(|s| s.songs.as_slice()).unwrap_or_default())
+    }
+
+    /// How many of the songs of the selected playlist are missing, counted again only when the playlists or the library
+    /// changed.
+    fn selected_missing_count(&self, songs: &[Song]) -> usize {
+        let playlist_index = self.selected_playlist_index.load(Ordering::Relaxed);
+        let version = self.version.load(Ordering::Relaxed);
+        let revision = self.library.revision();
+        let mut missing_count = self.missing_count.lock().unwrap();
+
+        match missing_count.as_ref() {
+            Some(m) if m.playlist_index == playlist_index && m.version == version && m.revision == revision => m.count,
+            _ => {
+                let count = songs.iter().filter(|s| self.library.is_missing(s)).count();
+                *missing_count = Some(MissingCount { playlist_index, version, revision, count });
+                count
+            }
+        }
+    }
+
+    /// Renames the selected playlist, be it a regular or a smart one.
+    fn rename_selected_playlist(&self, f: impl FnOnce(&mut String)) {
+        if let Some(i) = self.selected_smart_playlist_index() {
+            f(&mut self.smart_playlists.lock().unwrap()[i].name);
+        } else {
+            self.selected_playlist_mut(|pl| f(&mut pl.name));
+        }
+    }
+
     pub fn create_playlist(&self) {
         let playlist = Playlist {
             name: format!("New playlist created at {}", Local::now().format("%A %-l:%M:%S%P").to_string()),
             songs: vec![],
         };
         self.playlists.lock().unwrap().push(playlist);
+        self.mark_changed();
     }
 
-    pub fn selected_playlist<T>(&self, f: impl FnOnce(&Playlist) -> T) -> Option<T> {
+    pub fn selected_playlist_mut(&self, f: impl FnOnce(&mut Playlist)) {
         let selected_playlist_index = self.selected_playlist_index.load(Ordering::Relaxed);
         let mut playlists = self.playlists.lock().unwrap();
 
         if let Some(selected_playlist) = playlists.get_mut(selected_playlist_index) {
-            Some(f(selected_playlist))
-        } else {
-            None
+            f(selected_playlist);
+            self.mark_changed();
         }
     }
 
-    pub fn selected_playlist_mut(&self, f: impl FnOnce(&mut Playlist)) {
-        let selected_playlist_index = self.selected_playlist_index.load(Ordering::Relaxed);
-        let mut playlists = self.playlists.lock().unwrap();
+    /// Selects the given song in the playlist with the given name.
+    pub fn select_song(&self, playlist_name: &str, song: &Song) {
+        let playlists = self.playlists.lock().unwrap();
 
-        if let Some(selected_playlist) = playlists.get_mut(selected_playlist_index) {
-            f(selected_playlist);
-        }
+        let Some(playlist_index) = playlists.iter().position(|pl| pl.name == playlist_name) else {
+            return;
+        };
+
+        drop(playlists);
+        self.selected_playlist_index.store(playlist_index, Ordering::Relaxed);
+
+        let song_index = self.with_selected_songs(|songs| {
+            songs.iter().position(|s| s.path == song.path && s.start_time == song.start_time).unwrap_or(0)
+        });
+
+        self.selected_song_index.store(song_index, Ordering::Relaxed);
+        *self.focused_element.lock().unwrap() = PlaylistScreenElement::SongList;
     }
 
     pub fn add_song(&self, song: Song) {
@@ -106,6 +341,39 @@ impl<'a> Playlists<'a> {
             pl.songs.append(&mut songs);
         });
     }
+
+    /// Adds the playlist after the others, and selects it.
+    pub fn import(&self, playlist: Playlist) {
+        let mut playlists = self.playlists.lock().unwrap();
+        log::info!("importing playlist {:?} with {} songs", playlist.name, playlist.songs.len());
+
+        playlists.push(playlist);
+        self.selected_playlist_index.store(playlists.len() - 1, Ordering::Relaxed);
+        self.selected_song_index.store(0, Ordering::Relaxed);
+        self.mark_changed();
+    }
+
+    /// The selected playlist, in its own order. For smart playlists, the songs currently meeting their rules.
+    pub fn selected_playlist(&self) -> Option<Playlist> {
+        if let Some(i) = self.selected_smart_playlist_index() {
+            let playlist = self.smart_playlists.lock().unwrap()[i].clone();
+            let songs = self.library.smart_playlist_songs(&playlist);
+            return Some(Playlist { name: playlist.name, songs });
+        }
+
+        let selected_playlist_index = self.selected_playlist_index.load(Ordering::Relaxed);
+        self.playlists.lock().unwrap().get(selected_playlist_index).cloned()
+    }
+
+    /// Points the songs of every playlist whose files moved to their new paths.
+    pub fn relink(&self, new_paths: &BTreeMap<PathBuf, PathBuf>) {
+        for song in self.playlists.lock().unwrap().iter_mut().flat_map(|pl| pl.songs.iter_mut()) {
+            if let Some(new_path) = new_paths.get(&song.path) {
+                song.path = new_path.clone();
+                self.mark_changed();
+            }
+        }
+    }
 }
 
 impl<'a> Widget for Playlists<'a> {
@@ -116,6 +384,20 @@ impl<'a> Widget for Playlists<'a> {
 
 impl<'a> WidgetRef for Playlists<'a> {
     fn render_ref(&self, area: Rect, buf: &mut Buffer) {
+        let area = if self.rules_edit.lock().unwrap().is_some() {
+            let [area, _, area_edit] = Layout::vertical([
+                Constraint::Min(1),
+                Constraint::Length(1),
+                Constraint::Length(2),
+            ])
+                .areas(area);
+
+            self.render_ref_rules_edit(area_edit, buf);
+            area
+        } else {
+            area
+        };
+
         let [area_left, _, area_right] = Layout::horizontal([
             Constraint::Percentage(50),
             Constraint::Length(5),
@@ -125,8 +407,27 @@ impl<'a> WidgetRef for Playlists<'a> {
             .areas(area);
 
         let playlists = self.playlists.lock().unwrap();
-
-        if playlists.len() < 1 {
+        let smart_playlists = self.smart_playlists.lock().unwrap();
+
+        // Smart playlists are told apart from regular ones with a star
+        let names: Vec<String> = playlists
+            .iter()
+            .map(|pl| pl.name.clone())
+            .chain(smart_playlists.iter().map(|pl| format!("✦ {}", pl.name)))
+            .collect();
+
+        // Worked out from the guards held, as `selected_smart_playlist_index` would lock them again
+        let smart_query = self
+            .selected_playlist_index
+            .load(Ordering::Relaxed)
+            .checked_sub(playlists.len())
+            .and_then(|i| smart_playlists.get(i))
+            .map(|pl| pl.to_query());
+
+        drop(playlists);
+        drop(smart_playlists);
+
+        if names.len() < 1 {
             return;
         }
 
@@ -135,8 +436,7 @@ impl<'a> WidgetRef for Playlists<'a> {
         let focused_element = self.focused_element.lock().unwrap();
         let is_renaming = self.renaming.load(Ordering::Relaxed);
 
-        for i in 0..playlists.len() {
-            let playlist = &playlists[i];
+        for (i, name) in names.iter().enumerate() {
             let area = Rect {
                 y: area_left.y + i as u16,
                 height: 1,
@@ -164,9 +464,9 @@ impl<'a> WidgetRef for Playlists<'a> {
                 } else {
                     ' '
                 };
-                format!("{}{}", playlist.name, caret)
+                format!("{}{}", name, caret)
             } else {
-                playlist.name.clone()
+                name.clone()
             };
 
             let line = ratatui::text::Line::from(line).style(style);
@@ -174,40 +474,107 @@ impl<'a> WidgetRef for Playlists<'a> {
             line.render_ref(area, buf);
         }
 
-        if selected_playlist_index >= playlists.len() {
+        if selected_playlist_index >= names.len() {
             log::error!("selected_playlist_index >= playlists.len()");
             return;
         }
 
-        let selected_playlist = &playlists[selected_playlist_index];
+        // Smart playlists show their rules above their songs
+        let area_right = match smart_query {
+            Some(query) if area_right.height > 1 => {
+                let query = if query.is_empty() { "(no rules, E to edit)".to_string() } else { query };
 
-        for i in 0..selected_playlist.songs.len() {
-            let song = &selected_playlist.songs[i];
-            let area = Rect {
-                y: area_right.y + i as u16,
-                height: 1,
-                ..area_right
-            };
+                ratatui::text::Line::from(query)
+                    .style(Style::default().fg(self.theme.foreground).bg(self.theme.background))
+                    .render_ref(Rect { height: 1, ..area_right }, buf);
 
-            let style = if i == selected_song {
-                if *focused_element == PlaylistScreenElement::SongList {
-                    Style::default().fg(self.theme.foreground_selected).bg(self.theme.background_selected)
-                } else {
-                    Style::default().fg(self.theme.foreground_selected).bg(self.theme.background_selected_blur)
-                }
+                Rect { y: area_right.y + 1, height: area_right.height - 1, ..area_right }
+            }
+            _ => area_right,
+        };
+
+        let sort = self.sort();
+        let area_right = if sort != SongSort::default() && area_right.height > 1 {
+            ratatui::text::Line::from(format!("Sorted by {}", sort.name()))
+                .style(Style::default().fg(self.theme.foreground).bg(self.theme.background))
+                .render_ref(Rect { height: 1, ..area_right }, buf);
+
+            Rect { y: area_right.y + 1, height: area_right.height - 1, ..area_right }
+        } else {
+            area_right
+        };
+
+        self.with_selected_songs(|songs| {
+            // Songs whose files weren't found, such as the ones of imported playlists, can be relinked from the library
+            let missing = self.selected_missing_count(songs);
+            let area_right = if missing > 0 && area_right.height > 1 {
+                ratatui::text::Line::from(format!("{missing} songs not found, see Missing Files in the library"))
+                    .style(Style::default().fg(Color::DarkGray).bg(self.theme.background))
+                    .render_ref(Rect { height: 1, ..area_right }, buf);
+
+                Rect { y: area_right.y + 1, height: area_right.height - 1, ..area_right }
             } else {
-                Style::default().fg(self.theme.foreground_secondary).bg(self.theme.background)
+                area_right
             };
 
-            let line = ratatui::text::Line::from(song_to_string(song)).style(style);
-            line.render_ref(area, buf);
-        }
+            for (i, song) in songs.iter().enumerate().take(area_right.height as usize) {
+                let area = Rect {
+                    y: area_right.y + i as u16,
+                    height: 1,
+                    ..area_right
+                };
+
+                let style = if i == selected_song {
+                    if *focused_element == PlaylistScreenElement::SongList {
+                        Style::default().fg(self.theme.foreground_selected).bg(self.theme.background_selected)
+                    } else {
+                        Style::default().fg(self.theme.foreground_selected).bg(self.theme.background_selected_blur)
+                    }
+                } else if self.library.is_missing(song) {
+                    Style::default().fg(Color::DarkGray).bg(self.theme.background)
+                } else {
+                    Style::default().fg(self.theme.foreground_secondary).bg(self.theme.background)
+                };
+
+                let line = ratatui::text::Line::from(song_to_string(song)).style(style);
+                line.render_ref(area, buf);
+            }
+        });
+    }
+}
+
+impl<'a> Playlists<'a> {
+    fn render_ref_rules_edit(&self, area: Rect, buf: &mut Buffer) {
+        let rules_edit = self.rules_edit.lock().unwrap();
+        let Some(edit) = rules_edit.as_ref() else {
+            return;
+        };
+
+        let area = area.inner(ratatui::layout::Margin { horizontal: 2, vertical: 0 });
+
+        let hint = match &edit.error {
+            Some(error) => error.clone(),
+            None => "artist: album: genre: title: year: plays: played: added: length: favorite any sort: limit:".to_string(),
+        };
+
+        ratatui::text::Line::from(hint)
+            .style(Style::default().fg(self.theme.foreground_secondary))
+            .render_ref(Rect { height: 1, ..area }, buf);
+
+        ratatui::text::Line::from(format!("Rules: {}⎸", edit.query))
+            .style(Style::default().fg(self.theme.foreground_selected).bg(self.theme.search))
+            .render_ref(Rect { y: area.y + 1, height: 1, ..area }, buf);
     }
 }
 
 impl<'a> KeyboardHandlerRef<'a> for Playlists<'a> {
 
     fn on_key(&self, key: KeyEvent) -> bool {
+        if self.rules_edit.lock().unwrap().is_some() {
+            on_key_event_rules_edit(self, key);
+            return true;
+        }
+
         let mut focused_element_guard = self.focused_element.lock().unwrap();
 
         match key.code {
@@ -234,7 +601,8 @@ impl<'a> KeyboardHandlerRef<'a> for Playlists<'a> {
 
 
 fn on_key_event_playlist_list(s: &Playlists, key: KeyEvent) {
-    let len = s.playlists.lock().unwrap().len();
+    let playlist_count = s.playlists.lock().unwrap().len();
+    let len = playlist_count + s.smart_playlists.lock().unwrap().len();
     let is_renaming = s.renaming.load(Ordering::Relaxed);
 
     if !is_renaming {
@@ -253,20 +621,31 @@ fn on_key_event_playlist_list(s: &Playlists, key: KeyEvent) {
             },
             KeyCode::Char('n') if key.modifiers == KeyModifiers::CONTROL => {
                 s.create_playlist();
-                let _ = s.selected_playlist_index.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |a| { Some(a.saturating_add(1).min(len)) });
+                let _ = s.selected_playlist_index.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |a| { Some(a.saturating_add(1).min(playlist_count)) });
+            }
+            KeyCode::Char('n') if key.modifiers == KeyModifiers::ALT => {
+                s.create_smart_playlist();
+            }
+            KeyCode::Char('e') => {
+                s.start_rules_edit();
             }
             KeyCode::Char('r') if key.modifiers == KeyModifiers::CONTROL => {
                 s.renaming.store(true, Ordering::Relaxed);
             }
             KeyCode::Delete => {
                 let selected_playlist_index = s.selected_playlist_index.load(Ordering::Relaxed);
-                let mut playlists = s.playlists.lock().unwrap();
 
-                if playlists.len() > 0 {
-                    playlists.remove(selected_playlist_index);
-                    if selected_playlist_index > playlists.len().saturating_sub(1) {
-                        s.selected_playlist_index.store(playlists.len().saturating_sub(1), Ordering::Relaxed);
-                    }
+                if let Some(i) = s.selected_smart_playlist_index() {
+                    s.smart_playlists.lock().unwrap().remove(i);
+                    s.version.fetch_add(1, Ordering::Relaxed);
+                } else if selected_playlist_index < playlist_count {
+                    s.playlists.lock().unwrap().remove(selected_playlist_index);
+                    s.mark_changed();
+                }
+
+                let remaining = s.playlists.lock().unwrap().len() + s.smart_playlists.lock().unwrap().len();
+                if selected_playlist_index > remaining.saturating_sub(1) {
+                    s.selected_playlist_index.store(remaining.saturating_sub(1), Ordering::Relaxed);
                 }
             }
             _ => {},
@@ -274,18 +653,18 @@ fn on_key_event_playlist_list(s: &Playlists, key: KeyEvent) {
     } else {
         match key.code {
             KeyCode::Char(char) => {
-                s.selected_playlist_mut(move |pl| {
-                    if pl.name.len() < 60 {
-                        pl.name.push(char);
+                s.rename_selected_playlist(move |name| {
+                    if name.len() < 60 {
+                        name.push(char);
                     }
                 });
             }
             KeyCode::Backspace => {
-                s.selected_playlist_mut(move |pl| {
+                s.rename_selected_playlist(move |name| {
                     if key.modifiers == KeyModifiers::ALT {
-                        pl.name.clear();
+                        name.clear();
                     } else {
-                        pl.name.pop();
+                        name.pop();
                     }
                 });
             }
@@ -300,8 +679,42 @@ fn on_key_event_playlist_list(s: &Playlists, key: KeyEvent) {
     }
 }
 
+fn on_key_event_rules_edit(s: &Playlists, key: KeyEvent) {
+    let mut rules_edit = s.rules_edit.lock().unwrap();
+    let Some(edit) = rules_edit.as_mut() else {
+        return;
+    };
+
+    match key.code {
+        KeyCode::Char(char) => {
+            if edit.query.len() < 500 {
+                edit.query.push(char);
+            }
+        }
+        KeyCode::Backspace => {
+            if key.modifiers == KeyModifiers::ALT {
+                edit.query.clear();
+            } else {
+                edit.query.pop();
+            }
+        }
+        KeyCode::Esc => {
+            *rules_edit = None;
+        }
+        KeyCode::Enter => {
+            drop(rules_edit);
+            s.save_rules_edit();
+        }
+        _ => {},
+    }
+}
+
 fn on_key_event_song_list(s: &Playlists, key: KeyEvent) {
-    let Some(len) = s.selected_playlist(|pl| pl.songs.len()) else { return };
+    let len = s.with_selected_songs(|songs| songs.len());
+
+    if len == 0 {
+        return;
+    }
 
     match key.code {
         KeyCode::Up if key.modifiers == KeyModifiers::NONE => {
@@ -310,6 +723,14 @@ fn on_key_event_song_list(s: &Playlists, key: KeyEvent) {
         KeyCode::Down if key.modifiers == KeyModifiers::NONE => {
             let _ = s.selected_song_index.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |a| { Some(a.saturating_add(1).min(len.saturating_sub(1))) });
         },
+        KeyCode::Char('o') => {
+            s.set_sort(s.sort().next(true));
+        },
+        KeyCode::Char('O') => {
+            s.set_sort(s.sort().reversed());
+        },
+        // Songs are only moved within the playlist's own order
+        KeyCode::Up | KeyCode::Down if key.modifiers == KeyModifiers::ALT && s.sort() != SongSort::default() => {},
         KeyCode::Up if key.modifiers == KeyModifiers::ALT => {
             let selected_song = s.selected_song_index.load(Ordering::Relaxed);
             s.selected_playlist_mut(|pl| {
@@ -329,16 +750,20 @@ fn on_key_event_song_list(s: &Playlists, key: KeyEvent) {
             });
         },
         KeyCode::Enter | KeyCode::Char(_) => {
-            let selected_song = s.selected_playlist(|pl| pl.songs[s.selected_song_index.load(Ordering::Relaxed)].clone());
+            let selected_song = s.with_selected_songs(|songs| songs.get(s.selected_song_index.load(Ordering::Relaxed)).cloned());
             if let Some(song) = selected_song {
                 s.on_select_fn.lock().unwrap()((song, key));
             }
         },
         KeyCode::Delete => {
             let selected_song = s.selected_song_index.load(Ordering::Relaxed);
+            let song = s.with_selected_songs(|songs| songs.get(selected_song).cloned());
             s.selected_playlist_mut(|pl| {
-                if pl.songs.len() > 0 {
-                    pl.songs.remove(selected_song);
+                // The song shown at the selected index isn't the one at that index of the playlist, when it's sorted
+                let i = song.and_then(|song| pl.songs.iter().position(|s| s.path == song.path && s.start_time == song.start_time));
+
+                if let Some(i) = i {
+                    pl.songs.remove(i);
                     if selected_song >= pl.songs.len() {
                         s.selected_song_index.store(selected_song.saturating_sub(1), Ordering::Relaxed);
                     }
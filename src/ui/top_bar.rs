@@ -8,7 +8,9 @@ use ratatui::{
 
 use crate::{
     app::AppTab,
+    config::AutoDjStrategy,
     constants::MAIN_SECTIONS,
+    library_scanner::ScanProgress,
 };
 
 // static TIME_FORMAT: &str = "%A %-l:%M%P, %B %-e | %F";
@@ -30,14 +32,26 @@ fn time_format() -> String {
 
 pub struct TopBar {
     theme: crate::config::Theme,
-    active_tab: AppTab
+    active_tab: AppTab,
+    auto_dj_strategy: Option<AutoDjStrategy>,
+    scan_progress: Option<ScanProgress>,
+    notice: Option<String>,
 }
 
 impl TopBar {
-    pub fn new(theme: crate::config::Theme, active_tab: AppTab) -> Self {
+    pub fn new(
+        theme: crate::config::Theme,
+        active_tab: AppTab,
+        auto_dj_strategy: Option<AutoDjStrategy>,
+        scan_progress: Option<ScanProgress>,
+        notice: Option<String>,
+    ) -> Self {
         Self {
             theme,
             active_tab,
+            auto_dj_strategy,
+            scan_progress,
+            notice,
         }
     }
 }
@@ -71,5 +85,28 @@ impl Widget for TopBar {
 
         let clock = Line::from(time_format()).alignment(Alignment::Center);
         clock.render(area, buf);
+
+        let mut status: Vec<String> = self.notice.into_iter().collect();
+
+        if let Some(progress) = self.scan_progress {
+            // The whole path rarely fits, and the file name is what tells songs apart
+            let file_name = progress
+                .current_path
+                .as_ref()
+                .and_then(|p| p.file_name())
+                .map(|f| format!(" {}", f.to_string_lossy()))
+                .unwrap_or_default();
+
+            status.push(format!("scanning {}/{}{}", progress.scanned, progress.total, file_name));
+        }
+
+        if let Some(strategy) = self.auto_dj_strategy {
+            status.push(format!("auto-DJ: {}", strategy.name()));
+        }
+
+        if !status.is_empty() {
+            let status = Line::from(format!("{} ", status.join(" · "))).alignment(Alignment::Right);
+            status.render(area, buf);
+        }
     }
 }